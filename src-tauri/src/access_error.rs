@@ -0,0 +1,52 @@
+use std::{io, path::Path};
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// ファイル/ディレクトリにアクセス出来なかった理由。
+/// フロントエンドでは `kind` で判別し、ログに表示する。
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind")]
+pub enum AccessError {
+  NotFound {
+    path: String,
+  },
+  PermissionDenied {
+    path: String,
+  },
+  NotADirectory {
+    path: String,
+  },
+  AlreadyExists {
+    path: String,
+  },
+  /// 上記以外の OS のエラー(ネットワークパスのタイムアウト等)
+  Os {
+    path: String,
+    os_error_kind: String,
+    os_error_code: Option<i32>,
+    message: String,
+  },
+  PaneNotFound {
+    pane_idx: usize,
+  },
+}
+
+impl AccessError {
+  pub fn from_io_error(
+    path: &Path,
+    error: &io::Error,
+  ) -> Self {
+    let path = path.to_string_lossy().to_string();
+    match error.kind() {
+      io::ErrorKind::NotFound => AccessError::NotFound { path },
+      io::ErrorKind::PermissionDenied => AccessError::PermissionDenied { path },
+      io::ErrorKind::NotADirectory => AccessError::NotADirectory { path },
+      io::ErrorKind::AlreadyExists => AccessError::AlreadyExists { path },
+      kind => AccessError::Os {
+        path,
+        os_error_kind: format!("{:?}", kind),
+        os_error_code: error.raw_os_error(),
+        message: error.to_string(),
+      },
+    }
+  }
+}
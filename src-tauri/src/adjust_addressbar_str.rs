@@ -1,9 +1,10 @@
-use dirs;
 use std::{
   env, fs,
   path::PathBuf,
 };
 
+use crate::access_error::AccessError;
+
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AdjustedAddressbarStr {
@@ -11,7 +12,7 @@ pub struct AdjustedAddressbarStr {
   file_name: String,
 }
 #[tauri::command]
-pub fn adjust_addressbar_str(str: &str) -> Result<AdjustedAddressbarStr, String> {
+pub fn adjust_addressbar_str(str: &str) -> Result<AdjustedAddressbarStr, AccessError> {
   let str = &str.trim();
   if str.is_empty() {
     return Ok(AdjustedAddressbarStr {
@@ -24,13 +25,13 @@ pub fn adjust_addressbar_str(str: &str) -> Result<AdjustedAddressbarStr, String>
   let path = PathBuf::from(path);
   let path = resolve_home_dir(path);
 
-  let Ok(file_info) = fs::metadata(&path) else {
-    return Err("unfond".to_string());
-  };
+  let file_info = fs::metadata(&path).map_err(|error| AccessError::from_io_error(&path, &error))?;
 
   if file_info.is_file() {
     let Some(parent) = path.parent() else {
-      return Err("unfond".to_string());
+      return Err(AccessError::NotADirectory {
+        path: path.to_string_lossy().to_string(),
+      });
     };
     return Ok(AdjustedAddressbarStr {
       dir: parent.as_os_str().to_str().unwrap_or_default().to_string(),
@@ -50,7 +51,10 @@ pub fn adjust_addressbar_str(str: &str) -> Result<AdjustedAddressbarStr, String>
     });
   }
 
-  return Err("unfond".to_string());
+  // デバイスファイル等
+  Err(AccessError::NotADirectory {
+    path: path.to_string_lossy().to_string(),
+  })
 }
 
 
@@ -1,6 +1,8 @@
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 use std::process::Command;
+#[cfg(windows)]
 use std::os::windows::process::CommandExt;
+#[cfg(windows)]
 use windows::Win32::System::Threading::CREATE_NO_WINDOW;
 
 use tauri::{AppHandle, Emitter};
@@ -10,12 +12,20 @@ use crate::pane_info::update_file_list;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LogInfo {
-  title: String,
-  id: String,
-  command: String,
-  stdout: String,
-  stderr: String,
-  rc: Option<i32>,
+  pub(crate) title: String,
+  pub(crate) id: String,
+  pub(crate) command: String,
+  pub(crate) stdout: String,
+  pub(crate) stderr: String,
+  pub(crate) rc: Option<i32>,
+}
+
+/// 同じ `id` で送ると、ログペインの同じ項目が更新される。
+pub(crate) fn emit_log(
+  app_handle: &AppHandle,
+  log_info: &LogInfo,
+) {
+  let _ = app_handle.emit("LogMessageEvent", log_info);
 }
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
@@ -49,7 +59,7 @@ impl Executer {
   fn push_log(
     &self,
     app_handle: &AppHandle,
-  ) -> () {
+  ) {
     let log_info = LogInfo {
       title: self.title.to_string(),
       stdout: self.stdout.to_string(),
@@ -58,35 +68,55 @@ impl Executer {
       command: self.command.to_string(),
       rc: self.return_code,
     };
-    let _ = app_handle.emit("LogMessageEvent", log_info);
+    emit_log(app_handle, &log_info);
   }
 
   fn execute(
     &mut self,
     app_handle: &AppHandle,
   ) -> Option<()> {
-    self.push_log(&app_handle);
+    self.push_log(app_handle);
 
-let output = Command::new("powershell")
-    .args(["-Command", &self.command])
-    .current_dir(&self.dir)
-    .creation_flags(CREATE_NO_WINDOW.0)
-    .output()
-    .ok()?;
+    let output = shell_command(&self.command)
+      .current_dir(&self.dir)
+      .output()
+      .ok()?;
 
-    update_file_list(&app_handle);
+    update_file_list(app_handle);
 
-    let (std_out, _, _) = encoding_rs::SHIFT_JIS.decode(&output.stdout);
-    let (std_err, _, _) = encoding_rs::SHIFT_JIS.decode(&output.stderr);
+    let (std_out, _, _) = CONSOLE_ENCODING.decode(&output.stdout);
+    let (std_err, _, _) = CONSOLE_ENCODING.decode(&output.stderr);
     self.stdout = std_out.to_string();
     self.stderr = std_err.to_string();
     self.return_code = output.status.code();
-    self.push_log(&app_handle);
+    self.push_log(app_handle);
 
     Some(())
   }
 }
 
+///////////////////////////////////////////////////////////////////////////////////////////////////
+#[cfg(windows)]
+const CONSOLE_ENCODING: &encoding_rs::Encoding = encoding_rs::SHIFT_JIS;
+#[cfg(not(windows))]
+const CONSOLE_ENCODING: &encoding_rs::Encoding = encoding_rs::UTF_8;
+
+#[cfg(windows)]
+fn shell_command(command: &str) -> Command {
+  let mut result = Command::new("powershell");
+  result
+    .args(["-Command", command])
+    .creation_flags(CREATE_NO_WINDOW.0);
+  result
+}
+
+#[cfg(not(windows))]
+fn shell_command(command: &str) -> Command {
+  let mut result = Command::new("pwsh");
+  result.args(["-Command", command]);
+  result
+}
+
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 #[tauri::command]
 pub fn execute_shell_command(
@@ -94,7 +124,7 @@ pub fn execute_shell_command(
   title: &str,
   dir: &str,
   command: &str,
-) -> () {
+) {
   let dir = dir.to_owned();
   let command = command.to_owned();
   let title = title.to_owned();
@@ -0,0 +1,525 @@
+use std::{
+  collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+  fs,
+  path::{Path, PathBuf},
+  sync::{
+    mpsc::{self, Receiver, RecvTimeoutError},
+    Arc, Mutex,
+  },
+  time::{Duration, Instant},
+};
+
+use itertools::Itertools;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+
+use crate::pane_info::filter_info::{is_glob_match, matching_rate, FilterInfo};
+use crate::setting_file::{read_setting_file, write_setting_file};
+
+const SETTING_FILE_NAME: &str = "general/file_index_setting.json";
+const INDEX_FILE_NAME: &str = "general/file_index.json";
+
+/// 変更があれば、この間隔で索引を保存する。
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 変更通知をまとめて反映するまでの待ち時間
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 変更が続いても、この時間が経てば反映する。
+const MAX_UPDATE_DELAY: Duration = Duration::from_secs(2);
+
+/// Go to anywhere で返す候補の既定の最大数
+const DEFAULT_CANDIDATE_NUM: usize = 50;
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FileIndexSetting {
+  /// 索引を作るディレクトリ
+  roots: Vec<String>,
+  /// グロブ。名前に一致したファイルとディレクトリ(配下も含む)は索引に入れない。
+  exclude_patterns: Vec<String>,
+}
+
+impl Default for FileIndexSetting {
+  fn default() -> Self {
+    Self {
+      roots: Vec::new(),
+      exclude_patterns: vec![".git".to_owned(), "node_modules".to_owned(), "target".to_owned()],
+    }
+  }
+}
+
+impl FileIndexSetting {
+  fn is_excluded(
+    &self,
+    file_name: &str,
+  ) -> bool {
+    self
+      .exclude_patterns
+      .iter()
+      .filter(|pattern| !pattern.is_empty())
+      .any(|pattern| is_glob_match(pattern, file_name))
+  }
+
+  /// ルートからの途中のディレクトリも含めて、除外対象かを判定する。
+  fn is_excluded_path(
+    &self,
+    root: &Path,
+    path: &Path,
+  ) -> bool {
+    let Ok(relative_path) = path.strip_prefix(root) else {
+      return true;
+    };
+    relative_path
+      .components()
+      .any(|component| self.is_excluded(&component.as_os_str().to_string_lossy()))
+  }
+}
+
+/// ルート毎の、配下の全てのファイルのフルパス。
+/// 検索と保存はロックを外してから行える様に写しを取るので、ルート毎に `Arc` で共有し、変更する時に複製する。
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+struct FileIndex {
+  files: BTreeMap<String, Arc<BTreeSet<String>>>,
+}
+
+struct FileIndexer {
+  setting: FileIndexSetting,
+  index: FileIndex,
+  watcher: Option<RecommendedWatcher>,
+  /// 走査中のルートと、走査中に反映した変更。走査が終わるまでは、前回保存した索引で検索する。
+  /// 走査で読み終えたディレクトリへの変更は走査の結果に入らないので、結果に反映し直す。
+  scanning_roots: HashMap<String, Vec<IndexChanges>>,
+  is_dirty: bool,
+}
+
+static INDEXER: Lazy<Mutex<FileIndexer>> = Lazy::new(|| {
+  let setting = read_setting_file(SETTING_FILE_NAME)
+    .and_then(|content| serde_json::from_str(&content).ok())
+    .unwrap_or_default();
+  let index = read_setting_file(INDEX_FILE_NAME)
+    .and_then(|content| serde_json::from_str(&content).ok())
+    .unwrap_or_default();
+  Mutex::new(FileIndexer {
+    setting,
+    index,
+    watcher: None,
+    scanning_roots: HashMap::new(),
+    is_dirty: false,
+  })
+});
+
+/// 保存の順番待ち。書き出しは索引のロックを外してから行うので、古い写しで上書きしない様に順に行う。
+static INDEX_SAVING: Mutex<()> = Mutex::new(());
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+#[derive(Debug, Serialize, Clone)]
+pub struct GoToCandidate {
+  path: String,
+  dirctry_path: String,
+  file_name: String,
+  /// `path` 中の一致した位置(文字単位)
+  matched_idx_list: Vec<usize>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileIndexStatus {
+  file_num: usize,
+  scanning_root_num: usize,
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// 保存済みの索引を読み込み、ルートの監視と走査し直しを始める。
+pub fn start_file_indexer() {
+  let (sender, receiver) = mpsc::channel();
+  let watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+    let Ok(event) = result else {
+      return;
+    };
+    if matches!(event.kind, EventKind::Access(_)) {
+      return;
+    }
+    let _ = sender.send(event.paths);
+  })
+  .ok();
+
+  let roots = {
+    let mut indexer = INDEXER.lock().unwrap();
+    indexer.watcher = watcher;
+    let roots = indexer.setting.roots.clone();
+    indexer.watch_roots(&[], &roots);
+    roots
+  };
+  std::thread::spawn(move || update_loop(receiver));
+  for root in roots {
+    start_scan(root);
+  }
+}
+
+#[tauri::command]
+pub fn get_file_index_setting() -> FileIndexSetting {
+  INDEXER.lock().unwrap().setting.clone()
+}
+
+/// 外れたルートの索引は捨て、全てのルートを走査し直す。
+#[tauri::command]
+pub fn set_file_index_setting(setting: FileIndexSetting) {
+  let roots = {
+    let mut indexer = INDEXER.lock().unwrap();
+    let prev_roots = std::mem::replace(&mut indexer.setting, setting.clone()).roots;
+    indexer.watch_roots(&prev_roots, &setting.roots);
+    indexer.index.files.retain(|root, _| setting.roots.contains(root));
+    indexer.is_dirty = true;
+    if let Ok(content) = serde_json::to_string_pretty(&setting) {
+      write_setting_file(SETTING_FILE_NAME, &content);
+    }
+    setting.roots
+  };
+  for root in roots {
+    start_scan(root);
+  }
+}
+
+#[tauri::command]
+pub fn rebuild_file_index() {
+  let roots = INDEXER.lock().unwrap().setting.roots.clone();
+  for root in roots {
+    start_scan(root);
+  }
+}
+
+#[tauri::command]
+pub fn get_file_index_status() -> FileIndexStatus {
+  let indexer = INDEXER.lock().unwrap();
+  FileIndexStatus {
+    file_num: indexer.index.files.values().map(|files| files.len()).sum(),
+    scanning_root_num: indexer.scanning_roots.len(),
+  }
+}
+
+/// 索引中の全てのパスを、フィルタの文字列一致と同じ基準で照合し、一致度の高い順に返す。
+/// 一致度が同じなら、パスの短い方を先にする。
+#[tauri::command]
+pub fn go_to_anywhere(
+  query: String,
+  max_num: Option<usize>,
+) -> Vec<GoToCandidate> {
+  if query.is_empty() {
+    return Vec::new();
+  }
+  let filter = FilterInfo::new_str_match(&query);
+  // 照合には時間が掛かるので、写しを取ってロックを外す。
+  let index = INDEXER.lock().unwrap().index.clone();
+  index
+    .files
+    .values()
+    .flat_map(|files| files.iter())
+    .filter_map(|path| {
+      let matched_idx_list = filter.is_file_name_match(path)?;
+      let rate = matching_rate(&Some(matched_idx_list.clone()));
+      Some((path, matched_idx_list, rate))
+    })
+    .sorted_by(|(a_path, _, a_rate), (b_path, _, b_rate)| {
+      b_rate
+        .cmp(a_rate)
+        .then_with(|| a_path.len().cmp(&b_path.len()))
+    })
+    .take(max_num.unwrap_or(DEFAULT_CANDIDATE_NUM))
+    .map(|(path, matched_idx_list, _)| to_candidate(path, matched_idx_list))
+    .collect()
+}
+
+fn to_candidate(
+  path: &str,
+  matched_idx_list: Vec<usize>,
+) -> GoToCandidate {
+  let path_buf = PathBuf::from(path);
+  let dirctry_path = path_buf
+    .parent()
+    .map(|parent| parent.to_string_lossy().to_string())
+    .unwrap_or_default();
+  let file_name = path_buf
+    .file_name()
+    .map(|file_name| file_name.to_string_lossy().to_string())
+    .unwrap_or_default();
+  GoToCandidate {
+    path: path.to_owned(),
+    dirctry_path,
+    file_name,
+    matched_idx_list,
+  }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+impl FileIndexer {
+  fn watch_roots(
+    &mut self,
+    prev_roots: &[String],
+    roots: &[String],
+  ) {
+    let Some(watcher) = &mut self.watcher else {
+      return;
+    };
+    for root in prev_roots.iter().filter(|root| !roots.contains(root)) {
+      let _ = watcher.unwatch(Path::new(root));
+    }
+    for root in roots.iter().filter(|root| !prev_roots.contains(root)) {
+      let _ = watcher.watch(Path::new(root), RecursiveMode::Recursive);
+    }
+  }
+
+  fn root_of(
+    &self,
+    path: &Path,
+  ) -> Option<String> {
+    self
+      .setting
+      .roots
+      .iter()
+      .filter(|root| path.starts_with(root))
+      .max_by_key(|root| root.len())
+      .cloned()
+  }
+
+  fn apply_changes(
+    &mut self,
+    changes: &IndexChanges,
+  ) {
+    for (root, queued) in &mut self.scanning_roots {
+      if changes.affects(root) {
+        queued.push(changes.clone());
+      }
+    }
+    for root in changes.added.keys().filter(|root| self.setting.roots.contains(root)) {
+      self.index.files.entry(root.clone()).or_default();
+    }
+    for (root, files) in &mut self.index.files {
+      if changes.affects(root) {
+        changes.apply_to(root, Arc::make_mut(files));
+        self.is_dirty = true;
+      }
+    }
+  }
+}
+
+/// 写しを取り、ロックを外してから書き出す。
+fn save_index() {
+  let _saving = INDEX_SAVING.lock().unwrap();
+  let index = {
+    let mut indexer = INDEXER.lock().unwrap();
+    indexer.is_dirty = false;
+    indexer.index.clone()
+  };
+  if let Ok(content) = serde_json::to_string(&index) {
+    write_setting_file(INDEX_FILE_NAME, &content);
+  }
+}
+
+/// ルート配下を走査し、終わったら索引を置き換える。
+fn start_scan(root: String) {
+  let setting = {
+    let mut indexer = INDEXER.lock().unwrap();
+    if indexer.scanning_roots.contains_key(&root) {
+      return;
+    }
+    indexer.scanning_roots.insert(root.clone(), Vec::new());
+    indexer.setting.clone()
+  };
+
+  std::thread::spawn(move || {
+    let mut files = BTreeSet::new();
+    collect_files(&setting, Path::new(&root), &mut files);
+
+    {
+      let mut indexer = INDEXER.lock().unwrap();
+      let queued = indexer.scanning_roots.remove(&root).unwrap_or_default();
+      // 走査中に外されたルートは捨てる。
+      if !indexer.setting.roots.contains(&root) {
+        return;
+      }
+      for changes in &queued {
+        changes.apply_to(&root, &mut files);
+      }
+      indexer.index.files.insert(root, Arc::new(files));
+      indexer.is_dirty = true;
+    }
+    save_index();
+  });
+}
+
+/// シンボリックリンクのディレクトリは、循環を避ける為に辿らない。
+fn collect_files(
+  setting: &FileIndexSetting,
+  dir_path: &Path,
+  files: &mut BTreeSet<String>,
+) {
+  let mut stack = vec![dir_path.to_path_buf()];
+  while let Some(dir_path) = stack.pop() {
+    let Ok(read_dir) = fs::read_dir(&dir_path) else {
+      continue;
+    };
+    for entry in read_dir.filter_map(|entry| entry.ok()) {
+      if setting.is_excluded(&entry.file_name().to_string_lossy()) {
+        continue;
+      }
+      let Ok(file_type) = entry.file_type() else {
+        continue;
+      };
+      if file_type.is_dir() {
+        stack.push(entry.path());
+      } else {
+        files.insert(entry.path().to_string_lossy().to_string());
+      }
+    }
+  }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// 変更通知を受けて、変更のあったパスだけを索引に反映する。
+fn update_loop(receiver: Receiver<Vec<PathBuf>>) {
+  let mut pending = HashSet::new();
+  let mut pending_since: Option<Instant> = None;
+  let mut last_save = Instant::now();
+  loop {
+    match receiver.recv_timeout(DEBOUNCE_INTERVAL) {
+      Ok(paths) => {
+        pending.extend(paths);
+        let since = *pending_since.get_or_insert_with(Instant::now);
+        if since.elapsed() < MAX_UPDATE_DELAY {
+          continue;
+        }
+      }
+      Err(RecvTimeoutError::Timeout) => {}
+      Err(RecvTimeoutError::Disconnected) => return,
+    }
+
+    if !pending.is_empty() {
+      apply_changes(std::mem::take(&mut pending));
+      pending_since = None;
+    }
+    if INDEXER.lock().unwrap().is_dirty && last_save.elapsed() >= SAVE_INTERVAL {
+      save_index();
+      last_save = Instant::now();
+    }
+  }
+}
+
+/// 変更のあったパスを調べる間は、ロックを外す。
+fn apply_changes(changed_paths: HashSet<PathBuf>) {
+  let (setting, changed_paths) = {
+    let indexer = INDEXER.lock().unwrap();
+    let changed_paths = changed_paths
+      .into_iter()
+      .filter_map(|path| Some((indexer.root_of(&path)?, path)))
+      .collect_vec();
+    (indexer.setting.clone(), changed_paths)
+  };
+  let changes = IndexChanges::collect(&setting, changed_paths);
+  if !changes.is_empty() {
+    INDEXER.lock().unwrap().apply_changes(&changes);
+  }
+}
+
+/// 変更通知のあったパスを調べた結果
+#[derive(Debug, Default, Clone)]
+struct IndexChanges {
+  /// ルート毎の、増えたファイル
+  added: BTreeMap<String, BTreeSet<String>>,
+  /// 消えたパス。ディレクトリなら配下のファイルも消す。
+  removed: HashSet<String>,
+}
+
+impl IndexChanges {
+  fn collect(
+    setting: &FileIndexSetting,
+    changed_paths: Vec<(String, PathBuf)>,
+  ) -> IndexChanges {
+    let mut changes = IndexChanges::default();
+    for (root, path) in changed_paths {
+      if setting.is_excluded_path(Path::new(&root), &path) {
+        continue;
+      }
+      match fs::symlink_metadata(&path) {
+        Ok(meta_data) if meta_data.is_dir() => collect_files(setting, &path, changes.added.entry(root).or_default()),
+        Ok(_) => {
+          changes.added.entry(root).or_default().insert(path.to_string_lossy().to_string());
+        }
+        Err(_) => {
+          changes.removed.insert(path.to_string_lossy().to_string());
+        }
+      }
+    }
+    changes
+  }
+
+  fn is_empty(&self) -> bool {
+    self.added.values().all(BTreeSet::is_empty) && self.removed.is_empty()
+  }
+
+  fn affects(
+    &self,
+    root: &str,
+  ) -> bool {
+    self.added.get(root).is_some_and(|added| !added.is_empty())
+      || self.removed.iter().any(|path| Path::new(path).starts_with(root))
+  }
+
+  fn apply_to(
+    &self,
+    root: &str,
+    files: &mut BTreeSet<String>,
+  ) {
+    if let Some(added) = self.added.get(root) {
+      files.extend(added.iter().cloned());
+    }
+    if self.removed.is_empty() {
+      return;
+    }
+    // 消えたディレクトリは、配下のファイルもまとめて消す。
+    let removed_dir_prefixes = self
+      .removed
+      .iter()
+      .map(|path| format!("{}{}", path, std::path::MAIN_SEPARATOR))
+      .collect_vec();
+    files.retain(|file| {
+      !self.removed.contains(file) && !removed_dir_prefixes.iter().any(|prefix| file.starts_with(prefix))
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempdir::TempDir;
+
+  #[test]
+  fn changes_replayed_on_scan_result_add_new_files_and_drop_removed_directories() {
+    let work_dir = TempDir::new("file_index_test").unwrap();
+    let root = work_dir.path().to_string_lossy().to_string();
+    let setting = FileIndexSetting::default();
+    let dir = work_dir.path().join("dir");
+    fs::create_dir(&dir).unwrap();
+    fs::write(dir.join("a.txt"), "").unwrap();
+    let mut files = BTreeSet::new();
+    collect_files(&setting, work_dir.path(), &mut files);
+
+    // 走査の後で増えたファイルと、消えたディレクトリ
+    fs::write(work_dir.path().join("new.txt"), "").unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    let changes = IndexChanges::collect(
+      &setting,
+      vec![
+        (root.clone(), work_dir.path().join("new.txt")),
+        (root.clone(), dir.clone()),
+        (root.clone(), work_dir.path().join("target")),
+      ],
+    );
+    assert!(changes.affects(&root));
+    changes.apply_to(&root, &mut files);
+
+    assert_eq!(
+      files.into_iter().collect_vec(),
+      vec![work_dir.path().join("new.txt").to_string_lossy().to_string()]
+    );
+  }
+}
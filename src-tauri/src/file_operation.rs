@@ -0,0 +1,322 @@
+use std::{
+  fs::{self, OpenOptions},
+  io,
+  path::{Component, Path, PathBuf},
+};
+
+use tauri::AppHandle;
+use uuid::Uuid;
+
+use crate::access_error::AccessError;
+use crate::execute_shell_command::{emit_log, LogInfo};
+use crate::pane_info::update_file_list;
+
+pub mod conflict;
+use conflict::ConflictPolicy;
+pub mod job_queue;
+use job_queue::enqueue;
+pub mod journal;
+use journal::JournalItem;
+mod transfer;
+pub mod trash;
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// 選択項目に対するファイル操作。`item_names` は `src_dir` 直下の名前。
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum FileOperation {
+  Copy {
+    src_dir: String,
+    item_names: Vec<String>,
+    dst_dir: String,
+    #[serde(default)]
+    conflict_policy: ConflictPolicy,
+  },
+  Move {
+    src_dir: String,
+    item_names: Vec<String>,
+    dst_dir: String,
+    #[serde(default)]
+    conflict_policy: ConflictPolicy,
+  },
+  /// ゴミ箱を使わずに消す。
+  Delete {
+    src_dir: String,
+    item_names: Vec<String>,
+  },
+  Trash {
+    src_dir: String,
+    item_names: Vec<String>,
+  },
+}
+
+impl FileOperation {
+  fn title(&self) -> &'static str {
+    match self {
+      FileOperation::Copy { .. } => "Copy",
+      FileOperation::Move { .. } => "Move",
+      FileOperation::Delete { .. } => "Delete",
+      FileOperation::Trash { .. } => "Trash",
+    }
+  }
+
+  /// ログの「コマンド」欄に出す内容
+  fn description(&self) -> String {
+    match self {
+      FileOperation::Copy {
+        src_dir,
+        item_names,
+        dst_dir,
+        ..
+      }
+      | FileOperation::Move {
+        src_dir,
+        item_names,
+        dst_dir,
+        ..
+      } => format!("{} {} -> {}", src_dir, item_names.join(", "), dst_dir),
+      FileOperation::Delete { src_dir, item_names } | FileOperation::Trash { src_dir, item_names } => {
+        format!("{} {}", src_dir, item_names.join(", "))
+      }
+    }
+  }
+
+  fn src_dir(&self) -> PathBuf {
+    match self {
+      FileOperation::Copy { src_dir, .. }
+      | FileOperation::Move { src_dir, .. }
+      | FileOperation::Delete { src_dir, .. }
+      | FileOperation::Trash { src_dir, .. } => PathBuf::from(src_dir),
+    }
+  }
+
+  fn item_names(&self) -> &[String] {
+    match self {
+      FileOperation::Copy { item_names, .. }
+      | FileOperation::Move { item_names, .. }
+      | FileOperation::Delete { item_names, .. }
+      | FileOperation::Trash { item_names, .. } => item_names,
+    }
+  }
+
+  fn dst_dir(&self) -> Option<PathBuf> {
+    match self {
+      FileOperation::Copy { dst_dir, .. } | FileOperation::Move { dst_dir, .. } => Some(PathBuf::from(dst_dir)),
+      FileOperation::Delete { .. } | FileOperation::Trash { .. } => None,
+    }
+  }
+
+  fn conflict_policy(&self) -> ConflictPolicy {
+    match self {
+      FileOperation::Copy { conflict_policy, .. } | FileOperation::Move { conflict_policy, .. } => *conflict_policy,
+      FileOperation::Delete { .. } | FileOperation::Trash { .. } => ConflictPolicy::default(),
+    }
+  }
+
+  /// 書き込む先。削除とゴミ箱への移動では削除元。
+  fn target_dir(&self) -> PathBuf {
+    self.dst_dir().unwrap_or_else(|| self.src_dir())
+  }
+}
+
+/// ディレクトリ直下の 1 項目を指す名前か。
+/// 空、`.`、`..`、区切りを含む名前は、ディレクトリ自体や別の場所を指してしまう。
+fn is_valid_item_name(item_name: &str) -> bool {
+  let mut components = Path::new(item_name).components();
+  let is_single_name = matches!((components.next(), components.next()), (Some(Component::Normal(_)), None));
+  is_single_name && !item_name.contains(['/', std::path::MAIN_SEPARATOR])
+}
+
+fn invalid_item_name_error() -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidInput, "invalid item name")
+}
+
+/// `file_operation_progress` イベントで送る進捗。
+/// ファイル数とバイト数は、ディレクトリ配下を展開した後の合計。
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct FileOperationProgress {
+  id: String,
+  title: String,
+  current_file: Option<String>,
+  done_file_num: u64,
+  total_file_num: u64,
+  done_bytes: u64,
+  total_bytes: u64,
+  error_num: usize,
+  is_finished: bool,
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// ジョブとして順番待ちに入れ、ジョブの ID を返す。
+/// 書き込み先に同じ名前がある時は `conflict_policy` に従う。省略したら聞く。
+#[tauri::command]
+pub fn copy_items(
+  app_handle: AppHandle,
+  src_dir: String,
+  item_names: Vec<String>,
+  dst_dir: String,
+  conflict_policy: Option<ConflictPolicy>,
+) -> String {
+  enqueue(
+    &app_handle,
+    FileOperation::Copy {
+      src_dir,
+      item_names,
+      dst_dir,
+      conflict_policy: conflict_policy.unwrap_or_default(),
+    },
+  )
+}
+
+#[tauri::command]
+pub fn move_items(
+  app_handle: AppHandle,
+  src_dir: String,
+  item_names: Vec<String>,
+  dst_dir: String,
+  conflict_policy: Option<ConflictPolicy>,
+) -> String {
+  enqueue(
+    &app_handle,
+    FileOperation::Move {
+      src_dir,
+      item_names,
+      dst_dir,
+      conflict_policy: conflict_policy.unwrap_or_default(),
+    },
+  )
+}
+
+/// ゴミ箱を使わずに削除する。
+#[tauri::command]
+pub fn delete_items(
+  app_handle: AppHandle,
+  src_dir: String,
+  item_names: Vec<String>,
+) -> String {
+  enqueue(&app_handle, FileOperation::Delete { src_dir, item_names })
+}
+
+/// ゴミ箱に移す。元に戻すには `restore_trash_items` を使う。
+#[tauri::command]
+pub fn trash_items(
+  app_handle: AppHandle,
+  src_dir: String,
+  item_names: Vec<String>,
+) -> String {
+  enqueue(&app_handle, FileOperation::Trash { src_dir, item_names })
+}
+
+/// 名前を変える。`dst_names` は `src_names` と同じ順。
+/// 変更後の名前が既にある項目は変えない。失敗した項目があっても、残りは続ける。
+#[tauri::command]
+pub fn rename_items(
+  app_handle: AppHandle,
+  dir: String,
+  src_names: Vec<String>,
+  dst_names: Vec<String>,
+) -> Result<(), AccessError> {
+  let dir = Path::new(&dir);
+  let mut errors = ItemErrors::default();
+  let mut journal_items = Vec::new();
+  for (src_name, dst_name) in src_names.iter().zip(&dst_names) {
+    if src_name == dst_name {
+      continue;
+    }
+    let src = dir.join(src_name);
+    let dst = dir.join(dst_name);
+    if let Some(invalid_path) = [(src_name, &src), (dst_name, &dst)]
+      .into_iter()
+      .find_map(|(name, path)| (!is_valid_item_name(name)).then_some(path))
+    {
+      errors.push(invalid_path, &invalid_item_name_error());
+      continue;
+    }
+    if fs::symlink_metadata(&dst).is_ok() {
+      errors.push(&dst, &io::Error::from(io::ErrorKind::AlreadyExists));
+      continue;
+    }
+    match fs::rename(&src, &dst) {
+      Ok(()) => journal_items.extend(JournalItem::moved(&src, &dst)),
+      Err(error) => errors.push(&src, &error),
+    }
+  }
+  let description = format!("{} {} -> {}", dir.to_string_lossy(), src_names.join(", "), dst_names.join(", "));
+  journal::record("Rename", description.clone(), journal_items);
+  update_file_list(&app_handle);
+  errors.finish(&app_handle, "Rename", description)
+}
+
+/// 空のファイル(`is_dir` ならディレクトリ)を作る。既にある名前は作らない。
+#[tauri::command]
+pub fn create_items(
+  app_handle: AppHandle,
+  dir: String,
+  names: Vec<String>,
+  is_dir: bool,
+) -> Result<(), AccessError> {
+  let dir = Path::new(&dir);
+  let mut errors = ItemErrors::default();
+  let mut journal_items = Vec::new();
+  for name in names.iter().filter(|name| !name.is_empty()) {
+    let path = dir.join(name);
+    if !is_valid_item_name(name) {
+      errors.push(&path, &invalid_item_name_error());
+      continue;
+    }
+    let created = if is_dir {
+      fs::create_dir(&path)
+    } else {
+      OpenOptions::new().write(true).create_new(true).open(&path).map(|_| ())
+    };
+    match created {
+      Ok(()) => journal_items.extend(JournalItem::created(&path)),
+      Err(error) => errors.push(&path, &error),
+    }
+  }
+  let description = format!("{} {}", dir.to_string_lossy(), names.join(", "));
+  journal::record("Create", description.clone(), journal_items);
+  update_file_list(&app_handle);
+  errors.finish(&app_handle, "Create", description)
+}
+
+/// ジョブを通さない操作で、失敗した項目。
+/// 最初のエラーだけをコマンドの結果で返し、全ての項目はログペインに送る。
+#[derive(Default)]
+struct ItemErrors {
+  first: Option<AccessError>,
+  messages: Vec<String>,
+}
+
+impl ItemErrors {
+  fn push(
+    &mut self,
+    path: &Path,
+    error: &io::Error,
+  ) {
+    self.first.get_or_insert_with(|| AccessError::from_io_error(path, error));
+    self.messages.push(format!("{}: {}", path.to_string_lossy(), error));
+  }
+
+  fn finish(
+    self,
+    app_handle: &AppHandle,
+    title: &str,
+    description: String,
+  ) -> Result<(), AccessError> {
+    let Some(first) = self.first else {
+      return Ok(());
+    };
+    emit_log(
+      app_handle,
+      &LogInfo {
+        title: title.to_owned(),
+        id: Uuid::new_v4().to_string(),
+        command: description,
+        stdout: "".to_owned(),
+        stderr: self.messages.join("\n"),
+        rc: Some(1),
+      },
+    );
+    Err(first)
+  }
+}
@@ -0,0 +1,360 @@
+use std::{
+  collections::HashMap,
+  fs,
+  path::{Path, PathBuf},
+  sync::{mpsc, Mutex},
+  time::{Duration, SystemTime},
+};
+
+use chrono::{DateTime, Local};
+use once_cell::sync::Lazy;
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+use super::job_queue::JobControl;
+
+/// 回答待ちの間に、中断を確認する間隔
+const ANSWER_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// コピー先(移動先)に同じ名前の物がある時の扱い
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum ConflictPolicy {
+  /// 衝突する度に `file_conflict` イベントで聞く。
+  #[default]
+  Ask,
+  Overwrite,
+  Skip,
+  /// `name(2).ext` の様に、空いている名前で書き込む。
+  Rename,
+  /// コピー元の方が新しい時だけ上書きし、それ以外は飛ばす。
+  OverwriteIfNewer,
+  /// 大きさが同じなら飛ばし、違えば上書きする。
+  CompareSize,
+}
+
+/// 衝突毎の決定。ディレクトリ同士の上書きは、中身の統合になる。
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ConflictResolution {
+  Overwrite,
+  Skip,
+  Rename,
+}
+
+impl ConflictResolution {
+  fn to_policy(self) -> ConflictPolicy {
+    match self {
+      ConflictResolution::Overwrite => ConflictPolicy::Overwrite,
+      ConflictResolution::Skip => ConflictPolicy::Skip,
+      ConflictResolution::Rename => ConflictPolicy::Rename,
+    }
+  }
+}
+
+/// `file_conflict` イベントで送る、衝突した 2 つの情報。
+#[derive(Debug, Serialize, Clone)]
+pub struct FileConflictInfo {
+  conflict_id: String,
+  job_id: String,
+  src_path: String,
+  dst_path: String,
+  src_is_dir: bool,
+  dst_is_dir: bool,
+  src_size: u64,
+  dst_size: u64,
+  src_modified: Option<String>,
+  dst_modified: Option<String>,
+}
+
+#[derive(Debug)]
+struct ConflictAnswer {
+  resolution: ConflictResolution,
+  apply_to_all: bool,
+}
+
+/// 回答待ちの衝突と、回答の送り先
+static PENDING_CONFLICTS: Lazy<Mutex<HashMap<String, mpsc::Sender<ConflictAnswer>>>> =
+  Lazy::new(|| Mutex::new(HashMap::new()));
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// `apply_to_all` なら、同じジョブの残りの衝突にも同じ決定を使う。
+/// 既に回答済み(ジョブの中断を含む)の衝突への回答は無視する。
+#[tauri::command]
+pub fn answer_file_conflict(
+  conflict_id: String,
+  resolution: ConflictResolution,
+  apply_to_all: bool,
+) {
+  if let Some(sender) = PENDING_CONFLICTS.lock().unwrap().get(&conflict_id) {
+    let _ = sender.send(ConflictAnswer {
+      resolution,
+      apply_to_all,
+    });
+  }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// ジョブ毎の衝突の扱い。「全てに適用」された決定は、以降の方針になる。
+/// `app_handle` が None なら、聞く時にイベントを送らない(テスト用)。
+pub(super) struct ConflictResolver<'a> {
+  app_handle: Option<&'a AppHandle>,
+  job_id: &'a str,
+  control: &'a JobControl,
+  policy: ConflictPolicy,
+}
+
+impl<'a> ConflictResolver<'a> {
+  pub(super) fn new(
+    app_handle: Option<&'a AppHandle>,
+    job_id: &'a str,
+    control: &'a JobControl,
+    policy: ConflictPolicy,
+  ) -> Self {
+    ConflictResolver {
+      app_handle,
+      job_id,
+      control,
+      policy,
+    }
+  }
+
+  /// `dst` が既にある時に呼ぶ。ディレクトリ同士は、日時や大きさを比べずに上書き(統合)にする。
+  /// 回答待ちの間に中断されたら、飛ばす。
+  pub(super) fn resolve(
+    &mut self,
+    src: &Path,
+    dst: &Path,
+  ) -> ConflictResolution {
+    let src_meta_data = fs::symlink_metadata(src).ok();
+    let dst_meta_data = fs::symlink_metadata(dst).ok();
+    let is_dir_pair = src_meta_data.as_ref().is_some_and(|meta_data| meta_data.is_dir())
+      && dst_meta_data.as_ref().is_some_and(|meta_data| meta_data.is_dir());
+    match self.policy {
+      ConflictPolicy::Ask => self.ask(src, dst),
+      ConflictPolicy::Overwrite => ConflictResolution::Overwrite,
+      ConflictPolicy::Skip => ConflictResolution::Skip,
+      ConflictPolicy::Rename => ConflictResolution::Rename,
+      ConflictPolicy::OverwriteIfNewer => {
+        let modified = |meta_data: &Option<fs::Metadata>| meta_data.as_ref().and_then(|meta_data| meta_data.modified().ok());
+        let is_newer = match (modified(&src_meta_data), modified(&dst_meta_data)) {
+          (Some(src_modified), Some(dst_modified)) => src_modified > dst_modified,
+          _ => false,
+        };
+        if is_dir_pair || is_newer {
+          ConflictResolution::Overwrite
+        } else {
+          ConflictResolution::Skip
+        }
+      }
+      ConflictPolicy::CompareSize => {
+        let size = |meta_data: &Option<fs::Metadata>| meta_data.as_ref().map(|meta_data| meta_data.len());
+        if !is_dir_pair && size(&src_meta_data) == size(&dst_meta_data) {
+          ConflictResolution::Skip
+        } else {
+          ConflictResolution::Overwrite
+        }
+      }
+    }
+  }
+
+  fn ask(
+    &mut self,
+    src: &Path,
+    dst: &Path,
+  ) -> ConflictResolution {
+    let conflict_id = Uuid::new_v4().to_string();
+    let (sender, receiver) = mpsc::channel();
+    PENDING_CONFLICTS.lock().unwrap().insert(conflict_id.clone(), sender);
+
+    let src_meta_data = fs::symlink_metadata(src).ok();
+    let dst_meta_data = fs::symlink_metadata(dst).ok();
+    let info = FileConflictInfo {
+      conflict_id: conflict_id.clone(),
+      job_id: self.job_id.to_owned(),
+      src_path: src.to_string_lossy().to_string(),
+      dst_path: dst.to_string_lossy().to_string(),
+      src_is_dir: src_meta_data.as_ref().is_some_and(|meta_data| meta_data.is_dir()),
+      dst_is_dir: dst_meta_data.as_ref().is_some_and(|meta_data| meta_data.is_dir()),
+      src_size: src_meta_data.as_ref().map_or(0, |meta_data| meta_data.len()),
+      dst_size: dst_meta_data.as_ref().map_or(0, |meta_data| meta_data.len()),
+      src_modified: src_meta_data.and_then(|meta_data| meta_data.modified().ok()).map(format_time),
+      dst_modified: dst_meta_data.and_then(|meta_data| meta_data.modified().ok()).map(format_time),
+    };
+    if let Some(app_handle) = self.app_handle {
+      let _ = app_handle.emit("file_conflict", &info);
+    }
+
+    let answer = loop {
+      if self.control.is_cancelled() {
+        break None;
+      }
+      match receiver.recv_timeout(ANSWER_CHECK_INTERVAL) {
+        Ok(answer) => break Some(answer),
+        Err(mpsc::RecvTimeoutError::Timeout) => continue,
+        Err(mpsc::RecvTimeoutError::Disconnected) => break None,
+      }
+    };
+    PENDING_CONFLICTS.lock().unwrap().remove(&conflict_id);
+    if let Some(app_handle) = self.app_handle {
+      let _ = app_handle.emit("file_conflict_closed", &conflict_id);
+    }
+
+    let Some(answer) = answer else {
+      return ConflictResolution::Skip;
+    };
+    if answer.apply_to_all {
+      self.policy = answer.resolution.to_policy();
+    }
+    answer.resolution
+  }
+}
+
+fn format_time(time: SystemTime) -> String {
+  let local_time: DateTime<Local> = time.into();
+  local_time.format("%Y/%m/%d %H:%M:%S").to_string()
+}
+
+/// `name.ext` なら `name(2).ext`, `name(3).ext` … の内、まだ無い物を返す。ディレクトリは拡張子を区別しない。
+pub(super) fn unused_path_of(
+  path: &Path,
+  is_dir: bool,
+) -> PathBuf {
+  let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+  (2..)
+    .map(|number| path.with_file_name(numbered_name(&file_name, number, is_dir)))
+    .find(|candidate| fs::symlink_metadata(candidate).is_err())
+    .unwrap()
+}
+
+/// `name.ext` の拡張子の前に `(number)` を付ける。
+pub(super) fn numbered_name(
+  file_name: &str,
+  number: usize,
+  is_dir: bool,
+) -> String {
+  let path = Path::new(file_name);
+  match (is_dir, path.file_stem(), path.extension()) {
+    (false, Some(stem), Some(extension)) => format!(
+      "{}({}).{}",
+      stem.to_string_lossy(),
+      number,
+      extension.to_string_lossy()
+    ),
+    _ => format!("{}({})", file_name, number),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempdir::TempDir;
+
+  fn write_file(
+    path: &Path,
+    content: &str,
+    modified: SystemTime,
+  ) {
+    fs::write(path, content).unwrap();
+    fs::File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+  }
+
+  fn resolve(
+    policy: ConflictPolicy,
+    src: &Path,
+    dst: &Path,
+  ) -> ConflictResolution {
+    let control = JobControl::default();
+    ConflictResolver::new(None, "test", &control, policy).resolve(src, dst)
+  }
+
+  #[test]
+  fn numbered_name_puts_number_before_last_extension() {
+    assert_eq!(numbered_name("name.txt", 2, false), "name(2).txt");
+    assert_eq!(numbered_name("archive.tar.gz", 3, false), "archive.tar(3).gz");
+    assert_eq!(numbered_name("name", 2, false), "name(2)");
+  }
+
+  #[test]
+  fn numbered_name_appends_number_to_dotfiles_and_directories() {
+    assert_eq!(numbered_name(".bashrc", 2, false), ".bashrc(2)");
+    assert_eq!(numbered_name("my.folder", 2, true), "my.folder(2)");
+    assert_eq!(numbered_name(".config", 2, true), ".config(2)");
+  }
+
+  #[test]
+  fn unused_path_of_skips_existing_names() {
+    let work_dir = TempDir::new("conflict_test").unwrap();
+    fs::write(work_dir.path().join("a.txt"), "").unwrap();
+    fs::write(work_dir.path().join("a(2).txt"), "").unwrap();
+    fs::create_dir(work_dir.path().join("d.dir")).unwrap();
+
+    assert_eq!(
+      unused_path_of(&work_dir.path().join("a.txt"), false),
+      work_dir.path().join("a(3).txt")
+    );
+    assert_eq!(
+      unused_path_of(&work_dir.path().join("d.dir"), true),
+      work_dir.path().join("d.dir(2)")
+    );
+  }
+
+  #[test]
+  fn overwrite_if_newer_overwrites_only_older_files() {
+    let work_dir = TempDir::new("conflict_test").unwrap();
+    let old = work_dir.path().join("old.txt");
+    let new = work_dir.path().join("new.txt");
+    let same = work_dir.path().join("same.txt");
+    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    write_file(&old, "old", time);
+    write_file(&new, "new", time + Duration::from_secs(60));
+    write_file(&same, "same", time);
+
+    assert_eq!(resolve(ConflictPolicy::OverwriteIfNewer, &new, &old), ConflictResolution::Overwrite);
+    assert_eq!(resolve(ConflictPolicy::OverwriteIfNewer, &old, &new), ConflictResolution::Skip);
+    assert_eq!(resolve(ConflictPolicy::OverwriteIfNewer, &same, &old), ConflictResolution::Skip);
+  }
+
+  #[test]
+  fn compare_size_skips_only_files_of_same_size() {
+    let work_dir = TempDir::new("conflict_test").unwrap();
+    let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let a = work_dir.path().join("a.txt");
+    let b = work_dir.path().join("b.txt");
+    let longer = work_dir.path().join("longer.txt");
+    write_file(&a, "abc", time);
+    write_file(&b, "xyz", time);
+    write_file(&longer, "abcdef", time);
+
+    assert_eq!(resolve(ConflictPolicy::CompareSize, &a, &b), ConflictResolution::Skip);
+    assert_eq!(resolve(ConflictPolicy::CompareSize, &a, &longer), ConflictResolution::Overwrite);
+  }
+
+  #[test]
+  fn directory_pairs_are_merged_regardless_of_time_and_size() {
+    let work_dir = TempDir::new("conflict_test").unwrap();
+    let src = work_dir.path().join("src");
+    let dst = work_dir.path().join("dst");
+    fs::create_dir(&src).unwrap();
+    fs::create_dir(&dst).unwrap();
+
+    assert_eq!(resolve(ConflictPolicy::OverwriteIfNewer, &src, &dst), ConflictResolution::Overwrite);
+    assert_eq!(resolve(ConflictPolicy::CompareSize, &src, &dst), ConflictResolution::Overwrite);
+  }
+
+  #[test]
+  fn ask_skips_when_job_is_cancelled() {
+    let work_dir = TempDir::new("conflict_test").unwrap();
+    let src = work_dir.path().join("src.txt");
+    let dst = work_dir.path().join("dst.txt");
+    fs::write(&src, "src").unwrap();
+    fs::write(&dst, "dst").unwrap();
+    let control = JobControl::default();
+    control.cancel();
+    let mut resolver = ConflictResolver::new(None, "test", &control, ConflictPolicy::Ask);
+
+    assert_eq!(resolver.resolve(&src, &dst), ConflictResolution::Skip);
+    // 中断しても、方針は聞くままにする。
+    assert_eq!(resolver.policy, ConflictPolicy::Ask);
+    assert!(PENDING_CONFLICTS.lock().unwrap().is_empty());
+  }
+}
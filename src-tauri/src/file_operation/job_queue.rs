@@ -0,0 +1,302 @@
+use std::{
+  path::Path,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+  },
+};
+
+use once_cell::sync::Lazy;
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+use super::{transfer::run_operation, FileOperation};
+use crate::execute_shell_command::{emit_log, LogInfo};
+
+/// 同じデバイスに対して、同時に実行するジョブの数
+const MAX_RUNNING_JOB_NUM_PER_DEVICE: usize = 1;
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// 実行中のジョブへの指示。処理側はファイル毎(大きいファイルは読み込み毎)に確認する。
+#[derive(Debug, Default)]
+pub(super) struct JobControl {
+  paused: AtomicBool,
+  cancelled: AtomicBool,
+}
+
+impl JobControl {
+  pub(super) fn is_paused(&self) -> bool {
+    self.paused.load(Ordering::Relaxed)
+  }
+
+  pub(super) fn is_cancelled(&self) -> bool {
+    self.cancelled.load(Ordering::Relaxed)
+  }
+
+  pub(super) fn cancel(&self) {
+    self.cancelled.store(true, Ordering::Relaxed);
+  }
+}
+
+/// ジョブを操作出来なかった理由。フロントエンドでは `kind` で判別する。
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind")]
+pub enum JobError {
+  /// 終了済み、または存在しないジョブ
+  JobNotFound {
+    job_id: String,
+  },
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+pub enum JobState {
+  Queued,
+  Running,
+  /// 一時停止中。実行を始めていたジョブは、デバイスの実行枠を持ったままになる。
+  Paused,
+}
+
+#[derive(Debug)]
+struct Job {
+  id: String,
+  operation: FileOperation,
+  /// 書き込み先(削除は削除元)のデバイス
+  device: String,
+  state: JobState,
+  is_started: bool,
+  control: Arc<JobControl>,
+}
+
+/// 投入順。終わったジョブは取り除く。
+static JOBS: Lazy<Mutex<Vec<Job>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileJobInfo {
+  id: String,
+  title: String,
+  description: String,
+  state: JobState,
+}
+
+impl Job {
+  fn to_info(&self) -> FileJobInfo {
+    FileJobInfo {
+      id: self.id.clone(),
+      title: self.operation.title().to_owned(),
+      description: self.operation.description(),
+      state: self.state,
+    }
+  }
+
+  /// 実行前のジョブの状態をログペインに出す。実行を始めたら、ジョブ自身が進捗と一緒に出す。
+  fn emit_waiting_log(
+    &self,
+    app_handle: &AppHandle,
+  ) {
+    if self.is_started {
+      return;
+    }
+    let state = match self.state {
+      JobState::Queued => "queued",
+      JobState::Running => return,
+      JobState::Paused => "paused",
+    };
+    let log_info = LogInfo {
+      title: format!("{} ({})", self.operation.title(), state),
+      id: self.id.clone(),
+      command: self.operation.description(),
+      stdout: "".to_owned(),
+      stderr: "".to_owned(),
+      rc: None,
+    };
+    emit_log(app_handle, &log_info);
+  }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+#[tauri::command]
+pub fn list_file_jobs() -> Vec<FileJobInfo> {
+  JOBS.lock().unwrap().iter().map(Job::to_info).collect()
+}
+
+#[tauri::command]
+pub fn pause_file_job(
+  app_handle: AppHandle,
+  job_id: String,
+) -> Result<(), JobError> {
+  update_job(&app_handle, &job_id, |job| {
+    job.control.paused.store(true, Ordering::Relaxed);
+    job.state = JobState::Paused;
+  })
+}
+
+#[tauri::command]
+pub fn resume_file_job(
+  app_handle: AppHandle,
+  job_id: String,
+) -> Result<(), JobError> {
+  update_job(&app_handle, &job_id, |job| {
+    job.control.paused.store(false, Ordering::Relaxed);
+    job.state = if job.is_started { JobState::Running } else { JobState::Queued };
+  })?;
+  schedule(&app_handle);
+  Ok(())
+}
+
+/// 実行中のジョブは、処理中のファイルを消してから止まる。終わっていた分はそのまま残る。
+#[tauri::command]
+pub fn cancel_file_job(
+  app_handle: AppHandle,
+  job_id: String,
+) -> Result<(), JobError> {
+  let mut jobs = JOBS.lock().unwrap();
+  let Some(job_idx) = jobs.iter().position(|job| job.id == job_id) else {
+    return Err(JobError::JobNotFound { job_id });
+  };
+  jobs[job_idx].control.cancel();
+  if jobs[job_idx].is_started {
+    return Ok(());
+  }
+
+  let job = jobs.remove(job_idx);
+  emit_log(
+    &app_handle,
+    &LogInfo {
+      title: format!("{} (cancelled)", job.operation.title()),
+      id: job.id.clone(),
+      command: job.operation.description(),
+      stdout: "".to_owned(),
+      stderr: "".to_owned(),
+      rc: None,
+    },
+  );
+  emit_job_list(&app_handle, &jobs);
+  Ok(())
+}
+
+/// ジョブの ID を返す。進捗とログは、この ID で送られる。
+pub(super) fn enqueue(
+  app_handle: &AppHandle,
+  operation: FileOperation,
+) -> String {
+  let device = device_of(&operation.target_dir());
+  let job = Job {
+    id: Uuid::new_v4().to_string(),
+    operation,
+    device,
+    state: JobState::Queued,
+    is_started: false,
+    control: Arc::new(JobControl::default()),
+  };
+  let id = job.id.clone();
+  job.emit_waiting_log(app_handle);
+  JOBS.lock().unwrap().push(job);
+  schedule(app_handle);
+  id
+}
+
+fn update_job(
+  app_handle: &AppHandle,
+  job_id: &str,
+  modify: impl FnOnce(&mut Job),
+) -> Result<(), JobError> {
+  let mut jobs = JOBS.lock().unwrap();
+  let Some(job) = jobs.iter_mut().find(|job| job.id == job_id) else {
+    return Err(JobError::JobNotFound {
+      job_id: job_id.to_owned(),
+    });
+  };
+  modify(job);
+  job.emit_waiting_log(app_handle);
+  emit_job_list(app_handle, &jobs);
+  Ok(())
+}
+
+fn emit_job_list(
+  app_handle: &AppHandle,
+  jobs: &[Job],
+) {
+  let job_list = jobs.iter().map(Job::to_info).collect::<Vec<_>>();
+  let _ = app_handle.emit("file_jobs_updated", job_list);
+}
+
+/// デバイス毎の実行枠に空きがあれば、待っているジョブを投入順に始める。
+/// スレッドはロックを外してから作る。
+fn schedule(app_handle: &AppHandle) {
+  let mut jobs = JOBS.lock().unwrap();
+  let mut started = Vec::new();
+  for job_idx in 0..jobs.len() {
+    if jobs[job_idx].state != JobState::Queued {
+      continue;
+    }
+    let device = &jobs[job_idx].device;
+    let running_num = jobs
+      .iter()
+      .filter(|job| job.is_started && &job.device == device)
+      .count();
+    if running_num >= MAX_RUNNING_JOB_NUM_PER_DEVICE {
+      continue;
+    }
+
+    let job = &mut jobs[job_idx];
+    job.state = JobState::Running;
+    job.is_started = true;
+    started.push((job.id.clone(), job.operation.clone(), job.control.clone()));
+  }
+  emit_job_list(app_handle, &jobs);
+  drop(jobs);
+
+  for (id, operation, control) in started {
+    let app_handle = app_handle.clone();
+    std::thread::spawn(move || {
+      run_operation(&app_handle, &id, &operation, &control);
+      finish(&app_handle, &id);
+    });
+  }
+}
+
+fn finish(
+  app_handle: &AppHandle,
+  job_id: &str,
+) {
+  JOBS.lock().unwrap().retain(|job| job.id != job_id);
+  schedule(app_handle);
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// まだ無いパスは、ある所まで遡った祖先のデバイスにする。
+#[cfg(unix)]
+fn device_of(path: &Path) -> String {
+  use std::os::unix::fs::MetadataExt;
+  path
+    .ancestors()
+    .find_map(|ancestor| std::fs::metadata(ancestor).ok())
+    .map(|meta_data| meta_data.dev().to_string())
+    .unwrap_or_default()
+}
+
+/// ドライブ(UNC パスなら共有)単位で扱う。
+#[cfg(windows)]
+fn device_of(path: &Path) -> String {
+  path
+    .components()
+    .next()
+    .map(|component| component.as_os_str().to_string_lossy().to_uppercase())
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempdir::TempDir;
+
+  #[cfg(unix)]
+  #[test]
+  fn device_of_missing_path_is_device_of_nearest_ancestor() {
+    let work_dir = TempDir::new("job_queue_test").unwrap();
+    let device = device_of(work_dir.path());
+
+    assert!(!device.is_empty());
+    assert_eq!(device_of(&work_dir.path().join("missing").join("deeper")), device);
+  }
+}
@@ -0,0 +1,381 @@
+use std::{
+  fs,
+  path::Path,
+  sync::Mutex,
+  time::UNIX_EPOCH,
+};
+
+use once_cell::sync::Lazy;
+use tauri::AppHandle;
+
+use super::transfer::{is_cross_device, move_without_progress};
+use super::trash::{is_trash_supported, move_to_trash, restore};
+use crate::access_error::AccessError;
+use crate::pane_info::update_file_list;
+use crate::setting_file::{read_setting_file, write_setting_file};
+
+const JOURNAL_FILE_NAME: &str = "general/file_operation_journal.json";
+
+/// 取り消せる操作の数。古い物から捨てる。
+const MAX_JOURNAL_ENTRY_NUM: usize = 100;
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// 取り消し、やり直しを断った理由。フロントエンドでは `kind` で判別する。
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind")]
+pub enum JournalError {
+  NothingToUndo,
+  NothingToRedo,
+  /// 記録した後に、変更、移動、削除された
+  ChangedSinceOperation {
+    path: String,
+  },
+  /// 移す時のエラー。`AccessError` のまま送る。
+  #[serde(untagged)]
+  Access(AccessError),
+}
+
+impl From<AccessError> for JournalError {
+  fn from(error: AccessError) -> Self {
+    JournalError::Access(error)
+  }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// 項目の置き場所
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+enum Location {
+  Path(String),
+  /// ゴミ箱の中。作成とコピーの操作前は、まだどこにも無いので None。
+  Trash(Option<String>),
+}
+
+impl Location {
+  fn path(&self) -> Option<&String> {
+    match self {
+      Location::Path(path) | Location::Trash(Some(path)) => Some(path),
+      Location::Trash(None) => None,
+    }
+  }
+}
+
+/// 記録した時の状態。取り消しとやり直しの前に比べ、変わっていたら断る。
+/// 名前の変更やゴミ箱への移動では変わらない物だけを使う。
+/// ディレクトリは自身の情報だけで、中身の変更までは見ない。取り消しとやり直しは丸ごと移すだけなので、
+/// 中身が変わっていても失われることはない。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Fingerprint {
+  is_dir: bool,
+  size: u64,
+  /// UNIX 時間(ナノ秒)
+  modified: Option<u128>,
+}
+
+impl Fingerprint {
+  fn of(path: &Path) -> Option<Fingerprint> {
+    let meta_data = fs::symlink_metadata(path).ok()?;
+    Some(Fingerprint {
+      is_dir: meta_data.is_dir(),
+      size: if meta_data.is_dir() { 0 } else { meta_data.len() },
+      modified: meta_data
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos()),
+    })
+  }
+}
+
+/// 操作した 1 項目。取り消しは `after` から `before` へ、やり直しは `before` から `after` へ移す。
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub(super) struct JournalItem {
+  before: Location,
+  after: Location,
+  fingerprint: Fingerprint,
+}
+
+impl JournalItem {
+  /// 名前の変更と移動
+  pub(super) fn moved(
+    from: &Path,
+    to: &Path,
+  ) -> Option<JournalItem> {
+    Some(JournalItem {
+      before: Location::Path(from.to_string_lossy().to_string()),
+      after: Location::Path(to.to_string_lossy().to_string()),
+      fingerprint: Fingerprint::of(to)?,
+    })
+  }
+
+  /// 作成とコピー。取り消すとゴミ箱に移す。
+  /// ゴミ箱の無い環境では取り消せないので、記録しない。
+  pub(super) fn created(path: &Path) -> Option<JournalItem> {
+    if !is_trash_supported() {
+      return None;
+    }
+    Some(JournalItem {
+      before: Location::Trash(None),
+      after: Location::Path(path.to_string_lossy().to_string()),
+      fingerprint: Fingerprint::of(path)?,
+    })
+  }
+
+  pub(super) fn trashed(
+    original_path: &Path,
+    trashed_path: &Path,
+  ) -> Option<JournalItem> {
+    Some(JournalItem {
+      before: Location::Path(original_path.to_string_lossy().to_string()),
+      after: Location::Trash(Some(trashed_path.to_string_lossy().to_string())),
+      fingerprint: Fingerprint::of(trashed_path)?,
+    })
+  }
+
+  fn sides(
+    &self,
+    is_undo: bool,
+  ) -> (&Location, &Location) {
+    if is_undo {
+      (&self.after, &self.before)
+    } else {
+      (&self.before, &self.after)
+    }
+  }
+
+  /// 今の場所にあるのが記録した物のままで、移す先が空いていることを確かめる。
+  fn check(
+    &self,
+    is_undo: bool,
+  ) -> Result<(), JournalError> {
+    let (current, target) = self.sides(is_undo);
+    // まだどこにも無い物は移せない。エラーには、もう一方の場所を出す。
+    let Some(current_path) = current.path() else {
+      return Err(JournalError::ChangedSinceOperation {
+        path: target.path().cloned().unwrap_or_default(),
+      });
+    };
+    if Fingerprint::of(Path::new(current_path)).as_ref() != Some(&self.fingerprint) {
+      return Err(JournalError::ChangedSinceOperation {
+        path: current_path.clone(),
+      });
+    }
+    if let Location::Path(target_path) = target {
+      if fs::symlink_metadata(target_path).is_ok() {
+        return Err(
+          AccessError::AlreadyExists {
+            path: target_path.clone(),
+          }
+          .into(),
+        );
+      }
+    }
+    Ok(())
+  }
+
+  /// 移した先がゴミ箱なら、ゴミ箱の中の場所を覚えておく。
+  fn transfer(
+    &mut self,
+    is_undo: bool,
+  ) -> Result<(), JournalError> {
+    let (current, target) = self.sides(is_undo);
+    let new_target = match (current, target) {
+      // デバイスを跨いで名前の変更で移せない時だけ、コピーしてから元を消す。
+      (Location::Path(from), Location::Path(to)) => {
+        fs::rename(from, to)
+          .or_else(|error| {
+            if is_cross_device(&error) {
+              move_without_progress(Path::new(from), Path::new(to))
+            } else {
+              Err(error)
+            }
+          })
+          .map_err(|error| AccessError::from_io_error(Path::new(from), &error))?;
+        target.clone()
+      }
+      (Location::Path(from), Location::Trash(_)) => {
+        let trashed_path = move_to_trash(Path::new(from)).map_err(|error| AccessError::from_io_error(Path::new(from), &error))?;
+        Location::Trash(Some(trashed_path.to_string_lossy().to_string()))
+      }
+      (Location::Trash(Some(trashed_path)), Location::Path(_)) => {
+        restore(Path::new(trashed_path))?;
+        target.clone()
+      }
+      (Location::Trash(_), _) => {
+        return Err(JournalError::ChangedSinceOperation {
+          path: current.path().or(target.path()).cloned().unwrap_or_default(),
+        });
+      }
+    };
+    // コピーで移すと、ディレクトリの更新日時は変わる。
+    if let Some(path) = new_target.path() {
+      if let Some(fingerprint) = Fingerprint::of(Path::new(path)) {
+        self.fingerprint = fingerprint;
+      }
+    }
+    if is_undo {
+      self.before = new_target;
+    } else {
+      self.after = new_target;
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct JournalEntry {
+  title: String,
+  description: String,
+  items: Vec<JournalItem>,
+}
+
+impl JournalEntry {
+  /// 全ての項目を確かめてから移す。途中で失敗したら、移した分を戻す。
+  fn apply(
+    &mut self,
+    is_undo: bool,
+  ) -> Result<(), JournalError> {
+    let order = |len: usize| -> Vec<usize> {
+      if is_undo {
+        (0..len).rev().collect()
+      } else {
+        (0..len).collect()
+      }
+    };
+    for &idx in &order(self.items.len()) {
+      self.items[idx].check(is_undo)?;
+    }
+    let mut done: Vec<usize> = Vec::new();
+    for idx in order(self.items.len()) {
+      if let Err(error) = self.items[idx].transfer(is_undo) {
+        for &done_idx in done.iter().rev() {
+          let _ = self.items[done_idx].transfer(!is_undo);
+        }
+        return Err(error);
+      }
+      done.push(idx);
+    }
+    Ok(())
+  }
+}
+
+/// 設定ディレクトリに保存する、取り消しとやり直しの履歴。新しい物が末尾。
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct Journal {
+  undo_entries: Vec<JournalEntry>,
+  redo_entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+  /// `is_undo` なら取り消しの履歴、そうでなければやり直しの履歴。
+  fn entries_mut(
+    &mut self,
+    is_undo: bool,
+  ) -> &mut Vec<JournalEntry> {
+    if is_undo {
+      &mut self.undo_entries
+    } else {
+      &mut self.redo_entries
+    }
+  }
+
+  fn save(&self) {
+    if let Ok(content) = serde_json::to_string(self) {
+      write_setting_file(JOURNAL_FILE_NAME, &content);
+    }
+  }
+}
+
+static JOURNAL: Lazy<Mutex<Journal>> = Lazy::new(|| {
+  let journal = read_setting_file(JOURNAL_FILE_NAME)
+    .and_then(|content| serde_json::from_str(&content).ok())
+    .unwrap_or_default();
+  Mutex::new(journal)
+});
+
+/// 取り消せる項目が無ければ記録しない。新しい操作を記録したら、やり直しの履歴は捨てる。
+pub(super) fn record(
+  title: &str,
+  description: String,
+  items: Vec<JournalItem>,
+) {
+  if items.is_empty() {
+    return;
+  }
+  let mut journal = JOURNAL.lock().unwrap();
+  journal.undo_entries.push(JournalEntry {
+    title: title.to_owned(),
+    description,
+    items,
+  });
+  let over_num = journal.undo_entries.len().saturating_sub(MAX_JOURNAL_ENTRY_NUM);
+  journal.undo_entries.drain(..over_num);
+  journal.redo_entries.clear();
+  journal.save();
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// 取り消しとやり直しの結果
+#[derive(Debug, Serialize, Clone)]
+pub struct JournalApplied {
+  title: String,
+  description: String,
+}
+
+/// 最後の操作を取り消す。記録した後に変更された物があれば、何もせずにエラーにする。
+#[tauri::command]
+pub fn undo_file_operation(app_handle: AppHandle) -> Result<JournalApplied, JournalError> {
+  apply_last(&app_handle, true)
+}
+
+/// 最後に取り消した操作をやり直す。
+#[tauri::command]
+pub fn redo_file_operation(app_handle: AppHandle) -> Result<JournalApplied, JournalError> {
+  apply_last(&app_handle, false)
+}
+
+fn apply_last(
+  app_handle: &AppHandle,
+  is_undo: bool,
+) -> Result<JournalApplied, JournalError> {
+  // 移している間に記録されると、やり直しの履歴が古い物になるので、終わるまでロックしておく。
+  // 記録はその間待つ。
+  let mut journal = JOURNAL.lock().unwrap();
+  let Some(mut entry) = journal.entries_mut(is_undo).pop() else {
+    return Err(if is_undo { JournalError::NothingToUndo } else { JournalError::NothingToRedo });
+  };
+  let result = entry.apply(is_undo);
+
+  // 断った操作は、そのまま残しておく。
+  if let Err(error) = result {
+    journal.entries_mut(is_undo).push(entry);
+    return Err(error);
+  }
+  let applied = JournalApplied {
+    title: entry.title.clone(),
+    description: entry.description.clone(),
+  };
+  journal.entries_mut(!is_undo).push(entry);
+  journal.save();
+  drop(journal);
+  update_file_list(app_handle);
+  Ok(applied)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn journal_error_sends_access_error_as_is() {
+    let error = JournalError::from(AccessError::NotFound { path: "/a".to_owned() });
+    assert_eq!(
+      serde_json::to_value(&error).unwrap(),
+      serde_json::json!({ "kind": "NotFound", "path": "/a" })
+    );
+    assert_eq!(
+      serde_json::to_value(&JournalError::NothingToUndo).unwrap(),
+      serde_json::json!({ "kind": "NothingToUndo" })
+    );
+  }
+}
@@ -0,0 +1,972 @@
+use std::{
+  collections::HashMap,
+  fs::{self, OpenOptions},
+  io::{self, Read, Write},
+  path::{Path, PathBuf},
+  time::{Duration, Instant},
+};
+
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+use super::{
+  conflict::{unused_path_of, ConflictResolution, ConflictResolver},
+  invalid_item_name_error, is_valid_item_name,
+  job_queue::JobControl,
+  journal::{self, JournalItem},
+  trash::{is_trash_supported, move_to_trash},
+  FileOperation, FileOperationProgress,
+};
+use crate::execute_shell_command::{emit_log, LogInfo};
+use crate::pane_info::update_file_list;
+
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// 進捗を送る最短の間隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 一時停止中に、再開と中断を確認する間隔
+const PAUSE_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EntryKind {
+  Directory,
+  File,
+  Symlink,
+}
+
+/// 項目を展開した、個々のファイルとディレクトリ。
+#[derive(Debug)]
+struct PlanEntry {
+  /// 項目からの相対パス。項目自体は空。
+  relative: PathBuf,
+  kind: EntryKind,
+  size: u64,
+}
+
+/// 項目毎の展開結果。親ディレクトリが子より先に並ぶ。
+#[derive(Debug)]
+struct ItemPlan {
+  src: PathBuf,
+  dst: Option<PathBuf>,
+  entries: Vec<PlanEntry>,
+}
+
+/// 空の相対パスを `join` すると末尾に区切りが付くので、項目自体はそのまま返す。
+fn path_of(
+  base: &Path,
+  relative: &Path,
+) -> PathBuf {
+  if relative.as_os_str().is_empty() {
+    return base.to_path_buf();
+  }
+  base.join(relative)
+}
+
+/// シンボリックリンクは辿らず、リンク自体を対象にする。
+fn plan_entries(src: &Path) -> io::Result<Vec<PlanEntry>> {
+  let mut result = Vec::new();
+  let mut stack = vec![PathBuf::new()];
+  while let Some(relative) = stack.pop() {
+    let path = path_of(src, &relative);
+    let meta_data = fs::symlink_metadata(&path)?;
+    let file_type = meta_data.file_type();
+    let kind = if file_type.is_symlink() {
+      EntryKind::Symlink
+    } else if file_type.is_dir() {
+      EntryKind::Directory
+    } else {
+      EntryKind::File
+    };
+    if kind == EntryKind::Directory {
+      for entry in fs::read_dir(&path)? {
+        stack.push(relative.join(entry?.file_name()));
+      }
+    }
+    let size = if kind == EntryKind::File { meta_data.len() } else { 0 };
+    result.push(PlanEntry { relative, kind, size });
+  }
+  Ok(result)
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// 進捗をイベントで、経過と項目毎のエラーをログペインに送る。
+/// `app_handle` が None なら送らずに数えるだけ(テスト用)。
+struct Reporter<'a> {
+  app_handle: Option<&'a AppHandle>,
+  control: &'a JobControl,
+  log_info: LogInfo,
+  progress: FileOperationProgress,
+  errors: Vec<String>,
+  /// 衝突で飛ばした物
+  skipped: Vec<String>,
+  last_emit: Instant,
+}
+
+impl<'a> Reporter<'a> {
+  fn new(
+    app_handle: Option<&'a AppHandle>,
+    control: &'a JobControl,
+    id: &str,
+    operation: &FileOperation,
+  ) -> Self {
+    let title = operation.title().to_owned();
+    Reporter {
+      app_handle,
+      control,
+      log_info: LogInfo {
+        title: title.clone(),
+        id: id.to_owned(),
+        command: operation.description(),
+        stdout: "".to_owned(),
+        stderr: "".to_owned(),
+        rc: None,
+      },
+      progress: FileOperationProgress {
+        id: id.to_owned(),
+        title,
+        ..Default::default()
+      },
+      errors: Vec::new(),
+      skipped: Vec::new(),
+      last_emit: Instant::now(),
+    }
+  }
+
+  fn set_total(
+    &mut self,
+    plans: &[ItemPlan],
+  ) {
+    let entries = plans.iter().flat_map(|plan| &plan.entries);
+    self.progress.total_file_num = entries.clone().filter(|entry| entry.kind != EntryKind::Directory).count() as u64;
+    self.progress.total_bytes = entries.map(|entry| entry.size).sum();
+    self.emit(true);
+  }
+
+  fn start_file(
+    &mut self,
+    path: &Path,
+  ) {
+    self.progress.current_file = Some(path.to_string_lossy().to_string());
+    self.emit(false);
+  }
+
+  fn add_bytes(
+    &mut self,
+    bytes: u64,
+  ) {
+    self.progress.done_bytes += bytes;
+    self.emit(false);
+  }
+
+  fn finish_entry(
+    &mut self,
+    entry: &PlanEntry,
+    count_bytes: bool,
+  ) {
+    if entry.kind != EntryKind::Directory {
+      self.progress.done_file_num += 1;
+    }
+    if count_bytes {
+      self.progress.done_bytes += entry.size;
+    }
+    self.emit(false);
+  }
+
+  /// 一時停止中は再開まで待つ。中断されたら false を返す。
+  fn wait_if_paused(&mut self) -> bool {
+    if self.control.is_paused() && !self.control.is_cancelled() {
+      let title = std::mem::take(&mut self.log_info.title);
+      self.log_info.title = format!("{} (paused)", title);
+      self.emit(true);
+      while self.control.is_paused() && !self.control.is_cancelled() {
+        std::thread::sleep(PAUSE_CHECK_INTERVAL);
+      }
+      self.log_info.title = title;
+      self.emit(true);
+    }
+    !self.control.is_cancelled()
+  }
+
+  /// 飛ばした物の配下も、終わった分として数える。
+  fn skip<'b>(
+    &mut self,
+    path: &Path,
+    entries: impl IntoIterator<Item = &'b PlanEntry>,
+  ) {
+    self.skipped.push(path.to_string_lossy().to_string());
+    for entry in entries {
+      self.finish_entry(entry, true);
+    }
+    self.emit(true);
+  }
+
+  fn error(
+    &mut self,
+    path: &Path,
+    error: &io::Error,
+  ) {
+    // 中断で止めた物は、エラーとして扱わない。
+    if self.control.is_cancelled() && error.kind() == io::ErrorKind::Interrupted {
+      return;
+    }
+    self.operation_error(&format!("{}: {}", path.to_string_lossy(), error));
+  }
+
+  /// 項目に依らない、操作全体のエラー
+  fn operation_error(
+    &mut self,
+    message: &str,
+  ) {
+    self.errors.push(message.to_owned());
+    self.progress.error_num = self.errors.len();
+    self.emit(true);
+  }
+
+  fn emit(
+    &mut self,
+    force: bool,
+  ) {
+    if !force && self.last_emit.elapsed() < PROGRESS_INTERVAL {
+      return;
+    }
+    self.last_emit = Instant::now();
+
+    let progress = &self.progress;
+    self.log_info.stdout = format!(
+      "{} / {} files, {} / {}{}{}",
+      progress.done_file_num,
+      progress.total_file_num,
+      format_bytes(progress.done_bytes),
+      format_bytes(progress.total_bytes),
+      progress
+        .current_file
+        .as_ref()
+        .map(|current_file| format!("\n{}", current_file))
+        .unwrap_or_default(),
+      self
+        .skipped
+        .iter()
+        .map(|skipped| format!("\nskipped: {}", skipped))
+        .collect::<String>(),
+    );
+    self.log_info.stderr = self.errors.join("\n");
+    if let Some(app_handle) = self.app_handle {
+      let _ = app_handle.emit("file_operation_progress", &self.progress);
+      emit_log(app_handle, &self.log_info);
+    }
+  }
+
+  fn finish(mut self) {
+    if self.control.is_cancelled() {
+      self.log_info.title = format!("{} (cancelled)", self.log_info.title);
+    }
+    self.progress.current_file = None;
+    self.progress.is_finished = true;
+    self.log_info.rc = Some(if self.errors.is_empty() { 0 } else { 1 });
+    self.emit(true);
+  }
+}
+
+fn format_bytes(bytes: u64) -> String {
+  const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+  let mut value = bytes as f64;
+  let mut unit_idx = 0;
+  while value >= 1024.0 && unit_idx < UNITS.len() - 1 {
+    value /= 1024.0;
+    unit_idx += 1;
+  }
+  if unit_idx == 0 {
+    return format!("{} {}", bytes, UNITS[0]);
+  }
+  format!("{:.1} {}", value, UNITS[unit_idx])
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// 項目毎に処理し、失敗した項目があっても残りは続ける。
+/// 最後まで済んだ項目は、取り消せる様に記録する。上書きと統合は元に戻せないので記録しない。
+pub(super) fn run_operation(
+  app_handle: &AppHandle,
+  id: &str,
+  operation: &FileOperation,
+  control: &JobControl,
+) {
+  let mut reporter = Reporter::new(Some(app_handle), control, id, operation);
+  let mut resolver = ConflictResolver::new(Some(app_handle), id, control, operation.conflict_policy());
+
+  // 項目毎に同じエラーを並べず、1 つにまとめる。
+  if matches!(operation, FileOperation::Trash { .. }) && !is_trash_supported() {
+    reporter.operation_error("Trash is not supported on this platform. Use 'Delete permanently' instead.");
+    reporter.finish();
+    return;
+  }
+
+  let src_dir = operation.src_dir();
+  let dst_dir = operation.dst_dir();
+  let mut plans = Vec::new();
+  for item_name in operation.item_names() {
+    if control.is_cancelled() {
+      break;
+    }
+    let src = src_dir.join(item_name);
+    if !is_valid_item_name(item_name) {
+      reporter.error(&src, &invalid_item_name_error());
+      continue;
+    }
+    let dst = dst_dir.as_ref().map(|dst_dir| dst_dir.join(item_name));
+    if let Some(dst) = &dst {
+      if is_into_itself(&src, dst) {
+        let error = io::Error::new(io::ErrorKind::InvalidInput, "cannot copy or move a directory into itself");
+        reporter.error(&src, &error);
+        continue;
+      }
+    }
+    match plan_entries(&src) {
+      Ok(entries) => plans.push(ItemPlan { src, dst, entries }),
+      Err(error) => reporter.error(&src, &error),
+    }
+  }
+  reporter.set_total(&plans);
+
+  let mut journal_items = Vec::new();
+  for plan in &plans {
+    if !reporter.wait_if_paused() {
+      break;
+    }
+    let journal_item = match (operation, &plan.dst) {
+      (FileOperation::Copy { .. }, Some(dst)) => {
+        item_target(&mut reporter, &mut resolver, plan, dst).and_then(|target| {
+          let not_copied = copy_item(&mut reporter, &mut resolver, plan, &target.dst, target.overwrite);
+          let is_completed = not_copied.is_empty() && !control.is_cancelled();
+          (is_completed && !target.overwrite && !target.merge)
+            .then(|| JournalItem::created(&target.dst))
+            .flatten()
+        })
+      }
+      (FileOperation::Move { .. }, Some(dst)) => move_item(&mut reporter, &mut resolver, plan, dst),
+      (FileOperation::Trash { .. }, _) => trash_item(&mut reporter, plan),
+      _ => {
+        delete_item(&mut reporter, plan, true, &[]);
+        None
+      }
+    };
+    journal_items.extend(journal_item);
+  }
+  journal::record(operation.title(), operation.description(), journal_items);
+
+  update_file_list(app_handle);
+  reporter.finish();
+}
+
+/// ディレクトリを、自身やその配下へはコピーも移動も出来ない。
+fn is_into_itself(
+  src: &Path,
+  dst: &Path,
+) -> bool {
+  dst != src && dst.starts_with(src)
+}
+
+/// 項目の書き込み先
+struct ItemTarget {
+  dst: PathBuf,
+  /// 既にあるファイルを置き換える。
+  overwrite: bool,
+  /// 既にあるディレクトリに中身を足す。
+  merge: bool,
+}
+
+/// 書き込み先に同じ名前があれば、衝突の扱いに従って決める。飛ばす時とエラーの時は None を返す。
+fn item_target(
+  reporter: &mut Reporter,
+  resolver: &mut ConflictResolver,
+  plan: &ItemPlan,
+  dst: &Path,
+) -> Option<ItemTarget> {
+  let new_target = |dst: PathBuf| ItemTarget {
+    dst,
+    overwrite: false,
+    merge: false,
+  };
+  let Ok(dst_meta_data) = fs::symlink_metadata(dst) else {
+    return Some(new_target(dst.to_path_buf()));
+  };
+  let src_is_dir = plan.entries[0].kind == EntryKind::Directory;
+  let dst_is_dir = dst_meta_data.is_dir();
+  match resolver.resolve(&plan.src, dst) {
+    ConflictResolution::Skip => {
+      reporter.skip(&plan.src, &plan.entries);
+      None
+    }
+    ConflictResolution::Rename => Some(new_target(unused_path_of(dst, src_is_dir))),
+    ConflictResolution::Overwrite if dst == plan.src => {
+      let error = io::Error::new(io::ErrorKind::InvalidInput, "source and destination are the same");
+      reporter.error(dst, &error);
+      None
+    }
+    ConflictResolution::Overwrite if src_is_dir != dst_is_dir => {
+      let error = io::Error::new(io::ErrorKind::AlreadyExists, "cannot overwrite a directory with a file or vice versa");
+      reporter.error(dst, &error);
+      None
+    }
+    ConflictResolution::Overwrite => Some(ItemTarget {
+      dst: dst.to_path_buf(),
+      overwrite: !src_is_dir,
+      merge: src_is_dir,
+    }),
+  }
+}
+
+/// コピーしなかった物(飛ばした物と失敗した物)の相対パスを返す。
+/// ディレクトリは既にあれば中身を足し、作れなかったディレクトリの配下は飛ばす。
+/// 配下のファイルが衝突したら、衝突の扱いに従う。
+fn copy_item<'a>(
+  reporter: &mut Reporter,
+  resolver: &mut ConflictResolver,
+  plan: &'a ItemPlan,
+  dst_root: &Path,
+  overwrite_root: bool,
+) -> Vec<&'a Path> {
+  let mut not_copied: Vec<&Path> = Vec::new();
+  // 改名して書き込んだディレクトリの配下は、改名後の名前の下に書き込む。
+  let mut dst_dirs: HashMap<&Path, PathBuf> = HashMap::new();
+  for entry in &plan.entries {
+    if !reporter.wait_if_paused() {
+      break;
+    }
+    if not_copied.iter().any(|not_copied| entry.relative.starts_with(not_copied)) {
+      continue;
+    }
+    let src = path_of(&plan.src, &entry.relative);
+    let mut dst = match (entry.relative.parent(), entry.relative.file_name()) {
+      (Some(parent), Some(name)) => {
+        let Some(parent_dst) = dst_dirs.get(parent) else {
+          continue;
+        };
+        parent_dst.join(name)
+      }
+      _ => dst_root.to_path_buf(),
+    };
+    reporter.start_file(&src);
+
+    let mut overwrite = overwrite_root && entry.relative.as_os_str().is_empty();
+    if let (false, Ok(dst_meta_data)) = (overwrite, fs::symlink_metadata(&dst)) {
+      let is_dir = entry.kind == EntryKind::Directory;
+      if is_dir && dst_meta_data.is_dir() {
+        dst_dirs.insert(&entry.relative, dst);
+        reporter.finish_entry(entry, false);
+        continue;
+      }
+      match resolver.resolve(&src, &dst) {
+        ConflictResolution::Skip => {
+          let entries = plan.entries.iter().filter(|child| child.relative.starts_with(&entry.relative));
+          reporter.skip(&src, entries);
+          not_copied.push(&entry.relative);
+          continue;
+        }
+        ConflictResolution::Rename => dst = unused_path_of(&dst, is_dir),
+        ConflictResolution::Overwrite if is_dir || dst_meta_data.is_dir() => {
+          let error = io::Error::new(io::ErrorKind::AlreadyExists, "cannot overwrite a directory with a file or vice versa");
+          reporter.error(&dst, &error);
+          not_copied.push(&entry.relative);
+          continue;
+        }
+        ConflictResolution::Overwrite => overwrite = true,
+      }
+    }
+
+    let result = match entry.kind {
+      EntryKind::Directory => fs::create_dir(&dst),
+      EntryKind::File => copy_file(&src, &dst, overwrite, |written_size| {
+        reporter.add_bytes(written_size);
+        reporter.wait_if_paused()
+      }),
+      EntryKind::Symlink => copy_symlink(&src, &dst, overwrite),
+    };
+    match result {
+      Ok(()) => {
+        if entry.kind == EntryKind::Directory {
+          dst_dirs.insert(&entry.relative, dst);
+        }
+        reporter.finish_entry(entry, false);
+      }
+      Err(error) => {
+        reporter.error(&src, &error);
+        not_copied.push(&entry.relative);
+      }
+    }
+  }
+  not_copied
+}
+
+/// 上書きする時は、隣の一時ファイルに書き終えてから置き換える。途中で失敗しても、元のファイルは残る。
+/// 書く度に `on_written` に大きさを渡し、false が返ったら中断する。
+fn copy_file(
+  src: &Path,
+  dst: &Path,
+  overwrite: bool,
+  mut on_written: impl FnMut(u64) -> bool,
+) -> io::Result<()> {
+  let mut src_file = fs::File::open(src)?;
+  let meta_data = src_file.metadata()?;
+  let write_path = if overwrite {
+    let file_name = dst.file_name().unwrap_or_default().to_string_lossy();
+    dst.with_file_name(format!(".{}.{}.tmp", file_name, Uuid::new_v4().simple()))
+  } else {
+    dst.to_path_buf()
+  };
+  let mut dst_file = OpenOptions::new().write(true).create_new(true).open(&write_path)?;
+
+  let mut buffer = vec![0; COPY_BUFFER_SIZE];
+  let mut copy_content = || -> io::Result<()> {
+    loop {
+      let read_size = src_file.read(&mut buffer)?;
+      if read_size == 0 {
+        return Ok(());
+      }
+      dst_file.write_all(&buffer[..read_size])?;
+      if !on_written(read_size as u64) {
+        return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+      }
+    }
+  };
+  if let Err(error) = copy_content() {
+    // 途中までのファイルは残さない。
+    drop(dst_file);
+    let _ = fs::remove_file(&write_path);
+    return Err(error);
+  }
+
+  if let Ok(modified) = meta_data.modified() {
+    let _ = dst_file.set_modified(modified);
+  }
+  drop(dst_file);
+  let result = fs::set_permissions(&write_path, meta_data.permissions()).and_then(|_| {
+    if overwrite {
+      fs::rename(&write_path, dst)
+    } else {
+      Ok(())
+    }
+  });
+  if overwrite && result.is_err() {
+    let _ = fs::remove_file(&write_path);
+  }
+  result
+}
+
+#[cfg(unix)]
+fn copy_symlink(
+  src: &Path,
+  dst: &Path,
+  overwrite: bool,
+) -> io::Result<()> {
+  let target = fs::read_link(src)?;
+  if overwrite {
+    fs::remove_file(dst)?;
+  }
+  std::os::unix::fs::symlink(target, dst)
+}
+
+#[cfg(windows)]
+fn copy_symlink(
+  src: &Path,
+  dst: &Path,
+  overwrite: bool,
+) -> io::Result<()> {
+  let target = fs::read_link(src)?;
+  if overwrite {
+    fs::remove_file(dst).or_else(|_| fs::remove_dir(dst))?;
+  }
+  if fs::metadata(src).is_ok_and(|meta_data| meta_data.is_dir()) {
+    std::os::windows::fs::symlink_dir(target, dst)
+  } else {
+    std::os::windows::fs::symlink_file(target, dst)
+  }
+}
+
+/// 同じデバイス内なら名前の変更で済ませ、出来なければコピーしてから元を消す。
+/// 既にあるディレクトリに足す時はコピーになり、コピーしなかった物は元に残す。
+/// 全て移せたら、取り消し用の記録を返す。
+fn move_item(
+  reporter: &mut Reporter,
+  resolver: &mut ConflictResolver,
+  plan: &ItemPlan,
+  dst: &Path,
+) -> Option<JournalItem> {
+  let target = item_target(reporter, resolver, plan, dst)?;
+  let is_undoable = !target.overwrite && !target.merge;
+
+  reporter.start_file(&plan.src);
+  // 名前の変更で移せるのはデバイスを跨がない時だけ。跨ぐ時はコピーしてから元を消す。
+  if !target.merge {
+    match fs::rename(&plan.src, &target.dst) {
+      Ok(()) => {
+        for entry in &plan.entries {
+          reporter.finish_entry(entry, true);
+        }
+        return is_undoable.then(|| JournalItem::moved(&plan.src, &target.dst)).flatten();
+      }
+      Err(error) if !is_cross_device(&error) => {
+        reporter.error(&plan.src, &error);
+        return None;
+      }
+      Err(_) => {}
+    }
+  }
+
+  // コピーしたファイル分の進捗は数え済みなので、削除では数えない。
+  let not_copied = copy_item(reporter, resolver, plan, &target.dst, target.overwrite);
+  delete_item(reporter, plan, false, &not_copied);
+  let is_completed = fs::symlink_metadata(&plan.src).is_err();
+  (is_undoable && is_completed)
+    .then(|| JournalItem::moved(&plan.src, &target.dst))
+    .flatten()
+}
+
+/// 名前の変更が、デバイスを跨ぐために失敗したか。
+pub(super) fn is_cross_device(error: &io::Error) -> bool {
+  error.kind() == io::ErrorKind::CrossesDevices
+}
+
+/// 取り消しとやり直しで、名前の変更で移せない時(デバイスを跨ぐ時)に使う。進捗は出さない。
+/// コピーに失敗したら、コピーした分を消して元のままにする。
+pub(super) fn move_without_progress(
+  src: &Path,
+  dst: &Path,
+) -> io::Result<()> {
+  let entries = plan_entries(src)?;
+  for (idx, entry) in entries.iter().enumerate() {
+    let entry_src = path_of(src, &entry.relative);
+    let entry_dst = path_of(dst, &entry.relative);
+    let result = match entry.kind {
+      EntryKind::Directory => fs::create_dir(&entry_dst),
+      EntryKind::File => copy_file(&entry_src, &entry_dst, false, |_| true),
+      EntryKind::Symlink => copy_symlink(&entry_src, &entry_dst, false),
+    };
+    if let Err(error) = result {
+      remove_entries(dst, &entries[..idx], &[], |entry, path| Some(remove_entry(path, entry.kind)));
+      return Err(error);
+    }
+  }
+  let mut result = Ok(());
+  remove_entries(src, &entries, &[], |entry, path| {
+    let entry_result = remove_entry(path, entry.kind);
+    if let Err(error) = &entry_result {
+      result = Err(io::Error::new(error.kind(), error.to_string()));
+    }
+    Some(entry_result)
+  });
+  result
+}
+
+/// 経過とエラーを `reporter` に送りながら、`remove_entries` で消す。
+fn delete_item(
+  reporter: &mut Reporter,
+  plan: &ItemPlan,
+  count_progress: bool,
+  keep: &[&Path],
+) {
+  remove_entries(&plan.src, &plan.entries, keep, |entry, src| {
+    if !reporter.wait_if_paused() {
+      return None;
+    }
+    reporter.start_file(src);
+    let result = remove_entry(src, entry.kind);
+    match &result {
+      Ok(()) if count_progress => reporter.finish_entry(entry, true),
+      Ok(()) => {}
+      Err(error) => reporter.error(src, error),
+    }
+    Some(result)
+  });
+}
+
+/// 子から順に消す。`keep` と消せなかった物は、その祖先も子孫も残す。
+/// `remove` が None を返したら(中断)、そこで止める。
+fn remove_entries<'a>(
+  src: &Path,
+  entries: &'a [PlanEntry],
+  keep: &[&'a Path],
+  mut remove: impl FnMut(&'a PlanEntry, &Path) -> Option<io::Result<()>>,
+) {
+  let mut kept: Vec<&Path> = keep.to_vec();
+  for entry in entries.iter().rev() {
+    if kept.iter().any(|kept| kept.starts_with(&entry.relative) || entry.relative.starts_with(kept)) {
+      continue;
+    }
+    match remove(entry, &path_of(src, &entry.relative)) {
+      None => return,
+      Some(Ok(())) => {}
+      Some(Err(_)) => kept.push(&entry.relative),
+    }
+  }
+}
+
+fn remove_entry(
+  path: &Path,
+  kind: EntryKind,
+) -> io::Result<()> {
+  match kind {
+    EntryKind::Directory => fs::remove_dir(path),
+    // Windows のディレクトリへのリンクは、ディレクトリとして消す。
+    EntryKind::File | EntryKind::Symlink => fs::remove_file(path).or_else(|error| {
+      if kind == EntryKind::Symlink {
+        fs::remove_dir(path)
+      } else {
+        Err(error)
+      }
+    }),
+  }
+}
+
+/// 同じデバイスのゴミ箱に、名前の変更で移す。
+fn trash_item(
+  reporter: &mut Reporter,
+  plan: &ItemPlan,
+) -> Option<JournalItem> {
+  reporter.start_file(&plan.src);
+  match move_to_trash(&plan.src) {
+    Ok(trashed_path) => {
+      for entry in &plan.entries {
+        reporter.finish_entry(entry, true);
+      }
+      JournalItem::trashed(&plan.src, &trashed_path)
+    }
+    Err(error) => {
+      reporter.error(&plan.src, &error);
+      None
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::super::conflict::ConflictPolicy;
+  use super::*;
+  use tempdir::TempDir;
+
+  fn make_tree(root: &Path) -> PathBuf {
+    let src = root.join("src");
+    fs::create_dir_all(src.join("sub").join("deep")).unwrap();
+    fs::write(src.join("top.txt"), "top").unwrap();
+    fs::write(src.join("sub").join("inner.txt"), "inner").unwrap();
+    fs::write(src.join("sub").join("deep").join("deepest.txt"), "deepest").unwrap();
+    src
+  }
+
+  fn remove_all(
+    src: &Path,
+    keep: &[&Path],
+  ) {
+    let entries = plan_entries(src).unwrap();
+    remove_entries(src, &entries, keep, |entry, path| Some(remove_entry(path, entry.kind)));
+  }
+
+  fn copy_operation(
+    src: &Path,
+    dst_dir: &Path,
+  ) -> FileOperation {
+    FileOperation::Copy {
+      src_dir: src.parent().unwrap().to_string_lossy().to_string(),
+      item_names: vec![src.file_name().unwrap().to_string_lossy().to_string()],
+      dst_dir: dst_dir.to_string_lossy().to_string(),
+      conflict_policy: ConflictPolicy::Skip,
+    }
+  }
+
+  fn plan_of(
+    src: &Path,
+    dst: &Path,
+  ) -> ItemPlan {
+    ItemPlan {
+      src: src.to_path_buf(),
+      dst: Some(dst.to_path_buf()),
+      entries: plan_entries(src).unwrap(),
+    }
+  }
+
+  fn file_names_in(dir: &Path) -> Vec<String> {
+    let mut names = fs::read_dir(dir)
+      .unwrap()
+      .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+      .collect::<Vec<_>>();
+    names.sort();
+    names
+  }
+
+  #[test]
+  fn remove_entries_keeps_kept_subdirectory_and_its_ancestors() {
+    let work_dir = TempDir::new("transfer_test").unwrap();
+    let src = make_tree(work_dir.path());
+
+    // コピーで sub を飛ばした後の、移動元の削除
+    remove_all(&src, &[Path::new("sub")]);
+
+    assert!(!src.join("top.txt").exists());
+    assert!(src.join("sub").join("inner.txt").exists());
+    assert!(src.join("sub").join("deep").join("deepest.txt").exists());
+  }
+
+  #[test]
+  fn remove_entries_removes_whole_tree_without_kept_entries() {
+    let work_dir = TempDir::new("transfer_test").unwrap();
+    let src = make_tree(work_dir.path());
+
+    remove_all(&src, &[]);
+
+    assert!(!src.exists());
+  }
+
+  #[test]
+  fn move_without_progress_copies_tree_and_removes_source() {
+    let work_dir = TempDir::new("transfer_test").unwrap();
+    let src = make_tree(work_dir.path());
+    let dst = work_dir.path().join("dst");
+
+    move_without_progress(&src, &dst).unwrap();
+
+    assert!(!src.exists());
+    assert_eq!(fs::read_to_string(dst.join("top.txt")).unwrap(), "top");
+    assert_eq!(fs::read_to_string(dst.join("sub").join("deep").join("deepest.txt")).unwrap(), "deepest");
+  }
+
+  #[test]
+  fn copy_item_copies_tree_and_counts_progress() {
+    let work_dir = TempDir::new("transfer_test").unwrap();
+    let src = make_tree(work_dir.path());
+    let dst = work_dir.path().join("dst");
+    let operation = copy_operation(&src, work_dir.path());
+    let control = JobControl::default();
+    let mut reporter = Reporter::new(None, &control, "test", &operation);
+    let mut resolver = ConflictResolver::new(None, "test", &control, ConflictPolicy::Skip);
+    let plans = vec![plan_of(&src, &dst)];
+    reporter.set_total(&plans);
+
+    let not_copied = copy_item(&mut reporter, &mut resolver, &plans[0], &dst, false);
+
+    assert!(not_copied.is_empty());
+    assert!(src.join("top.txt").exists());
+    assert_eq!(fs::read_to_string(dst.join("top.txt")).unwrap(), "top");
+    assert_eq!(fs::read_to_string(dst.join("sub").join("inner.txt")).unwrap(), "inner");
+    assert_eq!(fs::read_to_string(dst.join("sub").join("deep").join("deepest.txt")).unwrap(), "deepest");
+    assert_eq!(reporter.progress.total_file_num, 3);
+    assert_eq!(reporter.progress.total_bytes, 15);
+    assert_eq!(reporter.progress.done_file_num, 3);
+    assert_eq!(reporter.progress.done_bytes, 15);
+    assert!(reporter.errors.is_empty());
+  }
+
+  #[test]
+  fn copy_item_skips_conflicting_file_and_counts_it_as_done() {
+    let work_dir = TempDir::new("transfer_test").unwrap();
+    let src = make_tree(work_dir.path());
+    let dst = work_dir.path().join("dst");
+    fs::create_dir_all(dst.join("sub")).unwrap();
+    fs::write(dst.join("sub").join("inner.txt"), "old").unwrap();
+    let operation = copy_operation(&src, work_dir.path());
+    let control = JobControl::default();
+    let mut reporter = Reporter::new(None, &control, "test", &operation);
+    let mut resolver = ConflictResolver::new(None, "test", &control, ConflictPolicy::Skip);
+    let plans = vec![plan_of(&src, &dst)];
+    reporter.set_total(&plans);
+
+    let not_copied = copy_item(&mut reporter, &mut resolver, &plans[0], &dst, false);
+
+    assert_eq!(not_copied, vec![Path::new("sub").join("inner.txt")]);
+    assert_eq!(fs::read_to_string(dst.join("sub").join("inner.txt")).unwrap(), "old");
+    assert_eq!(fs::read_to_string(dst.join("top.txt")).unwrap(), "top");
+    assert_eq!(reporter.progress.done_file_num, reporter.progress.total_file_num);
+    assert_eq!(reporter.progress.done_bytes, reporter.progress.total_bytes);
+    assert_eq!(reporter.skipped.len(), 1);
+  }
+
+  #[test]
+  fn move_item_renames_tree_and_records_it() {
+    let work_dir = TempDir::new("transfer_test").unwrap();
+    let src = make_tree(work_dir.path());
+    let dst = work_dir.path().join("dst");
+    let operation = copy_operation(&src, work_dir.path());
+    let control = JobControl::default();
+    let mut reporter = Reporter::new(None, &control, "test", &operation);
+    let mut resolver = ConflictResolver::new(None, "test", &control, ConflictPolicy::Skip);
+    let plans = vec![plan_of(&src, &dst)];
+    reporter.set_total(&plans);
+
+    let journal_item = move_item(&mut reporter, &mut resolver, &plans[0], &dst);
+
+    assert!(journal_item.is_some());
+    assert!(!src.exists());
+    assert_eq!(fs::read_to_string(dst.join("sub").join("deep").join("deepest.txt")).unwrap(), "deepest");
+    assert_eq!(reporter.progress.done_file_num, 3);
+    assert_eq!(reporter.progress.done_bytes, 15);
+  }
+
+  #[test]
+  fn move_item_merges_into_existing_directory_and_keeps_skipped_files() {
+    let work_dir = TempDir::new("transfer_test").unwrap();
+    let src = make_tree(work_dir.path());
+    let dst = work_dir.path().join("dst");
+    fs::create_dir_all(dst.join("sub")).unwrap();
+    // 大きさが同じなので飛ばす。
+    fs::write(dst.join("sub").join("inner.txt"), "INNER").unwrap();
+    let operation = copy_operation(&src, work_dir.path());
+    let control = JobControl::default();
+    let mut reporter = Reporter::new(None, &control, "test", &operation);
+    let mut resolver = ConflictResolver::new(None, "test", &control, ConflictPolicy::CompareSize);
+    let plans = vec![plan_of(&src, &dst)];
+    reporter.set_total(&plans);
+
+    let journal_item = move_item(&mut reporter, &mut resolver, &plans[0], &dst);
+
+    // 統合は取り消せないので記録しない。
+    assert!(journal_item.is_none());
+    assert!(!src.join("top.txt").exists());
+    assert!(!src.join("sub").join("deep").exists());
+    assert_eq!(fs::read_to_string(src.join("sub").join("inner.txt")).unwrap(), "inner");
+    assert_eq!(fs::read_to_string(dst.join("sub").join("inner.txt")).unwrap(), "INNER");
+    assert_eq!(fs::read_to_string(dst.join("top.txt")).unwrap(), "top");
+    assert_eq!(fs::read_to_string(dst.join("sub").join("deep").join("deepest.txt")).unwrap(), "deepest");
+  }
+
+  #[test]
+  fn copy_file_overwrites_through_temporary_file() {
+    let work_dir = TempDir::new("transfer_test").unwrap();
+    let src = work_dir.path().join("src.txt");
+    let dst = work_dir.path().join("dst.txt");
+    fs::write(&src, "new content").unwrap();
+    fs::write(&dst, "old").unwrap();
+
+    assert_eq!(
+      copy_file(&src, &dst, false, |_| true).unwrap_err().kind(),
+      io::ErrorKind::AlreadyExists
+    );
+    copy_file(&src, &dst, true, |_| true).unwrap();
+
+    assert_eq!(fs::read_to_string(&dst).unwrap(), "new content");
+    assert_eq!(file_names_in(work_dir.path()), vec!["dst.txt", "src.txt"]);
+  }
+
+  #[test]
+  fn copy_file_keeps_original_when_cancelled_while_overwriting() {
+    let work_dir = TempDir::new("transfer_test").unwrap();
+    let src = work_dir.path().join("src.txt");
+    let dst = work_dir.path().join("dst.txt");
+    fs::write(&src, "new content").unwrap();
+    fs::write(&dst, "old").unwrap();
+
+    let error = copy_file(&src, &dst, true, |_| false).unwrap_err();
+
+    assert_eq!(error.kind(), io::ErrorKind::Interrupted);
+    assert_eq!(fs::read_to_string(&dst).unwrap(), "old");
+    assert_eq!(file_names_in(work_dir.path()), vec!["dst.txt", "src.txt"]);
+  }
+
+  #[test]
+  fn is_into_itself_compares_whole_components() {
+    assert!(is_into_itself(Path::new("/a/b"), Path::new("/a/b/c")));
+    assert!(is_into_itself(Path::new("/a/b"), Path::new("/a/b/c/d")));
+    assert!(!is_into_itself(Path::new("/a/b"), Path::new("/a/b")));
+    assert!(!is_into_itself(Path::new("/a/b"), Path::new("/a/bc")));
+    assert!(!is_into_itself(Path::new("/a/b"), Path::new("/a")));
+  }
+}
@@ -0,0 +1,507 @@
+use std::{
+  fs::{self, OpenOptions},
+  io::{self, Write},
+  path::{Path, PathBuf},
+};
+
+use chrono::Local;
+use tauri::AppHandle;
+
+use super::conflict::numbered_name;
+use crate::access_error::AccessError;
+use crate::pane_info::update_file_list;
+#[cfg(unix)]
+use crate::pane_info::mount_list;
+
+const TRASH_INFO_EXTENSION: &str = "trashinfo";
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// ゴミ箱の中の 1 項目
+#[derive(Debug, Serialize, Clone)]
+pub struct TrashItemInfo {
+  /// 復元で指定する ID。ゴミ箱の `files` の中の実体のパス。
+  id: String,
+  /// 元の名前
+  name: String,
+  original_path: String,
+  /// `.trashinfo` の書式のまま(`2004-08-31T22:32:08`)
+  deletion_date: String,
+  is_dir: bool,
+}
+
+/// FreeDesktop のゴミ箱。`files` に実体を、`info` に元の場所を書いた `.trashinfo` を置く。
+#[derive(Debug)]
+struct TrashDir {
+  path: PathBuf,
+  /// マウントポイント毎のゴミ箱では、元の場所をマウントポイントからの相対パスで書く。
+  top_dir: Option<PathBuf>,
+}
+
+impl TrashDir {
+  fn files_dir(&self) -> PathBuf {
+    self.path.join("files")
+  }
+
+  fn info_dir(&self) -> PathBuf {
+    self.path.join("info")
+  }
+
+  fn info_path_of(
+    &self,
+    name: &str,
+  ) -> PathBuf {
+    self.info_dir().join(format!("{}.{}", name, TRASH_INFO_EXTENSION))
+  }
+
+  /// ゴミ箱の `files` の中のパスなら、そのゴミ箱を返す。
+  fn of_trashed_path(trashed_path: &Path) -> Option<TrashDir> {
+    let trash_path = trashed_path.parent()?.parent()?;
+    all_trash_dirs().into_iter().find(|trash_dir| trash_dir.path == trash_path)
+  }
+
+  /// 同じ名前が `files` と `info` のどちらにも無い名前で `.trashinfo` を作り、その名前を返す。
+  fn create_info_file(
+    &self,
+    path: &Path,
+  ) -> io::Result<String> {
+    let original_path = match &self.top_dir {
+      Some(top_dir) => path.strip_prefix(top_dir).unwrap_or(path),
+      None => path,
+    };
+    let content = format!(
+      "[Trash Info]\nPath={}\nDeletionDate={}\n",
+      encode_path(original_path),
+      Local::now().format("%Y-%m-%dT%H:%M:%S"),
+    );
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let is_dir = fs::symlink_metadata(path)?.is_dir();
+    for number in 1.. {
+      let name = if number == 1 { file_name.clone() } else { numbered_name(&file_name, number, is_dir) };
+      if fs::symlink_metadata(self.files_dir().join(&name)).is_ok() {
+        continue;
+      }
+      let info_path = self.info_path_of(&name);
+      let mut info_file = match OpenOptions::new().write(true).create_new(true).open(&info_path) {
+        Ok(info_file) => info_file,
+        Err(error) if error.kind() == io::ErrorKind::AlreadyExists => continue,
+        Err(error) => return Err(error),
+      };
+      if let Err(error) = info_file.write_all(content.as_bytes()) {
+        drop(info_file);
+        let _ = fs::remove_file(&info_path);
+        return Err(error);
+      }
+      return Ok(name);
+    }
+    unreachable!()
+  }
+
+  /// `.trashinfo` から元の場所と削除日時を読む。
+  fn read_info_file(
+    &self,
+    name: &str,
+  ) -> io::Result<(PathBuf, String)> {
+    let content = fs::read_to_string(self.info_path_of(name))?;
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid trash info file");
+    let mut lines = content.lines();
+    if lines.next().map(str::trim) != Some("[Trash Info]") {
+      return Err(invalid());
+    }
+    let mut original_path = None;
+    let mut deletion_date = String::new();
+    for line in lines {
+      let Some((key, value)) = line.split_once('=') else {
+        continue;
+      };
+      match key.trim() {
+        "Path" => original_path = Some(decode_path(value.trim())),
+        "DeletionDate" => deletion_date = value.trim().to_owned(),
+        _ => {}
+      }
+    }
+    let original_path = original_path.ok_or_else(invalid)?;
+    let original_path = match &self.top_dir {
+      Some(top_dir) if original_path.is_relative() => top_dir.join(original_path),
+      _ => original_path,
+    };
+    Ok((original_path, deletion_date))
+  }
+
+  /// `info` の無い実体(壊れた項目)は出さない。
+  fn items(&self) -> Vec<TrashItemInfo> {
+    let Ok(read_dir) = fs::read_dir(self.info_dir()) else {
+      return Vec::new();
+    };
+    read_dir
+      .filter_map(|entry| {
+        let info_path = entry.ok()?.path();
+        if info_path.extension()? != TRASH_INFO_EXTENSION {
+          return None;
+        }
+        let name = info_path.file_stem()?.to_string_lossy().to_string();
+        let trashed_path = self.files_dir().join(&name);
+        let meta_data = fs::symlink_metadata(&trashed_path).ok()?;
+        let (original_path, deletion_date) = self.read_info_file(&name).ok()?;
+        Some(TrashItemInfo {
+          id: trashed_path.to_string_lossy().to_string(),
+          name: original_path.file_name()?.to_string_lossy().to_string(),
+          original_path: original_path.to_string_lossy().to_string(),
+          deletion_date,
+          is_dir: meta_data.is_dir(),
+        })
+      })
+      .collect()
+  }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// ゴミ箱に移せる環境か。Windows のごみ箱には対応していない。
+#[tauri::command]
+pub fn is_trash_supported() -> bool {
+  cfg!(unix)
+}
+
+/// 新しい順
+#[tauri::command]
+pub fn list_trash_items() -> Vec<TrashItemInfo> {
+  let mut items = all_trash_dirs().iter().flat_map(TrashDir::items).collect::<Vec<_>>();
+  items.sort_by(|a, b| b.deletion_date.cmp(&a.deletion_date));
+  items
+}
+
+/// 元の場所に戻す。元の場所に同じ名前の物が既にあれば、戻さずにエラーにする。
+/// 失敗した項目があっても、残りは戻す。
+#[tauri::command]
+pub fn restore_trash_items(
+  app_handle: AppHandle,
+  ids: Vec<String>,
+) -> Result<(), AccessError> {
+  let mut result = Ok(());
+  for id in ids {
+    if let Err(error) = restore(Path::new(&id)) {
+      result = result.and(Err(error));
+    }
+  }
+  update_file_list(&app_handle);
+  result
+}
+
+/// 全てのゴミ箱を空にする。消せなかった物があっても、残りは消す。
+#[tauri::command]
+pub fn empty_trash(app_handle: AppHandle) -> Result<(), AccessError> {
+  let mut result = Ok(());
+  for trash_dir in all_trash_dirs() {
+    let Ok(read_dir) = fs::read_dir(trash_dir.files_dir()) else {
+      continue;
+    };
+    for entry in read_dir.flatten() {
+      let trashed_path = entry.path();
+      let removed = match entry.file_type() {
+        Ok(file_type) if file_type.is_dir() => fs::remove_dir_all(&trashed_path),
+        _ => fs::remove_file(&trashed_path),
+      };
+      match removed {
+        Ok(()) => {
+          let _ = fs::remove_file(trash_dir.info_path_of(&entry.file_name().to_string_lossy()));
+        }
+        Err(error) => result = result.and(Err(AccessError::from_io_error(&trashed_path, &error))),
+      }
+    }
+  }
+  update_file_list(&app_handle);
+  result
+}
+
+/// `info` を書いてから実体を移し、ゴミ箱の中のパスを返す。実体を移せなければ `info` も消す。
+pub(super) fn move_to_trash(path: &Path) -> io::Result<PathBuf> {
+  let trash_dir = trash_dir_for(path)?;
+  fs::create_dir_all(trash_dir.files_dir())?;
+  fs::create_dir_all(trash_dir.info_dir())?;
+  let name = trash_dir.create_info_file(path)?;
+  let trashed_path = trash_dir.files_dir().join(&name);
+  if let Err(error) = fs::rename(path, &trashed_path) {
+    let _ = fs::remove_file(trash_dir.info_path_of(&name));
+    return Err(error);
+  }
+  Ok(trashed_path)
+}
+
+pub(super) fn restore(trashed_path: &Path) -> Result<(), AccessError> {
+  let not_found = || AccessError::NotFound {
+    path: trashed_path.to_string_lossy().to_string(),
+  };
+  let Some(trash_dir) = TrashDir::of_trashed_path(trashed_path) else {
+    return Err(not_found());
+  };
+  let Some(name) = trashed_path.file_name() else {
+    return Err(not_found());
+  };
+  let name = name.to_string_lossy().to_string();
+  let (original_path, _) = trash_dir
+    .read_info_file(&name)
+    .map_err(|error| AccessError::from_io_error(&trash_dir.info_path_of(&name), &error))?;
+
+  if fs::symlink_metadata(&original_path).is_ok() {
+    let error = io::Error::new(io::ErrorKind::AlreadyExists, "original location is already in use");
+    return Err(AccessError::from_io_error(&original_path, &error));
+  }
+  if let Some(parent) = original_path.parent() {
+    fs::create_dir_all(parent).map_err(|error| AccessError::from_io_error(parent, &error))?;
+  }
+  fs::rename(trashed_path, &original_path).map_err(|error| AccessError::from_io_error(trashed_path, &error))?;
+  let _ = fs::remove_file(trash_dir.info_path_of(&name));
+  Ok(())
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// `$XDG_DATA_HOME/Trash`
+#[cfg(unix)]
+fn home_trash_dir() -> io::Result<TrashDir> {
+  let Some(data_dir) = dirs::data_dir() else {
+    return Err(io::Error::new(io::ErrorKind::NotFound, "data directory not found"));
+  };
+  Ok(TrashDir {
+    path: data_dir.join("Trash"),
+    top_dir: None,
+  })
+}
+
+/// ホームと同じデバイスならホームのゴミ箱、違えばマウントポイント毎のゴミ箱。
+/// 名前の変更だけで移せる様にする。
+#[cfg(unix)]
+fn trash_dir_for(path: &Path) -> io::Result<TrashDir> {
+  use std::os::unix::fs::MetadataExt;
+
+  let home_trash_dir = home_trash_dir()?;
+  let device = fs::symlink_metadata(path)?.dev();
+  let home_trash_device = home_trash_dir
+    .path
+    .ancestors()
+    .find_map(|ancestor| fs::metadata(ancestor).ok())
+    .map(|meta_data| meta_data.dev());
+  if home_trash_device == Some(device) {
+    return Ok(home_trash_dir);
+  }
+
+  let mut top_dir = path.parent().unwrap_or(path);
+  while let Some(parent) = top_dir.parent() {
+    if !fs::metadata(parent).is_ok_and(|meta_data| meta_data.dev() == device) {
+      break;
+    }
+    top_dir = parent;
+  }
+  top_dir_trash_dir(top_dir, true).ok_or_else(|| io::Error::new(io::ErrorKind::PermissionDenied, "cannot create trash directory"))
+}
+
+/// Windows のごみ箱はこの形式ではなく、エクスプローラーから見えない上にドライブを跨げないので、対応しない。
+#[cfg(not(unix))]
+fn trash_dir_for(_path: &Path) -> io::Result<TrashDir> {
+  Err(io::Error::new(io::ErrorKind::Unsupported, "trash is not supported on this platform"))
+}
+
+/// 管理者が用意した `$topdir/.Trash/$uid` を優先し、無ければ `$topdir/.Trash-$uid` を使う。
+/// `.Trash` はスティッキービット付きで、シンボリックリンクでない物だけ使う。
+#[cfg(unix)]
+fn top_dir_trash_dir(
+  top_dir: &Path,
+  create: bool,
+) -> Option<TrashDir> {
+  use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+  let uid = fs::metadata(dirs::home_dir()?).ok()?.uid();
+  let shared_dir = top_dir.join(".Trash");
+  let is_valid_shared_dir = fs::symlink_metadata(&shared_dir)
+    .is_ok_and(|meta_data| meta_data.is_dir() && meta_data.mode() & 0o1000 != 0);
+  let candidates = [
+    (is_valid_shared_dir, shared_dir.join(uid.to_string())),
+    (true, top_dir.join(format!(".Trash-{}", uid))),
+  ];
+  candidates
+    .into_iter()
+    .filter(|(is_valid, _)| *is_valid)
+    .find(|(_, path)| {
+      fs::symlink_metadata(path).is_ok_and(|meta_data| meta_data.is_dir())
+        || (create && fs::DirBuilder::new().mode(0o700).create(path).is_ok())
+    })
+    .map(|(_, path)| TrashDir {
+      path,
+      top_dir: Some(top_dir.to_path_buf()),
+    })
+}
+
+/// ホームのゴミ箱と、マウントされている所にある自分用のゴミ箱。
+#[cfg(unix)]
+fn all_trash_dirs() -> Vec<TrashDir> {
+  let mut result = Vec::new();
+  if let Ok(home_trash_dir) = home_trash_dir() {
+    result.push(home_trash_dir);
+  }
+  let mount_points = mount_list().unwrap_or_default().into_iter().map(|(mount_point, _)| mount_point);
+  for mount_point in mount_points {
+    // 同じ所が何度もマウントされていることがある。
+    let Some(trash_dir) = top_dir_trash_dir(Path::new(&mount_point), false) else {
+      continue;
+    };
+    if !result.iter().any(|added: &TrashDir| added.path == trash_dir.path) {
+      result.push(trash_dir);
+    }
+  }
+  result
+}
+
+#[cfg(not(unix))]
+fn all_trash_dirs() -> Vec<TrashDir> {
+  Vec::new()
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// `.trashinfo` の `Path` は URL と同じ様に % で符号化する。
+/// UTF-8 でない名前も壊さない様、バイト列のまま符号化する。
+fn encode_path(path: &Path) -> String {
+  #[cfg(unix)]
+  let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+  #[cfg(not(unix))]
+  let bytes = path.to_string_lossy().as_bytes().to_vec();
+  bytes
+    .into_iter()
+    .map(|byte| match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (byte as char).to_string(),
+      _ => format!("%{:02X}", byte),
+    })
+    .collect()
+}
+
+fn decode_path(encoded: &str) -> PathBuf {
+  let bytes = encoded.as_bytes();
+  let mut result = Vec::with_capacity(bytes.len());
+  let mut idx = 0;
+  while idx < bytes.len() {
+    let decoded = (bytes[idx] == b'%')
+      .then(|| std::str::from_utf8(bytes.get(idx + 1..idx + 3)?).ok())
+      .flatten()
+      .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+    match decoded {
+      Some(byte) => {
+        result.push(byte);
+        idx += 3;
+      }
+      None => {
+        result.push(bytes[idx]);
+        idx += 1;
+      }
+    }
+  }
+  #[cfg(unix)]
+  return PathBuf::from(<std::ffi::OsString as std::os::unix::ffi::OsStringExt>::from_vec(result));
+  #[cfg(not(unix))]
+  return PathBuf::from(String::from_utf8_lossy(&result).to_string());
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempdir::TempDir;
+
+  fn trash_dir_in(
+    work_dir: &Path,
+    top_dir: Option<&Path>,
+  ) -> TrashDir {
+    let trash_dir = TrashDir {
+      path: work_dir.join(".Trash-1000"),
+      top_dir: top_dir.map(Path::to_path_buf),
+    };
+    fs::create_dir_all(trash_dir.files_dir()).unwrap();
+    fs::create_dir_all(trash_dir.info_dir()).unwrap();
+    trash_dir
+  }
+
+  #[test]
+  fn encode_path_escapes_all_but_unreserved_characters() {
+    assert_eq!(encode_path(Path::new("/home/user/a b.txt")), "/home/user/a%20b.txt");
+    assert_eq!(encode_path(Path::new("/tmp/100%")), "/tmp/100%25");
+    assert_eq!(encode_path(Path::new("/tmp/日")), "/tmp/%E6%97%A5");
+  }
+
+  #[test]
+  fn decode_path_reverses_encode_path() {
+    for path in ["/home/user/a b.txt", "/tmp/100%", "/tmp/日本語 (1).txt", "relative/~name_-.txt"] {
+      assert_eq!(decode_path(&encode_path(Path::new(path))), Path::new(path));
+    }
+  }
+
+  #[test]
+  fn decode_path_keeps_broken_escapes() {
+    assert_eq!(decode_path("/tmp/50%"), Path::new("/tmp/50%"));
+    assert_eq!(decode_path("/tmp/%zz"), Path::new("/tmp/%zz"));
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn encode_path_keeps_non_utf8_bytes() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = Path::new(std::ffi::OsStr::from_bytes(b"/tmp/\xff\xfe.txt"));
+    assert_eq!(encode_path(path), "/tmp/%FF%FE.txt");
+    assert_eq!(decode_path(&encode_path(path)), path);
+  }
+
+  #[test]
+  fn info_file_stores_path_relative_to_top_dir() {
+    let work_dir = TempDir::new("trash_test").unwrap();
+    let trash_dir = trash_dir_in(work_dir.path(), Some(work_dir.path()));
+    let path = work_dir.path().join("dir").join("a b.txt");
+    fs::create_dir(work_dir.path().join("dir")).unwrap();
+    fs::write(&path, "").unwrap();
+
+    let name = trash_dir.create_info_file(&path).unwrap();
+    let content = fs::read_to_string(trash_dir.info_path_of(&name)).unwrap();
+    let (original_path, deletion_date) = trash_dir.read_info_file(&name).unwrap();
+
+    assert_eq!(name, "a b.txt");
+    assert!(content.contains("\nPath=dir/a%20b.txt\n"));
+    assert_eq!(original_path, path);
+    assert_eq!(deletion_date.len(), "2004-08-31T22:32:08".len());
+  }
+
+  #[test]
+  fn info_file_stores_absolute_path_in_home_trash() {
+    let work_dir = TempDir::new("trash_test").unwrap();
+    let trash_dir = trash_dir_in(work_dir.path(), None);
+    let path = work_dir.path().join("a.txt");
+    fs::write(&path, "").unwrap();
+
+    let name = trash_dir.create_info_file(&path).unwrap();
+
+    assert_eq!(trash_dir.read_info_file(&name).unwrap().0, path);
+  }
+
+  #[test]
+  fn create_info_file_numbers_names_in_use() {
+    let work_dir = TempDir::new("trash_test").unwrap();
+    let trash_dir = trash_dir_in(work_dir.path(), None);
+    let path = work_dir.path().join("a.txt");
+    fs::write(&path, "").unwrap();
+    fs::write(trash_dir.files_dir().join("a(2).txt"), "").unwrap();
+
+    assert_eq!(trash_dir.create_info_file(&path).unwrap(), "a.txt");
+    assert_eq!(trash_dir.create_info_file(&path).unwrap(), "a(3).txt");
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn info_file_keeps_non_utf8_original_path() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let work_dir = TempDir::new("trash_test").unwrap();
+    let trash_dir = trash_dir_in(work_dir.path(), Some(work_dir.path()));
+    let path = work_dir.path().join(std::ffi::OsStr::from_bytes(b"\xff.txt"));
+    fs::write(&path, "").unwrap();
+
+    let name = trash_dir.create_info_file(&path).unwrap();
+    let content = fs::read_to_string(trash_dir.info_path_of(&name)).unwrap();
+
+    assert!(content.contains("\nPath=%FF.txt\n"));
+    assert_eq!(trash_dir.read_info_file(&name).unwrap().0, path);
+  }
+}
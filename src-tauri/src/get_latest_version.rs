@@ -30,5 +30,5 @@ fn get_release_page_htlm() -> Option<String> {
       .unwrap();
     transfer.perform().unwrap();
   }
-  Some(String::from_utf8(data).ok()?)
+  String::from_utf8(data).ok()
 }
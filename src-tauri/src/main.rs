@@ -6,25 +6,57 @@
 #[macro_use]
 extern crate serde;
 
-use std::time::Duration;
 use tauri::Manager;
 
+mod access_error;
+
 mod get_exe_dir;
 use get_exe_dir::get_exe_dir;
 
 mod pane_info;
+use pane_info::create_pane;
+use pane_info::close_pane;
 use pane_info::set_background_color;
 use pane_info::set_ignore_system_file;
 use pane_info::is_ignore_system_file;
+use pane_info::get_file_list_ui_info;
 use pane_info::set_dirctry_path;
 use pane_info::set_filter;
+use pane_info::get_filter;
+use pane_info::filter_retention::set_filter_retention;
+use pane_info::filter_retention::get_filter_retention;
+use pane_info::recursive_listing::set_recursive_listing;
+use pane_info::recursive_listing::get_recursive_listing;
+use pane_info::content_search::set_content_search_max_file_size;
+use pane_info::content_search::get_content_search_max_file_size;
+use pane_info::filter_info::query::check_filter_query;
+use pane_info::saved_filters::get_saved_filters;
+use pane_info::saved_filters::save_filter;
+use pane_info::saved_filters::delete_saved_filter;
+use pane_info::saved_filters::add_recent_filter;
 use pane_info::set_focus_idx;
 use pane_info::set_viewing_idx_range;
-use pane_info::update_file_list;
+use pane_info::start_directory_watcher;
 use pane_info::sort::sort_file_list;
+use pane_info::sort::set_sort_spec;
+use pane_info::sort::get_sort_spec;
+use pane_info::directory_size::set_directory_size_enabled;
+use pane_info::directory_size::is_directory_size_enabled;
+use pane_info::history::go_back;
+use pane_info::history::go_forward;
+use pane_info::history::get_navigation_history;
 use pane_info::selections::add_selecting_idx;
 use pane_info::selections::set_selecting_idx;
 use pane_info::selections::toggle_selection;
+use pane_info::selections::get_selecting_item_name;
+
+mod file_index;
+use file_index::start_file_indexer;
+use file_index::get_file_index_setting;
+use file_index::set_file_index_setting;
+use file_index::rebuild_file_index;
+use file_index::get_file_index_status;
+use file_index::go_to_anywhere;
 
 mod setting_file;
 use setting_file::read_setting_file;
@@ -40,6 +72,25 @@ use resolve_symbolic_link::resolve_symbolic_link;
 mod execute_shell_command;
 use execute_shell_command::execute_shell_command;
 
+mod file_operation;
+use file_operation::copy_items;
+use file_operation::move_items;
+use file_operation::delete_items;
+use file_operation::trash_items;
+use file_operation::rename_items;
+use file_operation::create_items;
+use file_operation::journal::undo_file_operation;
+use file_operation::journal::redo_file_operation;
+use file_operation::trash::is_trash_supported;
+use file_operation::trash::list_trash_items;
+use file_operation::trash::restore_trash_items;
+use file_operation::trash::empty_trash;
+use file_operation::job_queue::list_file_jobs;
+use file_operation::job_queue::pause_file_job;
+use file_operation::job_queue::resume_file_job;
+use file_operation::job_queue::cancel_file_job;
+use file_operation::conflict::answer_file_conflict;
+
 mod get_latest_version;
 mod update_filer;
 use get_latest_version::get_latest_version;
@@ -48,20 +99,65 @@ use update_filer::update_filer;
 fn main() {
   tauri::Builder::default()
     .invoke_handler(tauri::generate_handler![
+      create_pane,
+      close_pane,
       set_background_color,
       set_ignore_system_file,
       is_ignore_system_file,
+      get_file_list_ui_info,
       set_dirctry_path,
       set_filter,
+      get_filter,
+      set_filter_retention,
+      get_filter_retention,
+      set_recursive_listing,
+      get_recursive_listing,
+      set_content_search_max_file_size,
+      get_content_search_max_file_size,
+      check_filter_query,
+      get_saved_filters,
+      save_filter,
+      delete_saved_filter,
+      add_recent_filter,
       add_selecting_idx,
       set_selecting_idx,
       set_viewing_idx_range,
       toggle_selection,
+      get_selecting_item_name,
       set_focus_idx,
       sort_file_list,
+      set_sort_spec,
+      get_sort_spec,
+      set_directory_size_enabled,
+      is_directory_size_enabled,
+      go_back,
+      go_forward,
+      get_navigation_history,
+      get_file_index_setting,
+      set_file_index_setting,
+      rebuild_file_index,
+      get_file_index_status,
+      go_to_anywhere,
       adjust_addressbar_str,
       resolve_symbolic_link,
       execute_shell_command,
+      copy_items,
+      move_items,
+      delete_items,
+      trash_items,
+      rename_items,
+      create_items,
+      undo_file_operation,
+      redo_file_operation,
+      is_trash_supported,
+      list_trash_items,
+      restore_trash_items,
+      empty_trash,
+      list_file_jobs,
+      pause_file_job,
+      resume_file_job,
+      cancel_file_job,
+      answer_file_conflict,
       read_setting_file,
       write_setting_file,
       setting_dir,
@@ -70,11 +166,8 @@ fn main() {
       update_filer,
     ])
     .setup(|app| {
-      let app_handle = app.app_handle().clone();
-      std::thread::spawn(move || loop {
-        std::thread::sleep(Duration::from_secs(1));
-        update_file_list(&app_handle);
-      });
+      start_directory_watcher(app.app_handle().clone());
+      start_file_indexer();
 
       #[cfg(debug_assertions)]
       app.get_webview_window("main").unwrap().open_devtools();
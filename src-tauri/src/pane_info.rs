@@ -2,36 +2,74 @@ use itertools::Itertools;
 use std::{
   collections::{HashMap, HashSet},
   path::PathBuf,
-  sync::{Mutex, MutexGuard},
+  sync::{Arc, Mutex, MutexGuard},
 };
 
 use once_cell::sync::Lazy;
 
+use crate::access_error::AccessError;
+
 mod get_file_icon;
 use get_file_icon::{get_file_icon, Color};
 
 mod get_file_list;
-use get_file_list::{get_file_list, FileBaseInfo};
+use get_file_list::{get_file_list, get_file_list_recursive, FileBaseInfo};
+#[cfg(unix)]
+pub(crate) use get_file_list::mount_list;
 
-mod filter_info;
+pub mod filter_info;
 use filter_info::{matching_rate, FilterInfo};
 use tauri::Emitter;
 
+mod file_list_delta;
+use file_list_delta::{calc_delta, FileListDelta, FileListSnapshot};
+
+mod directory_watcher;
+pub use directory_watcher::start_directory_watcher;
+use directory_watcher::{request_icon_update, unwatch_directory, watch_directory};
+
+pub mod directory_size;
+use directory_size::{cancel_directory_size_job, restart_directory_size_job};
+pub mod history;
+use history::NavigationHistory;
+pub mod saved_filters;
+use saved_filters::remember_recent_filter;
+pub mod filter_retention;
+use filter_retention::{filter_for_directory, remember_directory_filter, FilterRetention};
+pub mod content_search;
+use content_search::{
+  cancel_content_search_job, clear_content_match, restart_content_search_job, ContentMatch,
+};
+pub mod recursive_listing;
+use recursive_listing::RecursiveListing;
 pub mod selections;
 pub mod sort;
+use sort::SortSpec;
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
-pub fn get_file_list_ex(path: &str) -> Option<Vec<FileBaseInfo>> {
-  let Some(result) = get_file_list(&path) else {
-    return None;
-  };
-
+/// 再帰表示の指定があれば、配下の全てのファイルを相対パスで返す。
+pub fn get_file_list_ex(
+  path: &str,
+  recursive_listing: Option<&RecursiveListing>,
+) -> Result<Vec<FileBaseInfo>, AccessError> {
   let ignore_system_file = is_ignore_system_file();
+  if let Some(recursive_listing) = recursive_listing {
+    if !path.is_empty() {
+      return get_file_list_recursive(
+        path,
+        recursive_listing.max_depth(),
+        ignore_system_file,
+        |file_name, relative_path| recursive_listing.is_excluded(file_name, relative_path),
+      );
+    }
+  }
+
+  let result = get_file_list(path)?;
   if !ignore_system_file {
-    return Some(result);
+    return Ok(result);
   };
 
-  Some(
+  Ok(
     result
       .into_iter()
       .filter(|item| !item.is_system_file())
@@ -40,9 +78,14 @@ pub fn get_file_list_ex(path: &str) -> Option<Vec<FileBaseInfo>> {
 }
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
+/// 一覧のうち、`item_range_stt` から始まる表示範囲付近の要素のみを持つ。
 #[derive(Debug, Serialize, Clone)]
 pub struct FileListUiInfo {
+  version: u64,
   full_item_num: usize,
+  filtered_item_num: usize,
+  selected_item_num: usize,
+  item_range_stt: usize,
   filtered_item_list: Vec<FileListFilteredItem>,
   focus_idx: usize,
 }
@@ -58,10 +101,14 @@ pub struct FileListItem {
   is_selected: bool,
   file_name: String,
   file_extension: String,
-  file_size: Option<u64>,
+  file_size: Option<u64>, // ディレクトリの場合は、配下の合計(計算済みの場合のみ)
+  file_count: Option<u64>, // ディレクトリ配下のファイル数(計算済みの場合のみ)
   is_directory: bool,
   file_icon: Option<String>,
   date: Option<String>,
+  #[serde(skip)]
+  modified_time: Option<std::time::SystemTime>, // ソート用
+  content_match: Option<ContentMatch>, // 内容検索の結果(検索済みの場合のみ)
 }
 
 impl FileListItem {
@@ -74,9 +121,12 @@ impl FileListItem {
       file_name: base_info.file_name.to_string(),
       file_extension: base_info.file_extension(),
       file_size: base_info.file_size(),
+      file_count: None,
       is_directory: base_info.is_directory(),
       file_icon: None,
       date: base_info.date(),
+      modified_time: base_info.modified_time(),
+      content_match: None,
     }
   }
 }
@@ -97,17 +147,18 @@ pub struct FileListFullInfo {
 
 impl FileListFullInfo {
   fn new(
-    dirctry_path: &String,
+    dirctry_path: &str,
     initial_focus: Option<String>,
-  ) -> Option<FileListFullInfo> {
-    let Some(file_list) = get_file_list_ex(&dirctry_path) else {
-      return None;
-    };
+    sort_spec: &SortSpec,
+    recursive_listing: Option<&RecursiveListing>,
+  ) -> Result<FileListFullInfo, AccessError> {
+    let file_list = get_file_list_ex(dirctry_path, recursive_listing)?;
 
-    let file_list = file_list
+    let mut file_list = file_list
       .iter()
       .map(|file_data| FileListItem::new(file_data, false))
       .collect::<Vec<_>>();
+    sort_spec.sort(&mut file_list);
 
     let focus_idx = initial_focus
       .and_then(|initial_focus| {
@@ -117,7 +168,7 @@ impl FileListFullInfo {
       })
       .unwrap_or(0);
 
-    Some(FileListFullInfo {
+    Ok(FileListFullInfo {
       filtered_item_info: (0..file_list.len())
         .map(|org_idx| FilterdFileInfo {
           org_idx,
@@ -136,9 +187,38 @@ impl FileListFullInfo {
       .map(|item| self.full_item_list[item.org_idx].file_name.clone())
   }
 
-  fn to_ui_info(self: &FileListFullInfo) -> FileListUiInfo {
-    let filtered_item_list = self
+  /// 要素の状態が変わった時に、並びを変えずにフィルタをかけ直す。
+  /// フォーカスしていた要素が無くなれば、同じ位置をフォーカスする。
+  fn refilter(
+    self: &mut FileListFullInfo,
+    filter: &FilterInfo,
+  ) {
+    let focus_file_name = self.focus_file_name();
+    self.filtered_item_info = to_filtered_item_info(0..self.full_item_list.len(), &self.full_item_list, filter);
+    let found_idx = focus_file_name.and_then(|focus_file_name| {
+      self
+        .filtered_item_info
+        .iter()
+        .position(|item| self.full_item_list[item.org_idx].file_name == focus_file_name)
+    });
+    let last_idx = self.filtered_item_info.len().saturating_sub(1);
+    self.focus_idx = found_idx.unwrap_or(self.focus_idx.min(last_idx));
+  }
+
+  fn selected_item_num(self: &FileListFullInfo) -> usize {
+    self
       .filtered_item_info
+      .iter()
+      .filter(|item| self.full_item_list[item.org_idx].is_selected)
+      .count()
+  }
+
+  fn to_ui_info(
+    self: &FileListFullInfo,
+    version: u64,
+    window: &std::ops::Range<usize>,
+  ) -> FileListUiInfo {
+    let filtered_item_list = self.filtered_item_info[window.clone()]
       .iter()
       .map(|item| FileListFilteredItem {
         file_list_item: self.full_item_list[item.org_idx].clone(),
@@ -146,7 +226,11 @@ impl FileListFullInfo {
       })
       .collect::<Vec<_>>();
     FileListUiInfo {
+      version,
       full_item_num: self.full_item_list.len(),
+      filtered_item_num: self.filtered_item_info.len(),
+      selected_item_num: self.selected_item_num(),
+      item_range_stt: window.start,
       filtered_item_list,
       focus_idx: self.focus_idx,
     }
@@ -181,7 +265,7 @@ fn to_filtered_item_info(
 ) -> Vec<FilterdFileInfo> {
   idx_range
     .filter_map(|idx| {
-      let match_result = filter.is_match(&full_item_list[idx].file_name);
+      let match_result = filter.is_match(&full_item_list[idx]);
       match_result.map(|matched_file_name_idx| FilterdFileInfo {
         org_idx: idx,
         matched_file_name_idx,
@@ -198,6 +282,7 @@ pub struct PaneHandler {
   ui_operation_required: Mutex<bool>,
 }
 pub struct PaneInfoForUiOperation<'a> {
+  pane_idx: usize,
   guard: MutexGuard<'a, PaneInfo>,
   ui_operation_required_ref: &'a Mutex<bool>,
 }
@@ -205,6 +290,8 @@ pub struct PaneInfoForUiOperation<'a> {
 impl<'a> Drop for PaneInfoForUiOperation<'a> {
   fn drop(&mut self) {
     *self.ui_operation_required_ref.lock().unwrap() = false;
+    // 表示範囲の要素が変わっているかもしれないので、アイコンを取り直す。
+    request_icon_update(self.pane_idx);
   }
 }
 
@@ -212,13 +299,13 @@ impl<'a> std::ops::Deref for PaneInfoForUiOperation<'a> {
   type Target = PaneInfo;
 
   fn deref(&self) -> &Self::Target {
-    &*self.guard
+    &self.guard
   }
 }
 
 impl<'a> std::ops::DerefMut for PaneInfoForUiOperation<'a> {
   fn deref_mut(&mut self) -> &mut Self::Target {
-    &mut *self.guard
+    &mut self.guard
   }
 }
 
@@ -227,6 +314,7 @@ impl PaneHandler {
     *self.ui_operation_required.lock().unwrap() = true;
     let guard = self.data.lock().unwrap();
     PaneInfoForUiOperation {
+      pane_idx: self.pane_idx,
       guard,
       ui_operation_required_ref: &self.ui_operation_required,
     }
@@ -254,6 +342,13 @@ pub struct PaneInfo {
   filter: FilterInfo,
   viewing_idx_range: std::ops::Range<usize>,
   file_list_info: Option<FileListFullInfo>,
+  ui_version: u64,
+  ui_snapshot: Option<FileListSnapshot>, // フロントエンドに最後に送った一覧
+  history: NavigationHistory,
+  sort_spec: SortSpec,
+  calc_directory_size: bool,
+  filter_retention: FilterRetention,
+  recursive_listing: Option<RecursiveListing>,
 }
 impl PaneInfo {
   fn new() -> Self {
@@ -262,19 +357,120 @@ impl PaneInfo {
       filter: FilterInfo::new(),
       file_list_info: None,
       viewing_idx_range: 0..0,
+      ui_version: 0,
+      ui_snapshot: None,
+      history: NavigationHistory::default(),
+      sort_spec: SortSpec::default(),
+      calc_directory_size: false,
+      filter_retention: FilterRetention::default(),
+      recursive_listing: None,
     }
   }
 
-  fn to_ui_info(self: &PaneInfo) -> Option<FileListUiInfo> {
-    self.file_list_info.as_ref().map(|item| item.to_ui_info())
+  /// フロントエンドに送る範囲。
+  fn ui_window(self: &PaneInfo) -> std::ops::Range<usize> {
+    let Some(file_list_info) = &self.file_list_info else {
+      return 0..0;
+    };
+    let prev_window = self
+      .ui_snapshot
+      .as_ref()
+      .map(|snapshot| snapshot.window())
+      .unwrap_or(0..0);
+    calc_ui_window(
+      &prev_window,
+      &self.viewing_idx_range,
+      file_list_info.focus_idx,
+      file_list_info.filtered_item_info.len(),
+    )
+  }
+
+  /// 表示範囲付近の一覧をフロントエンドに送る時に使う。差分の基準も更新する。
+  fn to_ui_info(self: &mut PaneInfo) -> Option<FileListUiInfo> {
+    let window = self.ui_window();
+    self.ui_version += 1;
+    self.ui_snapshot = self
+      .file_list_info
+      .as_ref()
+      .map(|item| FileListSnapshot::new(item, &window));
+    self
+      .file_list_info
+      .as_ref()
+      .map(|item| item.to_ui_info(self.ui_version, &window))
+  }
+
+  /// 前回送った一覧からの更新内容。変化が無ければ None。
+  fn to_ui_update(
+    self: &mut PaneInfo,
+    pane_idx: usize,
+  ) -> Option<FileListUiUpdate> {
+    let (Some(prev), Some(file_list_info)) = (&self.ui_snapshot, &self.file_list_info) else {
+      if self.ui_snapshot.is_none() && self.file_list_info.is_none() {
+        return None;
+      }
+      return Some(FileListUiUpdate::Full(self.to_ui_info()));
+    };
+
+    let window = self.ui_window();
+    let delta = calc_delta(pane_idx, self.ui_version, prev, file_list_info, &window);
+    match delta {
+      None => Some(FileListUiUpdate::Full(self.to_ui_info())),
+      Some(delta) if delta.is_empty(prev) => None,
+      Some(delta) => {
+        self.ui_version = delta.version();
+        self.ui_snapshot = Some(FileListSnapshot::new(file_list_info, &window));
+        Some(FileListUiUpdate::Delta(delta))
+      }
+    }
   }
 }
 
+/// 余分に送っておく行数。この範囲内のスクロールでは、再取得が不要になる。
+const UI_WINDOW_MARGIN: usize = 100;
+
+/// 表示範囲とフォーカス位置を含む範囲を返す。
+/// 前回の範囲で足りている間は、前回の範囲を使い続ける。
+fn calc_ui_window(
+  prev_window: &std::ops::Range<usize>,
+  viewing_idx_range: &std::ops::Range<usize>,
+  focus_idx: usize,
+  item_num: usize,
+) -> std::ops::Range<usize> {
+  let page = viewing_idx_range.len().max(1);
+  // 表示範囲の終端は、最後に表示している行を指している。
+  let viewing_end = viewing_idx_range.end + 1;
+  let focus_is_near = viewing_idx_range.start <= focus_idx + page && focus_idx < viewing_end + page;
+  let required = if focus_is_near {
+    viewing_idx_range.start.min(focus_idx)..viewing_end.max(focus_idx + 1)
+  } else {
+    // フロントエンドはフォーカス位置にスクロールするので、その周辺を送る。
+    focus_idx.saturating_sub(page)..focus_idx + page + 1
+  };
+  let required = required.start.min(item_num)..required.end.min(item_num);
+
+  let prev_window = prev_window.start.min(item_num)..prev_window.end.min(item_num);
+  let prev_is_enough = prev_window.start <= required.start
+    && required.end <= prev_window.end
+    && !prev_window.is_empty();
+  if prev_is_enough {
+    return prev_window;
+  }
+
+  required.start.saturating_sub(UI_WINDOW_MARGIN)..(required.end + UI_WINDOW_MARGIN).min(item_num)
+}
+
+enum FileListUiUpdate {
+  Full(Option<FileListUiInfo>),
+  Delta(FileListDelta),
+}
+
 #[derive(Debug)]
 pub struct FilerData {
   ignore_system_file: Mutex<bool>,
   background: Mutex<Color>,
-  pane_info_list: [PaneHandler; 2],
+  /// ペインの id → ペイン。id は閉じた後も再利用しない。
+  pane_info_list: Mutex<HashMap<usize, Arc<PaneHandler>>>,
+  next_pane_idx: Mutex<usize>,
 }
 
 impl FilerData {
@@ -282,16 +478,62 @@ impl FilerData {
     Self {
       ignore_system_file: Mutex::new(true),
       background: Mutex::new(Color { r: 0, g: 0, b: 0 }),
-      pane_info_list: [PaneHandler::new(0), PaneHandler::new(1)],
+      pane_info_list: Mutex::new(HashMap::new()),
+      next_pane_idx: Mutex::new(0),
     }
   }
 
   fn get_background(self: &FilerData) -> Color {
     self.background.lock().unwrap().clone()
   }
+
+  fn pane_handler(
+    self: &FilerData,
+    pane_idx: usize,
+  ) -> Result<Arc<PaneHandler>, AccessError> {
+    self
+      .pane_info_list
+      .lock()
+      .unwrap()
+      .get(&pane_idx)
+      .cloned()
+      .ok_or(AccessError::PaneNotFound { pane_idx })
+  }
+
+  fn pane_handler_list(self: &FilerData) -> Vec<Arc<PaneHandler>> {
+    self.pane_info_list.lock().unwrap().values().cloned().collect()
+  }
+}
+
+static PANE_DATA: Lazy<FilerData> = Lazy::new(FilerData::new);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// ペインを作り、その id を返す。他のコマンドには、この id を `pane_idx` として渡す。
+#[tauri::command]
+pub fn create_pane() -> usize {
+  let mut next_pane_idx = PANE_DATA.next_pane_idx.lock().unwrap();
+  let pane_idx = *next_pane_idx;
+  *next_pane_idx += 1;
+
+  PANE_DATA
+    .pane_info_list
+    .lock()
+    .unwrap()
+    .insert(pane_idx, Arc::new(PaneHandler::new(pane_idx)));
+  pane_idx
 }
 
-static PANE_DATA: Lazy<FilerData> = Lazy::new(|| FilerData::new());
+#[tauri::command]
+pub fn close_pane(pane_idx: usize) -> Result<(), AccessError> {
+  let removed = PANE_DATA.pane_info_list.lock().unwrap().remove(&pane_idx);
+  if removed.is_none() {
+    return Err(AccessError::PaneNotFound { pane_idx });
+  }
+  unwatch_directory(pane_idx);
+  cancel_directory_size_job(pane_idx);
+  cancel_content_search_job(pane_idx);
+  Ok(())
+}
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 #[tauri::command]
@@ -310,35 +552,60 @@ pub fn set_ignore_system_file(
 
 #[tauri::command]
 pub fn is_ignore_system_file() -> bool {
-  PANE_DATA.ignore_system_file.lock().unwrap().clone()
+  *PANE_DATA.ignore_system_file.lock().unwrap()
 }
 
 #[tauri::command]
+/// 送信済みの範囲から外れた場合は、新しい範囲の一覧を返す。
 pub fn set_viewing_idx_range(
   pane_idx: usize,
   range_stt: usize,
   range_end: usize,
-) {
-  let mut pane_info = PANE_DATA.pane_info_list[pane_idx].data.lock().unwrap();
+) -> Result<Option<FileListUiInfo>, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let mut pane_info = pane_handler.data.lock().unwrap();
+  if pane_info.viewing_idx_range == (range_stt..range_end) {
+    return Ok(None);
+  }
   pane_info.viewing_idx_range = range_stt..range_end;
+  request_icon_update(pane_idx);
+
+  let Some(sent_window) = pane_info.ui_snapshot.as_ref().map(|snapshot| snapshot.window()) else {
+    return Ok(None);
+  };
+  if pane_info.ui_window() == sent_window {
+    return Ok(None);
+  }
+  Ok(pane_info.to_ui_info())
 }
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
+/// 差分を適用できなくなった時に、一覧全体を取り直す為に使う。
+#[tauri::command]
+pub fn get_file_list_ui_info(pane_idx: usize) -> Result<Option<FileListUiInfo>, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let mut pane_info = pane_handler.get_info_for_ui_operation();
+  Ok(pane_info.to_ui_info())
+}
+
 #[tauri::command]
 pub fn set_dirctry_path(
+  app_handle: tauri::AppHandle,
   pane_idx: usize,
   path: &str,
   initial_focus: Option<String>,
-) -> Option<FileListUiInfo> {
-  let mut pane_info = PANE_DATA.pane_info_list[pane_idx].get_info_for_ui_operation();
+) -> Result<Option<FileListUiInfo>, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let mut pane_info = pane_handler.get_info_for_ui_operation();
 
   if pane_info.dirctry_path == path {
     // パスの変更が無ければ、選択要素のみを変更する。
+    // 開けなかったディレクトリは、開ける様になったら更新で表示される。
     let Some(ref mut file_list_info) = &mut pane_info.file_list_info else {
-      return None;
+      return Ok(None);
     };
     let Some(initial_focus) = initial_focus else {
-      return Some(file_list_info.to_ui_info());
+      return Ok(pane_info.to_ui_info());
     };
 
     let new_idx = file_list_info
@@ -347,46 +614,125 @@ pub fn set_dirctry_path(
       .position(|item| file_list_info.full_item_list[item.org_idx].file_name == initial_focus);
 
     file_list_info.focus_idx = new_idx.unwrap_or(file_list_info.focus_idx);
-    return Some(file_list_info.to_ui_info());
+    return Ok(pane_info.to_ui_info());
   }
 
   let path = path.to_string();
-  let file_list_info = FileListFullInfo::new(&path, initial_focus);
+  // 開けなくてもパスは変更しておき、開ける様になったら更新で表示する。
+  let (file_list_info, access_error) =
+    match FileListFullInfo::new(
+      &path,
+      initial_focus.clone(),
+      &pane_info.sort_spec,
+      pane_info.recursive_listing.as_ref(),
+    ) {
+      Ok(file_list_info) => (Some(file_list_info), None),
+      Err(access_error) => (None, Some(access_error)),
+    };
+  watch_directory(pane_idx, &path, pane_info.recursive_listing.is_some());
+
+  let mut history = std::mem::take(&mut pane_info.history);
+  history.push(&pane_info);
+  remember_recent_filter(&pane_info.filter);
+  remember_directory_filter(&pane_info);
+  let filter = filter_for_directory(&pane_info, &path);
 
   *pane_info = PaneInfo {
     dirctry_path: path,
     filter: FilterInfo::new(),
     file_list_info,
     viewing_idx_range: 0..0,
+    ui_version: pane_info.ui_version,
+    ui_snapshot: None,
+    history,
+    sort_spec: pane_info.sort_spec.clone(),
+    calc_directory_size: pane_info.calc_directory_size,
+    filter_retention: pane_info.filter_retention,
+    recursive_listing: pane_info.recursive_listing.clone(),
   };
-  pane_info.to_ui_info()
+  if !filter.is_empty() {
+    apply_filter(&mut pane_info, filter);
+    if let Some(initial_focus) = initial_focus {
+      focus_by_name(&mut pane_info, &initial_focus);
+    }
+  }
+  restart_directory_size_job(&app_handle, pane_idx, &pane_info);
+  restart_content_search_job(&app_handle, pane_idx, &pane_info);
+
+  if let Some(access_error) = access_error {
+    return Err(access_error);
+  }
+  Ok(pane_info.to_ui_info())
 }
 
 #[tauri::command]
 pub fn set_focus_idx(
   pane_idx: usize,
   new_focus_idx: usize,
-) -> Option<FileListUiInfo> {
-  let mut pane_info = PANE_DATA.pane_info_list[pane_idx].get_info_for_ui_operation();
+) -> Result<Option<FileListUiInfo>, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let mut pane_info = pane_handler.get_info_for_ui_operation();
 
   let Some(file_list_info) = &mut pane_info.file_list_info else {
-    return None;
+    return Ok(None);
   };
 
   file_list_info.focus_idx = new_focus_idx;
-  pane_info.to_ui_info()
+  Ok(pane_info.to_ui_info())
 }
 
 #[tauri::command]
 pub fn set_filter(
+  app_handle: tauri::AppHandle,
   pane_idx: usize,
   filter: FilterInfo,
-) -> Option<FileListUiInfo> {
-  let mut pane_info = PANE_DATA.pane_info_list[pane_idx].get_info_for_ui_operation();
+) -> Result<Option<FileListUiInfo>, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let mut pane_info = pane_handler.get_info_for_ui_operation();
+
+  // 履歴から戻った時等、フィルタに変更が無ければフォーカス位置を保つ。
+  if pane_info.filter == filter {
+    return Ok(pane_info.to_ui_info());
+  }
+
+  clear_content_match(&mut pane_info);
+  apply_filter(&mut pane_info, filter);
+  restart_content_search_job(&app_handle, pane_idx, &pane_info);
+  Ok(pane_info.to_ui_info())
+}
+
+#[tauri::command]
+pub fn get_filter(pane_idx: usize) -> Result<FilterInfo, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let pane_info = pane_handler.data.lock().unwrap();
+  Ok(pane_info.filter.clone())
+}
+
+/// 見つからなければフォーカスは変えない。
+fn focus_by_name(
+  pane_info: &mut PaneInfo,
+  file_name: &str,
+) {
+  let Some(file_list_info) = &mut pane_info.file_list_info else {
+    return;
+  };
+  let found_idx = file_list_info
+    .filtered_item_info
+    .iter()
+    .position(|item| file_list_info.full_item_list[item.org_idx].file_name == file_name);
+  if let Some(found_idx) = found_idx {
+    file_list_info.focus_idx = found_idx;
+  }
+}
 
+/// フィルタを設定し、一致度の高い順に並べ直す。フォーカスは先頭に移る。
+fn apply_filter(
+  pane_info: &mut PaneInfo,
+  filter: FilterInfo,
+) {
   let Some(file_list_info) = &mut pane_info.file_list_info else {
     pane_info.filter = filter;
-    return None;
+    return;
   };
 
   let full_item_list = std::mem::take(&mut file_list_info.full_item_list);
@@ -394,7 +740,7 @@ pub fn set_filter(
   let (full_item_list, matching_results) = full_item_list
     .into_iter()
     .map(|item| {
-      let matching_result = filter.is_match(&item.file_name);
+      let matching_result = filter.is_match(&item);
       (item, matching_result)
     })
     .sorted_by_key(|a| std::cmp::Reverse(matching_rate(&a.1)))
@@ -417,8 +763,17 @@ pub fn set_filter(
     filtered_item_info,
     focus_idx: 0,
   });
+}
 
-  pane_info.to_ui_info()
+/// `apply_filter` と同じく、一致度の高い順に並べる。一致度が同じ物の順は変えない。
+fn sort_by_matching_rate(
+  item_list: &mut [FileListItem],
+  filter: &FilterInfo,
+) {
+  if filter.is_empty() {
+    return;
+  }
+  item_list.sort_by_cached_key(|item| std::cmp::Reverse(matching_rate(&filter.is_match(item))));
 }
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
@@ -431,38 +786,41 @@ pub struct UpdateFileListUiInfo {
 pub fn update_file_list(app_handle: &tauri::AppHandle) {
   let background = PANE_DATA.get_background();
 
-  for pane_idx in 0..=1 {
-    update_pane_info(&PANE_DATA.pane_info_list[pane_idx], app_handle, &background);
+  for pane_handler in PANE_DATA.pane_handler_list() {
+    update_pane_info(&pane_handler, app_handle, &background, true);
   }
 }
 
+/// `rescan` が false の場合は、表示範囲のアイコンのみを更新する。
+/// UI 操作中で更新出来なかった場合は false を返す。
 fn update_pane_info(
   pane_handler: &PaneHandler,
   app_handle: &tauri::AppHandle,
   background: &Color,
-) {
+  rescan: bool,
+) -> bool {
   let Ok(mut pane_info) = pane_handler.data.try_lock() else {
-    return;
+    return false;
   };
 
-  update_file_name_list(&mut pane_info);
-  if pane_handler.ui_operation_required() {
-    return;
+  if rescan {
+    // 検索中の物は、そのまま続ける。追加や変更された物があれば、それも含めて検索し直す。
+    if update_file_name_list(&mut pane_info) {
+      restart_content_search_job(app_handle, pane_handler.pane_idx, &pane_info);
+    }
+    if pane_handler.ui_operation_required() {
+      return true;
+    }
+    emit_ui_update(app_handle, pane_handler.pane_idx, &mut pane_info);
   }
-  let _ = app_handle.emit(
-    "update_path_list",
-    UpdateFileListUiInfo {
-      pane_idx: pane_handler.pane_idx,
-      data: pane_info.to_ui_info(),
-    },
-  );
 
   let viewing_idx_range = pane_info.viewing_idx_range.clone();
   let dirctry_path = pane_info.dirctry_path.clone();
   let Some(file_list_info) = &mut pane_info.file_list_info else {
-    return;
+    return true;
   };
 
+  let mut icon_updated = false;
   for filterd_idx in viewing_idx_range {
     let Some(filtered_item_info) = file_list_info.filtered_item_info.get(filterd_idx) else {
       continue;
@@ -471,42 +829,58 @@ fn update_pane_info(
     let Some(file_list_item) = file_list_info.full_item_list.get_mut(org_idx) else {
       continue;
     };
+    if !rescan && file_list_item.file_icon.is_some() {
+      continue;
+    }
 
     let file_path = &PathBuf::from(&dirctry_path).join(&file_list_item.file_name);
-    file_list_item.file_icon = get_file_icon(file_path, &background);
+    file_list_item.file_icon = get_file_icon(file_path, background);
+    icon_updated = true;
 
     if pane_handler.ui_operation_required() {
-      return;
+      return true;
     }
   }
-  let _ = app_handle.emit(
-    "update_path_list",
-    UpdateFileListUiInfo {
-      pane_idx: pane_handler.pane_idx,
-      data: Some(file_list_info.to_ui_info()),
-    },
-  );
+  if icon_updated {
+    emit_ui_update(app_handle, pane_handler.pane_idx, &mut pane_info);
+  }
+  true
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct FileUpdateInfo {
-  directory_path: String,
-  file_name: String,
-  file_icon: Option<String>,
-  file_size: Option<String>,
-  date: Option<String>,
+/// 差分を送れる場合は `patch_path_list`、送れない場合は一覧全体を `update_path_list` で送る。
+fn emit_ui_update(
+  app_handle: &tauri::AppHandle,
+  pane_idx: usize,
+  pane_info: &mut PaneInfo,
+) {
+  match pane_info.to_ui_update(pane_idx) {
+    None => {}
+    Some(FileListUiUpdate::Full(data)) => {
+      let _ = app_handle.emit("update_path_list", UpdateFileListUiInfo { pane_idx, data });
+    }
+    Some(FileListUiUpdate::Delta(delta)) => {
+      let _ = app_handle.emit("patch_path_list", delta);
+    }
+  }
 }
 
-pub fn update_file_name_list(pane_info: &mut PaneInfo) {
+/// 追加された物か、内容が変わった物があれば true を返す。
+pub fn update_file_name_list(pane_info: &mut PaneInfo) -> bool {
   let Some(mut file_list_info) = std::mem::take(&mut pane_info.file_list_info) else {
-    let file_list_info = FileListFullInfo::new(&pane_info.dirctry_path, None);
+    let file_list_info = FileListFullInfo::new(
+      &pane_info.dirctry_path,
+      None,
+      &pane_info.sort_spec,
+      pane_info.recursive_listing.as_ref(),
+    )
+    .ok();
     pane_info.file_list_info = file_list_info;
-    return;
+    return pane_info.file_list_info.is_some();
   };
 
-  let Some(new_file_list) = get_file_list_ex(&pane_info.dirctry_path) else {
+  let Ok(new_file_list) = get_file_list_ex(&pane_info.dirctry_path, pane_info.recursive_listing.as_ref()) else {
     pane_info.file_list_info = None;
-    return;
+    return false;
   };
 
   let org_focus_file_name = file_list_info.focus_file_name();
@@ -519,13 +893,24 @@ pub fn update_file_name_list(pane_info: &mut PaneInfo) {
   // 既にある物の位置は変えない。
   // 新規の物を下に追加しする。
   // 新規がある場合は、新規の物のみを選択状態にする。
+  let mut is_modified = false;
   let mut remain = file_list_info
     .full_item_list
     .iter_mut()
     .filter_map(|item| {
       new_file_list_map.get(&item.file_name).map(|file| {
-        let mut new_item = FileListItem::new(&file, item.is_selected);
+        let mut new_item = FileListItem::new(file, item.is_selected);
         new_item.file_icon = item.file_icon.take();
+        if new_item.is_directory {
+          new_item.file_size = item.file_size;
+          new_item.file_count = item.file_count;
+        }
+        // 変更の無いファイルは、内容を検索し直さない。
+        if new_item.modified_time == item.modified_time && new_item.file_size == item.file_size {
+          new_item.content_match = item.content_match.take();
+        } else {
+          is_modified = true;
+        }
         new_item
       })
     })
@@ -539,7 +924,7 @@ pub fn update_file_name_list(pane_info: &mut PaneInfo) {
   let added = new_file_list
     .iter()
     .filter(|file| !remain_file_name_list.contains(&file.file_name))
-    .map(|file| FileListItem::new(&file, true))
+    .map(|file| FileListItem::new(file, true))
     .collect::<Vec<_>>();
 
   if !added.is_empty() {
@@ -548,7 +933,7 @@ pub fn update_file_name_list(pane_info: &mut PaneInfo) {
     }
   }
 
-  let full_item_list: Vec<_> = [&remain[..], &added[..]].concat();
+  let mut full_item_list: Vec<_> = [&remain[..], &added[..]].concat();
 
   let full_focus_idx = if !added.is_empty() {
     remain.len()
@@ -559,8 +944,8 @@ pub fn update_file_name_list(pane_info: &mut PaneInfo) {
     let full_matched_idx = remain
       .iter()
       .position(|item| item.file_name == org_focus_file_name);
-    if full_matched_idx.is_some() {
-      full_matched_idx.unwrap()
+    if let Some(full_matched_idx) = full_matched_idx {
+      full_matched_idx
     } else {
       // 選択要素が無くなっているケース。消えた要素分繰り上げる形にする。
       file_list_info
@@ -572,9 +957,25 @@ pub fn update_file_name_list(pane_info: &mut PaneInfo) {
     }
   };
 
+  // ソート指定があれば、新規の物も含めて並べ直す。
+  // フィルタで一致度順にしていれば、ソート指定はその次にする。
+  let full_focus_idx = if pane_info.sort_spec.is_active() {
+    let focus_file_name = full_item_list
+      .get(full_focus_idx)
+      .map(|item| item.file_name.clone());
+    pane_info.sort_spec.sort(&mut full_item_list);
+    sort_by_matching_rate(&mut full_item_list, &pane_info.filter);
+    focus_file_name
+      .and_then(|name| full_item_list.iter().position(|item| item.file_name == name))
+      .unwrap_or(0)
+  } else {
+    full_focus_idx
+  };
+
   pane_info.file_list_info = Some(FileListFullInfo::create(
     full_item_list,
     full_focus_idx,
     &pane_info.filter,
   ));
+  is_modified || !added.is_empty()
 }
@@ -0,0 +1,260 @@
+use std::{
+  collections::{HashMap, HashSet},
+  fs,
+  io::Read,
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+  },
+  time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::{emit_ui_update, filter_info::FilterInfo, PaneInfo, PANE_DATA};
+use crate::setting_file::{read_setting_file, write_setting_file};
+
+const SETTING_FILE_NAME: &str = "general/content_search.json";
+
+/// 検索結果をまとめて反映する間隔
+const FLUSH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 先頭のこのバイト数に NUL があれば、バイナリファイルとみなす。
+const BINARY_CHECK_LENGTH: usize = 8000;
+
+/// 一致した最初の行の、表示する最大文字数
+const MAX_FIRST_LINE_LENGTH: usize = 200;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct ContentSearchSetting {
+  /// これより大きいファイルは検索しない。
+  max_file_size: u64,
+}
+
+impl Default for ContentSearchSetting {
+  fn default() -> Self {
+    Self {
+      max_file_size: 16 * 1024 * 1024,
+    }
+  }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// ペイン毎の、実行中の検索の中断フラグ
+static JOBS: Lazy<Mutex<HashMap<usize, Arc<AtomicBool>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+static SETTING: Lazy<Mutex<ContentSearchSetting>> = Lazy::new(|| {
+  let setting = read_setting_file(SETTING_FILE_NAME)
+    .and_then(|content| serde_json::from_str(&content).ok())
+    .unwrap_or_default();
+  Mutex::new(setting)
+});
+
+/// ファイル内容の検索結果。
+/// 検索出来なかった(バイナリ、大き過ぎる、読めない)ファイルは `match_count` が 0 になる。
+#[derive(Debug, Serialize, Clone, PartialEq, Hash)]
+pub struct ContentMatch {
+  match_count: u64,
+  /// 最初に一致した行(1 始まり)
+  first_line_number: Option<u64>,
+  first_line: Option<String>,
+}
+
+impl ContentMatch {
+  pub(super) fn is_match(&self) -> bool {
+    self.match_count > 0
+  }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+#[tauri::command]
+pub fn set_content_search_max_file_size(max_file_size: u64) {
+  let mut setting = SETTING.lock().unwrap();
+  setting.max_file_size = max_file_size;
+  if let Ok(content) = serde_json::to_string_pretty(&*setting) {
+    write_setting_file(SETTING_FILE_NAME, &content);
+  }
+}
+
+#[tauri::command]
+pub fn get_content_search_max_file_size() -> u64 {
+  SETTING.lock().unwrap().max_file_size
+}
+
+/// 実行中の検索を中断し、内容検索のフィルタなら未検索のファイルを検索する。
+/// ペインのロック中に呼ぶ事。
+pub(crate) fn restart_content_search_job(
+  app_handle: &tauri::AppHandle,
+  pane_idx: usize,
+  pane_info: &PaneInfo,
+) {
+  cancel_content_search_job(pane_idx);
+  let Some(regex) = pane_info.filter.content_search_regex() else {
+    return;
+  };
+
+  // 表示範囲のファイルから検索する。
+  let Some(file_list_info) = &pane_info.file_list_info else {
+    return;
+  };
+  let viewing_file_names = file_list_info.filtered_item_info[pane_info.ui_window()]
+    .iter()
+    .map(|item| file_list_info.full_item_list[item.org_idx].file_name.as_str())
+    .collect::<HashSet<_>>();
+  let (visible, others) = file_list_info
+    .full_item_list
+    .iter()
+    .filter(|item| !item.is_directory && item.content_match.is_none())
+    .map(|item| item.file_name.clone())
+    .partition::<Vec<_>, _>(|file_name| viewing_file_names.contains(file_name.as_str()));
+  let target_list = visible.into_iter().chain(others).collect::<Vec<_>>();
+  if target_list.is_empty() {
+    return;
+  }
+
+  let cancelled = Arc::new(AtomicBool::new(false));
+  JOBS.lock().unwrap().insert(pane_idx, cancelled.clone());
+
+  let app_handle = app_handle.clone();
+  let dirctry_path = pane_info.dirctry_path.clone();
+  let filter = pane_info.filter.clone();
+  let max_file_size = SETTING.lock().unwrap().max_file_size;
+  std::thread::spawn(move || {
+    let target = SearchTarget {
+      pane_idx,
+      dirctry_path,
+      filter,
+    };
+    search(&app_handle, &target, &regex, max_file_size, &target_list, &cancelled);
+  });
+}
+
+pub(crate) fn cancel_content_search_job(pane_idx: usize) {
+  if let Some(cancelled) = JOBS.lock().unwrap().remove(&pane_idx) {
+    cancelled.store(true, Ordering::Relaxed);
+  }
+}
+
+/// フィルタを変えた時に、前の検索結果を捨てる。
+pub(crate) fn clear_content_match(pane_info: &mut PaneInfo) {
+  let Some(file_list_info) = &mut pane_info.file_list_info else {
+    return;
+  };
+  for item in file_list_info.full_item_list.iter_mut() {
+    item.content_match = None;
+  }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// 検索開始時のペインの状態。変わっていれば結果を捨てる。
+struct SearchTarget {
+  pane_idx: usize,
+  dirctry_path: String,
+  filter: FilterInfo,
+}
+
+fn search(
+  app_handle: &tauri::AppHandle,
+  target: &SearchTarget,
+  regex: &Regex,
+  max_file_size: u64,
+  target_list: &[String],
+  cancelled: &AtomicBool,
+) {
+  let mut results = Vec::new();
+  let mut last_flush = Instant::now();
+  for file_name in target_list {
+    if cancelled.load(Ordering::Relaxed) {
+      return;
+    }
+    let path = PathBuf::from(&target.dirctry_path).join(file_name);
+    results.push((file_name.clone(), search_file(&path, regex, max_file_size)));
+
+    if last_flush.elapsed() >= FLUSH_INTERVAL {
+      if !flush(app_handle, target, &mut results, cancelled) {
+        return;
+      }
+      last_flush = Instant::now();
+    }
+  }
+  flush(app_handle, target, &mut results, cancelled);
+}
+
+fn search_file(
+  path: &Path,
+  regex: &Regex,
+  max_file_size: u64,
+) -> ContentMatch {
+  let not_searched = ContentMatch {
+    match_count: 0,
+    first_line_number: None,
+    first_line: None,
+  };
+  let Ok(meta_data) = fs::metadata(path) else {
+    return not_searched;
+  };
+  if meta_data.len() > max_file_size {
+    return not_searched;
+  }
+  let Ok(mut file) = fs::File::open(path) else {
+    return not_searched;
+  };
+  let mut content = Vec::new();
+  if file.by_ref().take(max_file_size).read_to_end(&mut content).is_err() {
+    return not_searched;
+  }
+  if content.iter().take(BINARY_CHECK_LENGTH).any(|byte| *byte == 0) {
+    return not_searched;
+  }
+
+  let content = String::from_utf8_lossy(&content);
+  let mut result = not_searched;
+  for (line_idx, line) in content.lines().enumerate() {
+    let count = regex.find_iter(line).count() as u64;
+    if count == 0 {
+      continue;
+    }
+    if result.first_line.is_none() {
+      result.first_line_number = Some(line_idx as u64 + 1);
+      result.first_line = Some(line.trim().chars().take(MAX_FIRST_LINE_LENGTH).collect());
+    }
+    result.match_count += count;
+  }
+  result
+}
+
+/// 検索結果を一覧に反映する。続けるべきでない場合は false を返す。
+fn flush(
+  app_handle: &tauri::AppHandle,
+  target: &SearchTarget,
+  results: &mut Vec<(String, ContentMatch)>,
+  cancelled: &AtomicBool,
+) -> bool {
+  let Ok(pane_handler) = PANE_DATA.pane_handler(target.pane_idx) else {
+    return false;
+  };
+  let mut pane_info = pane_handler.data.lock().unwrap();
+  if cancelled.load(Ordering::Relaxed)
+    || pane_info.dirctry_path != target.dirctry_path
+    || pane_info.filter != target.filter
+  {
+    return false;
+  }
+
+  let mut results = std::mem::take(results).into_iter().collect::<HashMap<_, _>>();
+  let filter = pane_info.filter.clone();
+  let Some(file_list_info) = &mut pane_info.file_list_info else {
+    return false;
+  };
+  for item in file_list_info.full_item_list.iter_mut() {
+    if let Some(content_match) = results.remove(&item.file_name) {
+      item.content_match = Some(content_match);
+    }
+  }
+  file_list_info.refilter(&filter);
+
+  emit_ui_update(app_handle, target.pane_idx, &mut pane_info);
+  true
+}
@@ -0,0 +1,196 @@
+use std::{
+  collections::HashMap,
+  fs,
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+  },
+  time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+
+use super::{
+  emit_ui_update,
+  sort::{apply_sort_spec, SortKey},
+  PaneInfo, PANE_DATA,
+};
+use crate::access_error::AccessError;
+
+/// 計算結果をまとめて反映する間隔
+const FLUSH_INTERVAL: Duration = Duration::from_millis(200);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// ペイン毎の、実行中の計算の中断フラグ
+static JOBS: Lazy<Mutex<HashMap<usize, Arc<AtomicBool>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, Default)]
+struct DirectorySize {
+  size: u64,
+  file_count: u64,
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+#[tauri::command]
+pub fn set_directory_size_enabled(
+  app_handle: tauri::AppHandle,
+  pane_idx: usize,
+  enabled: bool,
+) -> Result<(), AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let mut pane_info = pane_handler.data.lock().unwrap();
+  if pane_info.calc_directory_size == enabled {
+    return Ok(());
+  }
+  pane_info.calc_directory_size = enabled;
+  restart_directory_size_job(&app_handle, pane_idx, &pane_info);
+  Ok(())
+}
+
+#[tauri::command]
+pub fn is_directory_size_enabled(pane_idx: usize) -> Result<bool, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let pane_info = pane_handler.data.lock().unwrap();
+  Ok(pane_info.calc_directory_size)
+}
+
+/// 実行中の計算を中断し、有効なら表示中のディレクトリで計算し直す。
+/// ペインのロック中に呼ぶ事。
+pub(crate) fn restart_directory_size_job(
+  app_handle: &tauri::AppHandle,
+  pane_idx: usize,
+  pane_info: &PaneInfo,
+) {
+  cancel_directory_size_job(pane_idx);
+  if !pane_info.calc_directory_size {
+    return;
+  }
+
+  // 表示範囲のディレクトリから計算する。
+  let Some(file_list_info) = &pane_info.file_list_info else {
+    return;
+  };
+  let (visible, others) = file_list_info
+    .filtered_item_info
+    .iter()
+    .enumerate()
+    .map(|(filtered_idx, item)| (filtered_idx, &file_list_info.full_item_list[item.org_idx]))
+    .filter(|(_, item)| item.is_directory)
+    .partition::<Vec<_>, _>(|(filtered_idx, _)| pane_info.viewing_idx_range.contains(filtered_idx));
+  let target_list = visible
+    .into_iter()
+    .chain(others)
+    .map(|(_, item)| item.file_name.clone())
+    .collect::<Vec<_>>();
+  if target_list.is_empty() {
+    return;
+  }
+
+  let cancelled = Arc::new(AtomicBool::new(false));
+  JOBS.lock().unwrap().insert(pane_idx, cancelled.clone());
+
+  let app_handle = app_handle.clone();
+  let dirctry_path = pane_info.dirctry_path.clone();
+  std::thread::spawn(move || {
+    calc_directory_size(&app_handle, pane_idx, &dirctry_path, &target_list, &cancelled);
+  });
+}
+
+pub(crate) fn cancel_directory_size_job(pane_idx: usize) {
+  if let Some(cancelled) = JOBS.lock().unwrap().remove(&pane_idx) {
+    cancelled.store(true, Ordering::Relaxed);
+  }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+fn calc_directory_size(
+  app_handle: &tauri::AppHandle,
+  pane_idx: usize,
+  dirctry_path: &str,
+  target_list: &[String],
+  cancelled: &AtomicBool,
+) {
+  let mut results = Vec::new();
+  let mut last_flush = Instant::now();
+  for file_name in target_list {
+    let path = PathBuf::from(dirctry_path).join(file_name);
+    let Some(size) = walk_directory(&path, cancelled) else {
+      return;
+    };
+    results.push((file_name.clone(), size));
+
+    if last_flush.elapsed() >= FLUSH_INTERVAL {
+      if !flush(app_handle, pane_idx, dirctry_path, &mut results, cancelled) {
+        return;
+      }
+      last_flush = Instant::now();
+    }
+  }
+  flush(app_handle, pane_idx, dirctry_path, &mut results, cancelled);
+}
+
+/// 中断された場合は None を返す。
+/// シンボリックリンクは辿らない。読めないディレクトリは無視する。
+fn walk_directory(
+  path: &Path,
+  cancelled: &AtomicBool,
+) -> Option<DirectorySize> {
+  let mut result = DirectorySize::default();
+  let mut stack = vec![path.to_path_buf()];
+  while let Some(dir) = stack.pop() {
+    let Ok(read_dir) = fs::read_dir(&dir) else {
+      continue;
+    };
+    for entry in read_dir.flatten() {
+      if cancelled.load(Ordering::Relaxed) {
+        return None;
+      }
+      let Ok(file_type) = entry.file_type() else {
+        continue;
+      };
+      if file_type.is_dir() {
+        stack.push(entry.path());
+        continue;
+      }
+      result.file_count += 1;
+      result.size += entry.metadata().map(|meta| meta.len()).unwrap_or(0);
+    }
+  }
+  Some(result)
+}
+
+/// 計算結果を一覧に反映する。続けるべきでない場合は false を返す。
+fn flush(
+  app_handle: &tauri::AppHandle,
+  pane_idx: usize,
+  dirctry_path: &str,
+  results: &mut Vec<(String, DirectorySize)>,
+  cancelled: &AtomicBool,
+) -> bool {
+  let Ok(pane_handler) = PANE_DATA.pane_handler(pane_idx) else {
+    return false;
+  };
+  let mut pane_info = pane_handler.data.lock().unwrap();
+  if cancelled.load(Ordering::Relaxed) || pane_info.dirctry_path != dirctry_path {
+    return false;
+  }
+
+  let results = std::mem::take(results).into_iter().collect::<HashMap<_, _>>();
+  let Some(file_list_info) = &mut pane_info.file_list_info else {
+    return false;
+  };
+  for item in file_list_info.full_item_list.iter_mut() {
+    let Some(size) = results.get(&item.file_name) else {
+      continue;
+    };
+    item.file_size = Some(size.size);
+    item.file_count = Some(size.file_count);
+  }
+
+  if pane_info.sort_spec.contains_key(SortKey::Size) {
+    apply_sort_spec(&mut pane_info);
+  }
+  emit_ui_update(app_handle, pane_idx, &mut pane_info);
+  true
+}
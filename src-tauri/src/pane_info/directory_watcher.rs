@@ -0,0 +1,303 @@
+use std::{
+  collections::{HashMap, HashSet},
+  path::{Path, PathBuf},
+  sync::{
+    mpsc::{self, Receiver, RecvTimeoutError, Sender},
+    Mutex,
+  },
+  time::{Duration, Instant},
+};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+
+use super::{update_pane_info, PANE_DATA};
+
+/// 監視できないディレクトリ(ドライブ一覧、一部のネットワークパス等)の更新間隔
+const POLLING_INTERVAL: Duration = Duration::from_secs(1);
+/// 通知が途切れるまで待つ時間
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(100);
+/// 通知が途切れなくても、この時間が経ったら更新する
+const MAX_UPDATE_DELAY: Duration = Duration::from_secs(1);
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+enum UpdateRequest {
+  /// ファイルシステムの変更通知
+  Changed(Vec<PathBuf>),
+  /// 表示範囲のアイコンの取得のみ
+  Icon(usize),
+}
+
+struct DirectoryWatcher {
+  watcher: Option<RecommendedWatcher>,
+  watching_path: HashMap<usize, PathBuf>,
+  /// 配下も再帰的に表示しているペイン
+  recursive_panes: HashSet<usize>,
+  /// パス毎に登録した監視の方法。同じパスを再帰と非再帰のペインが見ていれば、再帰で登録する。
+  registered_mode: HashMap<PathBuf, RecursiveMode>,
+  polling_panes: HashSet<usize>,
+  sender: Sender<UpdateRequest>,
+}
+
+static WATCHER: Lazy<Mutex<Option<DirectoryWatcher>>> = Lazy::new(|| Mutex::new(None));
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// 変更通知を受けて、ペインの一覧を更新するスレッドを起動する。
+pub fn start_directory_watcher(app_handle: tauri::AppHandle) {
+  let (sender, receiver) = mpsc::channel();
+
+  // 通知のコールバック内では、WATCHER をロックしない事。
+  // `watch` の完了待ちとデッドロックする為。
+  let event_sender = sender.clone();
+  let watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+    let Ok(event) = result else {
+      return;
+    };
+    if matches!(event.kind, EventKind::Access(_)) {
+      return;
+    }
+    let _ = event_sender.send(UpdateRequest::Changed(event.paths));
+  })
+  .ok();
+
+  *WATCHER.lock().unwrap() = Some(DirectoryWatcher {
+    watcher,
+    watching_path: HashMap::new(),
+    recursive_panes: HashSet::new(),
+    registered_mode: HashMap::new(),
+    polling_panes: HashSet::new(),
+    sender,
+  });
+
+  std::thread::spawn(move || update_loop(&app_handle, receiver));
+}
+
+/// ペインの表示ディレクトリを監視対象にする。
+/// 監視できない場合は、ポーリングでの更新に切り替える。
+/// `recursive` なら配下のディレクトリの変更も通知を受ける。
+pub(crate) fn watch_directory(
+  pane_idx: usize,
+  path: &str,
+  recursive: bool,
+) {
+  let mut guard = WATCHER.lock().unwrap();
+  let Some(directory_watcher) = guard.as_mut() else {
+    return;
+  };
+
+  directory_watcher.unwatch(pane_idx);
+
+  let path = PathBuf::from(path);
+  if path.as_os_str().is_empty() {
+    directory_watcher.polling_panes.insert(pane_idx);
+    return;
+  }
+
+  directory_watcher.watching_path.insert(pane_idx, path.clone());
+  if recursive {
+    directory_watcher.recursive_panes.insert(pane_idx);
+  }
+  if !directory_watcher.register(&path) {
+    directory_watcher.unwatch(pane_idx);
+    directory_watcher.polling_panes.insert(pane_idx);
+  }
+}
+
+/// 閉じたペインを監視対象から外す。
+pub(crate) fn unwatch_directory(pane_idx: usize) {
+  let mut guard = WATCHER.lock().unwrap();
+  if let Some(directory_watcher) = guard.as_mut() {
+    directory_watcher.unwatch(pane_idx);
+  }
+}
+
+pub(crate) fn request_icon_update(pane_idx: usize) {
+  send_request(UpdateRequest::Icon(pane_idx));
+}
+
+fn send_request(request: UpdateRequest) {
+  let guard = WATCHER.lock().unwrap();
+  if let Some(directory_watcher) = guard.as_ref() {
+    let _ = directory_watcher.sender.send(request);
+  }
+}
+
+impl DirectoryWatcher {
+  fn unwatch(
+    &mut self,
+    pane_idx: usize,
+  ) {
+    self.polling_panes.remove(&pane_idx);
+    self.recursive_panes.remove(&pane_idx);
+    let Some(path) = self.watching_path.remove(&pane_idx) else {
+      return;
+    };
+    self.register(&path);
+  }
+
+  /// `path` を見ているペインに合わせて、監視を登録し直す。誰も見ていなければ外す。
+  /// 必要な監視を登録出来なければ false を返す。
+  fn register(
+    &mut self,
+    path: &Path,
+  ) -> bool {
+    let watching_panes = self
+      .watching_path
+      .iter()
+      .filter(|(_, watching)| watching.as_path() == path)
+      .map(|(pane_idx, _)| *pane_idx)
+      .collect::<Vec<_>>();
+    let mode = if watching_panes.is_empty() {
+      None
+    } else if watching_panes.iter().any(|pane_idx| self.recursive_panes.contains(pane_idx)) {
+      Some(RecursiveMode::Recursive)
+    } else {
+      Some(RecursiveMode::NonRecursive)
+    };
+    let registered_mode = self.registered_mode.get(path).copied();
+    if mode == registered_mode {
+      return true;
+    }
+
+    let Some(watcher) = &mut self.watcher else {
+      return mode.is_none();
+    };
+    if registered_mode.is_some() {
+      let _ = watcher.unwatch(path);
+      self.registered_mode.remove(path);
+    }
+    let Some(mode) = mode else {
+      return true;
+    };
+    if watcher.watch(path, mode).is_err() {
+      return false;
+    }
+    self.registered_mode.insert(path.to_path_buf(), mode);
+    true
+  }
+
+  fn panes_for_changed_path(
+    &self,
+    changed_path: &Path,
+  ) -> Vec<usize> {
+    self
+      .watching_path
+      .iter()
+      .filter(|(pane_idx, watching)| {
+        if self.recursive_panes.contains(pane_idx) {
+          return changed_path.starts_with(watching);
+        }
+        changed_path.parent() == Some(watching.as_path()) || changed_path == watching.as_path()
+      })
+      .map(|(pane_idx, _)| *pane_idx)
+      .collect()
+  }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+#[derive(Default)]
+struct PendingUpdate {
+  rescan: HashSet<usize>,
+  icon: HashSet<usize>,
+  since: Option<Instant>,
+}
+
+impl PendingUpdate {
+  fn is_empty(&self) -> bool {
+    self.rescan.is_empty() && self.icon.is_empty()
+  }
+
+  fn add(
+    &mut self,
+    request: UpdateRequest,
+  ) {
+    match request {
+      UpdateRequest::Changed(paths) => {
+        let guard = WATCHER.lock().unwrap();
+        let Some(directory_watcher) = guard.as_ref() else {
+          return;
+        };
+        for path in paths {
+          self
+            .rescan
+            .extend(directory_watcher.panes_for_changed_path(&path));
+        }
+      }
+      UpdateRequest::Icon(pane_idx) => {
+        self.icon.insert(pane_idx);
+      }
+    }
+    if !self.is_empty() && self.since.is_none() {
+      self.since = Some(Instant::now());
+    }
+  }
+}
+
+fn update_loop(
+  app_handle: &tauri::AppHandle,
+  receiver: Receiver<UpdateRequest>,
+) {
+  let mut pending = PendingUpdate::default();
+  let mut last_polling = Instant::now();
+
+  loop {
+    let timeout = if pending.is_empty() {
+      POLLING_INTERVAL.saturating_sub(last_polling.elapsed())
+    } else {
+      DEBOUNCE_INTERVAL
+    };
+
+    match receiver.recv_timeout(timeout) {
+      Ok(request) => {
+        pending.add(request);
+        let waited_enough = pending
+          .since
+          .is_some_and(|since| since.elapsed() >= MAX_UPDATE_DELAY);
+        if !waited_enough {
+          continue;
+        }
+      }
+      Err(RecvTimeoutError::Timeout) => {}
+      Err(RecvTimeoutError::Disconnected) => return,
+    }
+
+    if last_polling.elapsed() >= POLLING_INTERVAL {
+      last_polling = Instant::now();
+      let guard = WATCHER.lock().unwrap();
+      if let Some(directory_watcher) = guard.as_ref() {
+        pending.rescan.extend(&directory_watcher.polling_panes);
+      }
+    }
+
+    pending = process_pending_update(app_handle, pending);
+  }
+}
+
+/// 処理できなかった(UI 操作中でロックが取れなかった)物を返す。
+fn process_pending_update(
+  app_handle: &tauri::AppHandle,
+  pending: PendingUpdate,
+) -> PendingUpdate {
+  let background = PANE_DATA.get_background();
+
+  let mut remain = PendingUpdate::default();
+  for pane_idx in pending.rescan.iter().chain(pending.icon.difference(&pending.rescan)) {
+    // 閉じたペインは無視する。
+    let Ok(pane_handler) = PANE_DATA.pane_handler(*pane_idx) else {
+      continue;
+    };
+    let rescan = pending.rescan.contains(pane_idx);
+    if update_pane_info(&pane_handler, app_handle, &background, rescan) {
+      continue;
+    }
+    if rescan {
+      remain.rescan.insert(*pane_idx);
+    } else {
+      remain.icon.insert(*pane_idx);
+    }
+  }
+  if !remain.is_empty() {
+    remain.since = pending.since;
+  }
+  remain
+}
@@ -0,0 +1,215 @@
+use std::{
+  collections::{hash_map::DefaultHasher, HashMap, HashSet},
+  hash::{Hash, Hasher},
+  ops::Range,
+};
+
+use itertools::Itertools;
+
+use super::{FileListFilteredItem, FileListFullInfo, FileListItem};
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// フロントエンドに最後に送った一覧の要約。差分の計算に使う。
+#[derive(Debug, Clone)]
+pub struct FileListSnapshot {
+  /// filtered_item_list の `item_range_stt` からの範囲。順番もそのまま。
+  entries: Vec<SnapshotEntry>,
+  item_range_stt: usize,
+  full_item_num: usize,
+  filtered_item_num: usize,
+  selected_item_num: usize,
+  focus_idx: usize,
+}
+
+#[derive(Debug, Clone)]
+struct SnapshotEntry {
+  file_name: String,
+  content_hash: u64,
+  is_selected: bool,
+}
+
+impl FileListSnapshot {
+  pub(super) fn new(
+    file_list_info: &FileListFullInfo,
+    window: &Range<usize>,
+  ) -> Self {
+    let entries = file_list_info.filtered_item_info[window.clone()]
+      .iter()
+      .map(|item| {
+        let file_list_item = &file_list_info.full_item_list[item.org_idx];
+        SnapshotEntry {
+          file_name: file_list_item.file_name.clone(),
+          content_hash: content_hash(file_list_item, &item.matched_file_name_idx),
+          is_selected: file_list_item.is_selected,
+        }
+      })
+      .collect();
+
+    Self {
+      entries,
+      item_range_stt: window.start,
+      full_item_num: file_list_info.full_item_list.len(),
+      filtered_item_num: file_list_info.filtered_item_info.len(),
+      selected_item_num: file_list_info.selected_item_num(),
+      focus_idx: file_list_info.focus_idx,
+    }
+  }
+
+  pub(super) fn window(&self) -> Range<usize> {
+    self.item_range_stt..self.item_range_stt + self.entries.len()
+  }
+}
+
+/// 選択状態以外の内容のハッシュ
+fn content_hash(
+  item: &FileListItem,
+  matched_idx_list: &[usize],
+) -> u64 {
+  let mut hasher = DefaultHasher::new();
+  item.file_name.hash(&mut hasher);
+  item.file_extension.hash(&mut hasher);
+  item.file_size.hash(&mut hasher);
+  item.file_count.hash(&mut hasher);
+  item.content_match.hash(&mut hasher);
+  item.is_directory.hash(&mut hasher);
+  item.file_icon.hash(&mut hasher);
+  item.date.hash(&mut hasher);
+  matched_idx_list.hash(&mut hasher);
+  hasher.finish()
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// `base_version` の一覧に適用すると、`version` の一覧になる差分。
+/// 要素はファイル名で識別する。
+#[derive(Debug, Serialize, Clone)]
+pub struct FileListDelta {
+  pane_idx: usize,
+  base_version: u64,
+  version: u64,
+  item_range_stt: usize,
+  full_item_num: usize,
+  filtered_item_num: usize,
+  selected_item_num: usize,
+  focus_idx: usize,
+  removed: Vec<String>,
+  /// `idx` は適用後の filtered_item_list(`item_range_stt` からの範囲)での位置。昇順。
+  added: Vec<AddedFileListItem>,
+  changed: Vec<FileListFilteredItem>,
+  selected: Vec<String>,
+  deselected: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AddedFileListItem {
+  idx: usize,
+  item: FileListFilteredItem,
+}
+
+impl FileListDelta {
+  pub(super) fn version(&self) -> u64 {
+    self.version
+  }
+
+  /// 要素に変化が無くても、件数やフォーカスが変わっていれば送る必要がある。
+  pub(super) fn is_empty(
+    &self,
+    prev: &FileListSnapshot,
+  ) -> bool {
+    self.removed.is_empty()
+      && self.added.is_empty()
+      && self.changed.is_empty()
+      && self.selected.is_empty()
+      && self.deselected.is_empty()
+      && prev.item_range_stt == self.item_range_stt
+      && prev.full_item_num == self.full_item_num
+      && prev.filtered_item_num == self.filtered_item_num
+      && prev.selected_item_num == self.selected_item_num
+      && prev.focus_idx == self.focus_idx
+  }
+}
+
+/// 残った要素の並びが変わっている場合(ソート等)は、差分では表せないので None を返す。
+pub(super) fn calc_delta(
+  pane_idx: usize,
+  base_version: u64,
+  prev: &FileListSnapshot,
+  current: &FileListFullInfo,
+  window: &Range<usize>,
+) -> Option<FileListDelta> {
+  let current_items = &current.filtered_item_info[window.clone()];
+  let current_names = current_items
+    .iter()
+    .map(|item| &current.full_item_list[item.org_idx].file_name)
+    .collect::<HashSet<_>>();
+  let prev_entries = prev
+    .entries
+    .iter()
+    .map(|entry| (&entry.file_name, entry))
+    .collect::<HashMap<_, _>>();
+
+  let remain_prev_order = prev
+    .entries
+    .iter()
+    .map(|entry| &entry.file_name)
+    .filter(|name| current_names.contains(name));
+  let remain_current_order = current_items
+    .iter()
+    .map(|item| &current.full_item_list[item.org_idx].file_name)
+    .filter(|name| prev_entries.contains_key(name));
+  if !remain_prev_order.eq(remain_current_order) {
+    return None;
+  }
+
+  let removed = prev
+    .entries
+    .iter()
+    .filter(|entry| !current_names.contains(&entry.file_name))
+    .map(|entry| entry.file_name.clone())
+    .collect_vec();
+
+  let mut added = Vec::new();
+  let mut changed = Vec::new();
+  let mut selected = Vec::new();
+  let mut deselected = Vec::new();
+  for (idx, item) in current_items.iter().enumerate() {
+    let file_list_item = &current.full_item_list[item.org_idx];
+    let filtered_item = || FileListFilteredItem {
+      file_list_item: file_list_item.clone(),
+      matched_idx_list: item.matched_file_name_idx.clone(),
+    };
+
+    let Some(prev_entry) = prev_entries.get(&file_list_item.file_name) else {
+      added.push(AddedFileListItem {
+        idx,
+        item: filtered_item(),
+      });
+      continue;
+    };
+
+    if prev_entry.content_hash != content_hash(file_list_item, &item.matched_file_name_idx) {
+      changed.push(filtered_item());
+    } else if prev_entry.is_selected != file_list_item.is_selected {
+      if file_list_item.is_selected {
+        selected.push(file_list_item.file_name.clone());
+      } else {
+        deselected.push(file_list_item.file_name.clone());
+      }
+    }
+  }
+
+  Some(FileListDelta {
+    pane_idx,
+    base_version,
+    version: base_version + 1,
+    item_range_stt: window.start,
+    full_item_num: current.full_item_list.len(),
+    filtered_item_num: current.filtered_item_info.len(),
+    selected_item_num: current.selected_item_num(),
+    focus_idx: current.focus_idx,
+    removed,
+    added,
+    changed,
+    selected,
+    deselected,
+  })
+}
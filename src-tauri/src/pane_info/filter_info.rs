@@ -1,41 +1,135 @@
 use std::borrow::Cow;
 use std::cmp::Ordering;
 
+use serde::{Deserialize, Deserializer};
+
 extern crate regex;
 use itertools::Itertools;
 use regex::Regex;
 
-#[derive(Debug, Deserialize, Clone, PartialEq)]
+use super::FileListItem;
+
+mod migemo;
+use migemo::migemo_match;
+pub mod query;
+use query::{parse_query, query_match, ParsedQuery};
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum FilterType {
   StrMatch,
   RegExpr,
+  /// RegExpr と同じ条件で、キャプチャグループの部分だけを強調する
+  RegExprCaptures,
+  Glob,
+  /// ローマ字をかな(と辞書の漢字)に展開して照合する
+  Migemo,
+  /// ファイル名以外の属性も条件に出来るクエリ。書式は query.rs を参照
+  Query,
+  /// ファイルの内容を正規表現で検索する。正規表現として不正なら文字列として検索する。
+  /// 検索はバックグラウンドで行い、結果が出たファイルから一覧に現れる。
+  Grep,
 }
 
-#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[derive(Debug, Serialize, Clone)]
 pub struct FilterInfo {
   filter_type: FilterType,
   matcher_str: String,
+  /// Query の解釈結果。作った時に 1 度だけ解釈する。解釈出来なければ None
+  #[serde(skip)]
+  query: Option<ParsedQuery>,
+}
+
+/// フロントエンドや設定ファイルから受け取る形。受け取った時にクエリを解釈する。
+#[derive(Deserialize)]
+struct FilterInfoSource {
+  filter_type: FilterType,
+  matcher_str: String,
+}
+
+impl<'de> Deserialize<'de> for FilterInfo {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let source = FilterInfoSource::deserialize(deserializer)?;
+    Ok(FilterInfo::with(source.filter_type, source.matcher_str))
+  }
+}
+
+/// 解釈結果は matcher_str から決まるので、比べない。
+impl PartialEq for FilterInfo {
+  fn eq(
+    &self,
+    other: &Self,
+  ) -> bool {
+    self.filter_type == other.filter_type && self.matcher_str == other.matcher_str
+  }
 }
 
 impl FilterInfo {
   pub(crate) fn new() -> Self {
+    Self::with(FilterType::StrMatch, "".to_string())
+  }
+
+  /// フィルタバー以外(Go to anywhere 等)で、文字列一致の照合を使う為のもの。
+  pub(crate) fn new_str_match(matcher_str: &str) -> Self {
+    Self::with(FilterType::StrMatch, matcher_str.to_owned())
+  }
+
+  fn with(
+    filter_type: FilterType,
+    matcher_str: String,
+  ) -> Self {
+    let query = match filter_type {
+      FilterType::Query => parse_query(&matcher_str).ok(),
+      _ => None,
+    };
     Self {
-      filter_type: FilterType::StrMatch,
-      matcher_str: "".to_string(),
+      filter_type,
+      matcher_str,
+      query,
+    }
+  }
+
+  pub(crate) fn is_empty(&self) -> bool {
+    self.matcher_str.is_empty()
+  }
+
+  /// 内容検索のフィルタなら、検索に使う正規表現を返す。
+  pub(crate) fn content_search_regex(&self) -> Option<Regex> {
+    if self.filter_type != FilterType::Grep || self.matcher_str.is_empty() {
+      return None;
     }
+    // 大文字を含まなければ、大文字小文字を区別しない。
+    let case_flag = if self.matcher_str.chars().any(|c| c.is_uppercase()) { "" } else { "(?i)" };
+    Regex::new(&format!("{}{}", case_flag, self.matcher_str))
+      .or_else(|_| Regex::new(&format!("{}{}", case_flag, regex::quote(&self.matcher_str))))
+      .ok()
   }
 }
 
+/// 一致した位置。全てのフィルタで、ファイル名の文字(char)単位で返す。
 type MatchResult = Option<Vec<usize>>;
 impl FilterInfo {
   pub(crate) fn is_match(
     &self,
-    target: &String,
+    item: &FileListItem,
   ) -> MatchResult {
     if self.matcher_str.is_empty() {
       return Some(Vec::new());
     }
+    if self.filter_type == FilterType::Query {
+      // 解釈出来ないクエリには、何も一致しない。
+      return self.query.as_ref().and_then(|query| query_match(query, item));
+    }
+    if self.filter_type == FilterType::Grep {
+      let is_match = item.content_match.as_ref().is_some_and(|content_match| content_match.is_match());
+      return is_match.then(Vec::new);
+    }
+    self.is_file_name_match(&item.file_name)
+  }
 
+  pub(crate) fn is_file_name_match(
+    &self,
+    target: &str,
+  ) -> MatchResult {
     let exist_upper_case = self.matcher_str.chars().any(|c| c.is_uppercase());
 
     let matcher_str = if !exist_upper_case {
@@ -45,7 +139,7 @@ impl FilterInfo {
     };
 
     let target = if !exist_upper_case {
-      Cow::Owned(target.to_lowercase())
+      Cow::Owned(to_lowercase_per_char(target))
     } else {
       Cow::Borrowed(target)
     };
@@ -53,39 +147,51 @@ impl FilterInfo {
     match self.filter_type {
       FilterType::StrMatch => str_match(&matcher_str, &target),
       FilterType::RegExpr => reg_expr_match(&matcher_str, &target),
+      FilterType::RegExprCaptures => reg_expr_captures_match(&matcher_str, &target),
+      FilterType::Glob => glob_match(&matcher_str, &target),
+      FilterType::Migemo => migemo_match(&matcher_str, &target),
+      FilterType::Query | FilterType::Grep => None,
     }
   }
 }
 
+/// 小文字にすると文字数が変わる文字(`İ` 等)は、位置がずれないようそのままにする。
+fn to_lowercase_per_char(target: &str) -> String {
+  target
+    .chars()
+    .map(|c| {
+      let mut lower = c.to_lowercase();
+      match (lower.next(), lower.next()) {
+        (Some(lower_char), None) => lower_char,
+        _ => c,
+      }
+    })
+    .collect()
+}
+
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
 fn str_match(
-  matcher_str: &String,
-  target: &String,
+  matcher_str: &str,
+  target: &str,
 ) -> MatchResult {
+  let matcher_chars = matcher_str.chars().collect_vec();
+  let target_chars = target.chars().collect_vec();
   let mut matched_idx_list: Vec<usize> = Vec::new();
 
-  let mut matcher_str_part = matcher_str.clone();
-  let mut target_part = target.clone();
-
-  while matcher_str_part.len() != 0 {
-    let Some(StrMatchPartResult {
+  let mut matcher_idx = 0;
+  let mut target_idx = 0;
+  while matcher_idx < matcher_chars.len() {
+    let StrMatchPartResult {
       match_start_idx,
       matching_length,
-    }) = str_match_part(&matcher_str_part, &target_part)
-    else {
-      return None;
-    };
+    } = str_match_part(&matcher_chars[matcher_idx..], &target_chars[target_idx..])?;
 
-    let diff = target.len() - target_part.len();
     let part_matched_range = match_start_idx..match_start_idx + matching_length;
-    let matched_range = part_matched_range.map(|idx| idx + diff);
+    let matched_range = part_matched_range.map(|idx| idx + target_idx);
     matched_idx_list.append(&mut matched_range.collect_vec());
-    matcher_str_part = matcher_str_part.chars().skip(matching_length).collect();
-    target_part = target_part
-      .chars()
-      .skip(match_start_idx + matching_length)
-      .collect();
+    matcher_idx += matching_length;
+    target_idx += match_start_idx + matching_length;
   }
 
   Some(matched_idx_list)
@@ -96,17 +202,14 @@ struct StrMatchPartResult {
   matching_length: usize,
 }
 fn str_match_part(
-  matcher_str: &String,
-  target: &String,
+  matcher_chars: &[char],
+  target_chars: &[char],
 ) -> Option<StrMatchPartResult> {
-  (1..=matcher_str.len()).rev().find_map(|matching_length| {
-    let part_matcher_str = matcher_str
-      .chars()
-      .take(matching_length)
-      .collect::<String>();
-
-    target
-      .find(&part_matcher_str)
+  (1..=matcher_chars.len()).rev().find_map(|matching_length| {
+    let part_matcher_chars = &matcher_chars[..matching_length];
+    target_chars
+      .windows(matching_length)
+      .position(|window| window == part_matcher_chars)
       .map(|match_start_idx| StrMatchPartResult {
         match_start_idx,
         matching_length,
@@ -116,18 +219,246 @@ fn str_match_part(
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
+/// 重ならない全ての一致を強調する。
 fn reg_expr_match(
-  matcher_str: &String,
-  target: &String,
+  matcher_str: &str,
+  target: &str,
 ) -> MatchResult {
-  let Ok(reg_exp) = Regex::new(matcher_str.as_str()) else {
+  let Ok(reg_exp) = Regex::new(matcher_str) else {
     return None;
   };
 
-  let Some(res) = reg_exp.find(target.as_str()) else {
+  let byte_range_list = reg_exp.find_iter(target).collect_vec();
+  if byte_range_list.is_empty() {
+    return None;
+  }
+  Some(to_char_idx_list(target, &byte_range_list))
+}
+
+/// 全ての一致のキャプチャグループを強調する。グループが無ければ一致全体を強調する。
+fn reg_expr_captures_match(
+  matcher_str: &str,
+  target: &str,
+) -> MatchResult {
+  let Ok(reg_exp) = Regex::new(matcher_str) else {
     return None;
   };
-  Some((res.0..res.1).collect::<Vec<usize>>())
+
+  let captures_list = reg_exp.captures_iter(target).collect_vec();
+  if captures_list.is_empty() {
+    return None;
+  }
+  let byte_range_list = captures_list
+    .iter()
+    .flat_map(|captures| {
+      let group_range_list = captures.iter_pos().skip(1).flatten().collect_vec();
+      if group_range_list.is_empty() {
+        captures.pos(0).into_iter().collect_vec()
+      } else {
+        group_range_list
+      }
+    })
+    .collect_vec();
+  Some(to_char_idx_list(target, &byte_range_list))
+}
+
+/// バイト単位の範囲を、文字単位の位置に直す。
+fn to_char_idx_list(
+  target: &str,
+  byte_range_list: &[(usize, usize)],
+) -> Vec<usize> {
+  target
+    .char_indices()
+    .enumerate()
+    .filter(|(_, (byte_idx, _))| {
+      byte_range_list
+        .iter()
+        .any(|(start_byte, end_byte)| (*start_byte..*end_byte).contains(byte_idx))
+    })
+    .map(|(char_idx, _)| char_idx)
+    .collect()
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// シェルのグロブ(`*`, `?`, `[a-z]`, `[!0-9]`, `{foo,bar}`)でファイル名全体と照合する。
+/// `*` 以外で一致した文字の位置(文字単位)を返す。
+fn glob_match(
+  matcher_str: &str,
+  target: &str,
+) -> MatchResult {
+  let target = target.chars().collect_vec();
+  expand_braces(matcher_str)
+    .iter()
+    .filter_map(|pattern| {
+      let tokens = parse_glob(pattern);
+      let mut matched_idx_list = Vec::new();
+      glob_match_tokens(&tokens, &target, &mut matched_idx_list).then_some(matched_idx_list)
+    })
+    .max_by_key(|matched_idx_list| matching_rate(&Some(matched_idx_list.clone())))
+}
+
+/// フィルタ以外(除外パターン等)で、グロブに一致するかのみを判定する。
+pub(crate) fn is_glob_match(
+  pattern: &str,
+  target: &str,
+) -> bool {
+  glob_match(pattern, target).is_some()
+}
+
+#[derive(Debug)]
+enum GlobToken {
+  Char(char),
+  AnyChar,
+  AnyString,
+  Class {
+    negated: bool,
+    ranges: Vec<(char, char)>,
+  },
+}
+
+impl GlobToken {
+  fn is_match(
+    &self,
+    c: char,
+  ) -> bool {
+    match self {
+      GlobToken::Char(token_char) => *token_char == c,
+      GlobToken::AnyChar => true,
+      GlobToken::AnyString => true,
+      GlobToken::Class { negated, ranges } => {
+        ranges.iter().any(|(start, end)| (*start..=*end).contains(&c)) != *negated
+      }
+    }
+  }
+}
+
+/// `{a,b}` を展開する。入れ子にも対応する。対応の取れない括弧は文字として扱う。
+fn expand_braces(pattern: &str) -> Vec<String> {
+  let chars = pattern.chars().collect_vec();
+  let Some(open_idx) = chars.iter().position(|c| *c == '{') else {
+    return vec![pattern.to_owned()];
+  };
+
+  let mut depth = 0;
+  let mut separator_idx_list = Vec::new();
+  let mut close_idx = None;
+  for (idx, c) in chars.iter().enumerate().skip(open_idx) {
+    match c {
+      '{' => depth += 1,
+      '}' => {
+        depth -= 1;
+        if depth == 0 {
+          close_idx = Some(idx);
+          break;
+        }
+      }
+      ',' if depth == 1 => separator_idx_list.push(idx),
+      _ => {}
+    }
+  }
+  let Some(close_idx) = close_idx else {
+    return vec![pattern.to_owned()];
+  };
+
+  let prefix = chars[..open_idx].iter().collect::<String>();
+  let suffix = chars[close_idx + 1..].iter().collect::<String>();
+  let bounds = std::iter::once(open_idx)
+    .chain(separator_idx_list)
+    .chain(std::iter::once(close_idx))
+    .collect_vec();
+  bounds
+    .windows(2)
+    .map(|bound| chars[bound[0] + 1..bound[1]].iter().collect::<String>())
+    .flat_map(|alternative| expand_braces(&format!("{}{}{}", prefix, alternative, suffix)))
+    .collect()
+}
+
+fn parse_glob(pattern: &str) -> Vec<GlobToken> {
+  let chars = pattern.chars().collect_vec();
+  let mut tokens = Vec::new();
+  let mut idx = 0;
+  while idx < chars.len() {
+    let token = match chars[idx] {
+      '*' => GlobToken::AnyString,
+      '?' => GlobToken::AnyChar,
+      '[' => match parse_glob_class(&chars[idx + 1..]) {
+        Some((token, length)) => {
+          idx += length;
+          token
+        }
+        None => GlobToken::Char('['),
+      },
+      c => GlobToken::Char(c),
+    };
+    tokens.push(token);
+    idx += 1;
+  }
+  tokens
+}
+
+/// `[` の後ろを解析し、トークンと `]` までの文字数を返す。
+/// `]` が無ければ None を返す。
+fn parse_glob_class(chars: &[char]) -> Option<(GlobToken, usize)> {
+  let negated = matches!(chars.first(), Some('!') | Some('^'));
+  let mut idx = if negated { 1 } else { 0 };
+  let mut ranges = Vec::new();
+  // 先頭の `]` は文字として扱う。
+  let mut is_first = true;
+  while idx < chars.len() {
+    let c = chars[idx];
+    if c == ']' && !is_first {
+      return Some((GlobToken::Class { negated, ranges }, idx + 1));
+    }
+    is_first = false;
+
+    if chars.get(idx + 1) == Some(&'-') && chars.get(idx + 2).is_some_and(|end| *end != ']') {
+      ranges.push((c, chars[idx + 2]));
+      idx += 3;
+    } else {
+      ranges.push((c, c));
+      idx += 1;
+    }
+  }
+  None
+}
+
+/// `*` は短い一致を優先する。
+/// 戻るのは最後の `*` までで、`*` が多くても照合の回数は `トークン数 × 文字数` に収まる。
+fn glob_match_tokens(
+  tokens: &[GlobToken],
+  target: &[char],
+  matched_idx_list: &mut Vec<usize>,
+) -> bool {
+  let mut token_idx = 0;
+  let mut target_idx = 0;
+  // 最後の `*` の次のトークン、その `*` が含む文字の終わり、その時点の一致位置の数
+  let mut last_any_string: Option<(usize, usize, usize)> = None;
+  while target_idx < target.len() {
+    match tokens.get(token_idx) {
+      Some(GlobToken::AnyString) => {
+        token_idx += 1;
+        last_any_string = Some((token_idx, target_idx, matched_idx_list.len()));
+        continue;
+      }
+      Some(token) if token.is_match(target[target_idx]) => {
+        matched_idx_list.push(target_idx);
+        token_idx += 1;
+        target_idx += 1;
+        continue;
+      }
+      _ => {}
+    }
+
+    // 一致しなければ、最後の `*` を 1 文字伸ばしてやり直す。
+    let Some((next_token_idx, any_string_end, matched_num)) = last_any_string else {
+      return false;
+    };
+    matched_idx_list.truncate(matched_num);
+    token_idx = next_token_idx;
+    target_idx = any_string_end + 1;
+    last_any_string = Some((next_token_idx, target_idx, matched_num));
+  }
+  tokens[token_idx..].iter().all(|token| matches!(token, GlobToken::AnyString))
 }
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
@@ -149,6 +480,8 @@ impl MatchingRate {
   }
 }
 
+/// 先頭から順に塊が長い程、同じなら塊が多い程(正規表現なら一致が多い程)、
+/// 次に前方で一致する程高い。
 impl Ord for MatchingRate {
   fn cmp(
     &self,
@@ -185,7 +518,7 @@ pub(crate) fn matching_rate(matched_idx_list: &MatchResult) -> MatchingRate {
     return MatchingRate::no_match();
   };
 
-  if matched_idx_list.len() == 0 {
+  if matched_idx_list.is_empty() {
     return MatchingRate::no_match();
   }
 
@@ -198,19 +531,118 @@ pub(crate) fn matching_rate(matched_idx_list: &MatchResult) -> MatchingRate {
     let next_match_idx = matched_idx_list[list_idx + 1];
     let is_continuous = (next_match_idx - match_idx) == 1;
     if is_continuous {
-      continuous_count = continuous_count + 1;
+      continuous_count += 1;
     } else {
       cluster_length_list.push(continuous_count);
       cluster_start_idx_list.push(next_match_idx);
+      continuous_count = 1;
     }
   }
   cluster_length_list.push(continuous_count);
 
-  return MatchingRate {
+  MatchingRate {
     result: cluster_length_list
       .into_iter()
       .zip(cluster_start_idx_list)
       .map(|(length, start_idx)| ClusterInfo { length, start_idx })
       .collect_vec(),
-  };
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn file_name_match(
+    filter_type: FilterType,
+    matcher_str: &str,
+    target: &str,
+  ) -> MatchResult {
+    let filter = FilterInfo::with(filter_type, matcher_str.to_owned());
+    filter.is_file_name_match(target)
+  }
+
+  #[test]
+  fn str_match_returns_char_indices_for_japanese() {
+    assert_eq!(
+      file_name_match(FilterType::StrMatch, "議事録", "2024年_議事録.txt"),
+      Some(vec![6, 7, 8])
+    );
+    assert_eq!(
+      file_name_match(FilterType::StrMatch, "年txt", "2024年_議事録.txt"),
+      Some(vec![4, 10, 11, 12])
+    );
+  }
+
+  #[test]
+  fn str_match_returns_char_indices_for_emoji() {
+    assert_eq!(file_name_match(FilterType::StrMatch, "cat", "🐱🐈cat.png"), Some(vec![2, 3, 4]));
+    assert_eq!(file_name_match(FilterType::StrMatch, "🐈", "🐱🐈cat.png"), Some(vec![1]));
+  }
+
+  #[test]
+  fn str_match_ignores_case_without_shifting_indices() {
+    assert_eq!(file_name_match(FilterType::StrMatch, "i", "İi"), Some(vec![1]));
+    assert_eq!(file_name_match(FilterType::StrMatch, "readme", "日本語README"), Some(vec![3, 4, 5, 6, 7, 8]));
+  }
+
+  #[test]
+  fn reg_expr_match_returns_char_indices() {
+    assert_eq!(
+      file_name_match(FilterType::RegExpr, "[0-9]+", "写真_001.jpg"),
+      Some(vec![3, 4, 5])
+    );
+    assert_eq!(file_name_match(FilterType::RegExpr, "録.+", "議事録.txt"), Some(vec![2, 3, 4, 5, 6]));
+  }
+
+  #[test]
+  fn reg_expr_match_highlights_every_match() {
+    assert_eq!(
+      file_name_match(FilterType::RegExpr, "[0-9]+", "第1回_02.txt"),
+      Some(vec![1, 4, 5])
+    );
+  }
+
+  #[test]
+  fn reg_expr_captures_match_highlights_groups() {
+    assert_eq!(
+      file_name_match(FilterType::RegExprCaptures, r"(\d{4})-(\d{2})", "報告_2024-05.md"),
+      Some(vec![3, 4, 5, 6, 8, 9])
+    );
+    assert_eq!(
+      file_name_match(FilterType::RegExprCaptures, r"\d+", "a1b22"),
+      Some(vec![1, 3, 4])
+    );
+  }
+
+  #[test]
+  fn matching_rate_ranks_more_matches_higher() {
+    let one_match = file_name_match(FilterType::RegExpr, "log", "app.log");
+    let two_matches = file_name_match(FilterType::RegExpr, "log", "log_app.log");
+    assert!(matching_rate(&two_matches) > matching_rate(&one_match));
+  }
+
+  #[test]
+  fn glob_match_returns_char_indices() {
+    assert_eq!(file_name_match(FilterType::Glob, "*.txt", "議事録.txt"), Some(vec![3, 4, 5, 6]));
+    assert_eq!(file_name_match(FilterType::Glob, "?事*", "議事録.txt"), Some(vec![0, 1]));
+  }
+
+  #[test]
+  fn glob_match_does_not_backtrack_exponentially() {
+    let target = "a".repeat(64);
+    assert_eq!(file_name_match(FilterType::Glob, "*a*a*a*a*a*a*a*a*a*a*b", &target), None);
+    assert_eq!(file_name_match(FilterType::Glob, "*a*b*c", "xaxbxc"), Some(vec![1, 3, 5]));
+  }
+
+  #[test]
+  fn migemo_match_returns_char_indices() {
+    assert_eq!(file_name_match(FilterType::Migemo, "tesuto", "📝テスト結果.md"), Some(vec![1, 2, 3]));
+  }
+
+  #[test]
+  fn query_rejects_too_large_age() {
+    assert!(query::check_filter_query("modified<7d".to_owned()).is_ok());
+    assert!(query::check_filter_query("modified<999999999999y".to_owned()).is_err());
+  }
 }
@@ -0,0 +1,375 @@
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use once_cell::sync::Lazy;
+
+use super::{matching_rate, str_match, MatchResult};
+use crate::setting_file::read_setting_file;
+
+/// 漢字の辞書。C/Migemo の migemo-dict と同じ書式(`よみ<TAB>単語<TAB>単語...`、`;` で始まる行はコメント)。
+/// 起動後、最初に使った時に読み込む。
+const DICTIONARY_FILE_NAME: &str = "general/migemo-dict";
+
+/// 辞書から引く単語の最大数
+const MAX_DICTIONARY_WORD_NUM: usize = 100;
+
+static DICTIONARY: Lazy<BTreeMap<String, Vec<String>>> = Lazy::new(load_dictionary);
+
+fn load_dictionary() -> BTreeMap<String, Vec<String>> {
+  let Some(content) = read_setting_file(DICTIONARY_FILE_NAME) else {
+    return BTreeMap::new();
+  };
+  content
+    .lines()
+    .filter(|line| !line.starts_with(';'))
+    .filter_map(|line| {
+      let mut columns = line.split('\t');
+      let reading = columns.next()?.to_owned();
+      let words = columns.filter(|word| !word.is_empty()).map(str::to_owned).collect_vec();
+      (!words.is_empty()).then_some((reading, words))
+    })
+    .collect()
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// ローマ字をひらがな/カタカナ(と辞書の漢字)に展開して照合する。
+/// ローマ字のままの一致も含め、最も一致度の高い物を返す。
+pub(super) fn migemo_match(
+  matcher_str: &str,
+  target: &str,
+) -> MatchResult {
+  let syllables = to_hiragana_syllables(matcher_str);
+  let target_chars = target.chars().collect_vec();
+
+  let kana_match = kana_match(&syllables, &target_chars);
+  let word_match_list = dictionary_words(&syllables)
+    .into_iter()
+    .filter_map(|word| word_match(&word, &target_chars));
+
+  [str_match(matcher_str, target), kana_match]
+    .into_iter()
+    .flatten()
+    .chain(word_match_list)
+    .max_by_key(|matched_idx_list| matching_rate(&Some(matched_idx_list.clone())))
+}
+
+/// 各音節のどれかに連続して一致する、最初の位置を探す。
+fn kana_match(
+  syllables: &[Vec<String>],
+  target: &[char],
+) -> MatchResult {
+  if syllables.is_empty() {
+    return None;
+  }
+  let syllables = syllables
+    .iter()
+    .map(|alternatives| {
+      alternatives
+        .iter()
+        .flat_map(|hiragana| [hiragana.chars().collect_vec(), to_katakana(hiragana).chars().collect_vec()])
+        .unique()
+        .collect_vec()
+    })
+    .collect_vec();
+  (0..target.len()).find_map(|start| {
+    let end = match_syllables_from(&syllables, target, start)?;
+    Some((start..end).collect())
+  })
+}
+
+/// 一致すれば、一致した範囲の終端を返す。
+fn match_syllables_from(
+  syllables: &[Vec<Vec<char>>],
+  target: &[char],
+  target_idx: usize,
+) -> Option<usize> {
+  let Some((alternatives, remain_syllables)) = syllables.split_first() else {
+    return Some(target_idx);
+  };
+  alternatives
+    .iter()
+    .filter(|kana| target[target_idx..].starts_with(kana))
+    .find_map(|kana| match_syllables_from(remain_syllables, target, target_idx + kana.len()))
+}
+
+fn word_match(
+  word: &str,
+  target: &[char],
+) -> MatchResult {
+  let word = word.chars().collect_vec();
+  let start = target.windows(word.len()).position(|window| window == word.as_slice())?;
+  Some((start..start + word.len()).collect())
+}
+
+/// 読みが前方一致する単語を引く。音節の候補が複数ある場合は最初の物を使う。
+/// 末尾の入力途中の音節は、その全ての候補で引く。
+fn dictionary_words(syllables: &[Vec<String>]) -> Vec<String> {
+  if DICTIONARY.is_empty() {
+    return Vec::new();
+  }
+  let Some((last, init)) = syllables.split_last() else {
+    return Vec::new();
+  };
+  let reading_stem = init.iter().filter_map(|alternatives| alternatives.first()).join("");
+  last
+    .iter()
+    .flat_map(|last_kana| {
+      let reading = format!("{}{}", reading_stem, last_kana);
+      DICTIONARY
+        .range(reading.clone()..)
+        .take_while(move |(key, _)| key.starts_with(&reading))
+        .flat_map(|(_, words)| words.iter().cloned())
+    })
+    .unique()
+    .take(MAX_DICTIONARY_WORD_NUM)
+    .collect()
+}
+
+fn to_katakana(hiragana: &str) -> String {
+  hiragana
+    .chars()
+    .map(|c| match c {
+      'ぁ'..='ゖ' => char::from_u32(c as u32 + 0x60).unwrap_or(c),
+      _ => c,
+    })
+    .collect()
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// ローマ字を音節毎のひらがなの候補に変換する。
+/// 入力途中の末尾(`k` や `ky` 等)は、続きうる全ての音節を候補にする。
+/// ローマ字として読めない文字は、そのまま候補にする。
+fn to_hiragana_syllables(romaji: &str) -> Vec<Vec<String>> {
+  let chars = romaji.to_lowercase().chars().collect_vec();
+  let mut syllables = Vec::new();
+  let mut idx = 0;
+  while idx < chars.len() {
+    let rest = &chars[idx..];
+
+    // 子音の重複は促音にする。
+    if rest.len() >= 2 && rest[0] == rest[1] && is_consonant(rest[0]) && rest[0] != 'n' {
+      syllables.push(vec!["っ".to_owned()]);
+      idx += 1;
+      continue;
+    }
+
+    // `n` の後ろが母音や `y` でなければ `ん` にする。(`konnichiha` は `こんにちは`)
+    if rest[0] == 'n' && rest.get(1).is_some_and(|c| !"aiueoy".contains(*c)) {
+      syllables.push(vec!["ん".to_owned()]);
+      idx += if rest[1] == '\'' { 2 } else { 1 };
+      continue;
+    }
+
+    let longest = (1..=rest.len().min(MAX_ROMAJI_LENGTH)).rev().find_map(|length| {
+      let key = rest[..length].iter().collect::<String>();
+      ROMAJI_TABLE.get(key.as_str()).map(|kana| (length, kana))
+    });
+    let is_last = |length: usize| idx + length == chars.len();
+    match longest {
+      Some((length, kana)) if !is_last(length) || !has_longer_key(&rest[..length]) => {
+        syllables.push(kana.iter().map(|kana| kana.to_string()).collect());
+        idx += length;
+      }
+      _ => {
+        let incomplete = rest.iter().collect::<String>();
+        let candidates = candidates_starting_with(&incomplete);
+        if !candidates.is_empty() && rest.len() < MAX_ROMAJI_LENGTH {
+          syllables.push(candidates);
+          idx = chars.len();
+        } else {
+          syllables.push(vec![rest[0].to_string()]);
+          idx += 1;
+        }
+      }
+    }
+  }
+  syllables
+}
+
+fn is_consonant(c: char) -> bool {
+  c.is_ascii_lowercase() && !"aiueo".contains(c)
+}
+
+fn has_longer_key(prefix: &[char]) -> bool {
+  let prefix = prefix.iter().collect::<String>();
+  ROMAJI_TABLE
+    .keys()
+    .any(|key| key.len() > prefix.len() && key.starts_with(&prefix))
+}
+
+/// 入力途中の末尾に続きうる音節
+fn candidates_starting_with(incomplete: &str) -> Vec<String> {
+  ROMAJI_TABLE
+    .iter()
+    .filter(|(key, _)| key.starts_with(incomplete))
+    .flat_map(|(_, kana)| kana.iter().map(|kana| kana.to_string()))
+    .unique()
+    .collect()
+}
+
+const MAX_ROMAJI_LENGTH: usize = 4;
+
+/// ヘボン式、訓令式、ワープロ入力の綴り
+static ROMAJI_TABLE: Lazy<BTreeMap<&'static str, Vec<&'static str>>> = Lazy::new(|| {
+  [
+    ("a", vec!["あ"]),
+    ("i", vec!["い"]),
+    ("u", vec!["う"]),
+    ("e", vec!["え"]),
+    ("o", vec!["お"]),
+    ("ka", vec!["か"]),
+    ("ki", vec!["き"]),
+    ("ku", vec!["く"]),
+    ("ke", vec!["け"]),
+    ("ko", vec!["こ"]),
+    ("sa", vec!["さ"]),
+    ("si", vec!["し"]),
+    ("shi", vec!["し"]),
+    ("su", vec!["す"]),
+    ("se", vec!["せ"]),
+    ("so", vec!["そ"]),
+    ("ta", vec!["た"]),
+    ("ti", vec!["ち"]),
+    ("chi", vec!["ち"]),
+    ("tu", vec!["つ"]),
+    ("tsu", vec!["つ"]),
+    ("te", vec!["て"]),
+    ("to", vec!["と"]),
+    ("na", vec!["な"]),
+    ("ni", vec!["に"]),
+    ("nu", vec!["ぬ"]),
+    ("ne", vec!["ね"]),
+    ("no", vec!["の"]),
+    ("n", vec!["ん"]),
+    ("ha", vec!["は"]),
+    ("hi", vec!["ひ"]),
+    ("hu", vec!["ふ"]),
+    ("fu", vec!["ふ"]),
+    ("he", vec!["へ"]),
+    ("ho", vec!["ほ"]),
+    ("ma", vec!["ま"]),
+    ("mi", vec!["み"]),
+    ("mu", vec!["む"]),
+    ("me", vec!["め"]),
+    ("mo", vec!["も"]),
+    ("ya", vec!["や"]),
+    ("yu", vec!["ゆ"]),
+    ("yo", vec!["よ"]),
+    ("ra", vec!["ら"]),
+    ("ri", vec!["り"]),
+    ("ru", vec!["る"]),
+    ("re", vec!["れ"]),
+    ("ro", vec!["ろ"]),
+    ("wa", vec!["わ"]),
+    ("wo", vec!["を"]),
+    ("ga", vec!["が"]),
+    ("gi", vec!["ぎ"]),
+    ("gu", vec!["ぐ"]),
+    ("ge", vec!["げ"]),
+    ("go", vec!["ご"]),
+    ("za", vec!["ざ"]),
+    ("zi", vec!["じ"]),
+    ("ji", vec!["じ", "ぢ"]),
+    ("zu", vec!["ず", "づ"]),
+    ("ze", vec!["ぜ"]),
+    ("zo", vec!["ぞ"]),
+    ("da", vec!["だ"]),
+    ("di", vec!["ぢ"]),
+    ("du", vec!["づ"]),
+    ("de", vec!["で"]),
+    ("do", vec!["ど"]),
+    ("ba", vec!["ば"]),
+    ("bi", vec!["び"]),
+    ("bu", vec!["ぶ"]),
+    ("be", vec!["べ"]),
+    ("bo", vec!["ぼ"]),
+    ("pa", vec!["ぱ"]),
+    ("pi", vec!["ぴ"]),
+    ("pu", vec!["ぷ"]),
+    ("pe", vec!["ぺ"]),
+    ("po", vec!["ぽ"]),
+    ("vu", vec!["ゔ"]),
+    ("va", vec!["ゔぁ"]),
+    ("vi", vec!["ゔぃ"]),
+    ("ve", vec!["ゔぇ"]),
+    ("vo", vec!["ゔぉ"]),
+    ("kya", vec!["きゃ"]),
+    ("kyu", vec!["きゅ"]),
+    ("kyo", vec!["きょ"]),
+    ("sya", vec!["しゃ"]),
+    ("syu", vec!["しゅ"]),
+    ("syo", vec!["しょ"]),
+    ("sha", vec!["しゃ"]),
+    ("shu", vec!["しゅ"]),
+    ("she", vec!["しぇ"]),
+    ("sho", vec!["しょ"]),
+    ("tya", vec!["ちゃ"]),
+    ("tyu", vec!["ちゅ"]),
+    ("tyo", vec!["ちょ"]),
+    ("cha", vec!["ちゃ"]),
+    ("chu", vec!["ちゅ"]),
+    ("che", vec!["ちぇ"]),
+    ("cho", vec!["ちょ"]),
+    ("nya", vec!["にゃ"]),
+    ("nyu", vec!["にゅ"]),
+    ("nyo", vec!["にょ"]),
+    ("hya", vec!["ひゃ"]),
+    ("hyu", vec!["ひゅ"]),
+    ("hyo", vec!["ひょ"]),
+    ("fa", vec!["ふぁ"]),
+    ("fi", vec!["ふぃ"]),
+    ("fe", vec!["ふぇ"]),
+    ("fo", vec!["ふぉ"]),
+    ("mya", vec!["みゃ"]),
+    ("myu", vec!["みゅ"]),
+    ("myo", vec!["みょ"]),
+    ("rya", vec!["りゃ"]),
+    ("ryu", vec!["りゅ"]),
+    ("ryo", vec!["りょ"]),
+    ("gya", vec!["ぎゃ"]),
+    ("gyu", vec!["ぎゅ"]),
+    ("gyo", vec!["ぎょ"]),
+    ("zya", vec!["じゃ"]),
+    ("zyu", vec!["じゅ"]),
+    ("zyo", vec!["じょ"]),
+    ("ja", vec!["じゃ", "ぢゃ"]),
+    ("ju", vec!["じゅ", "ぢゅ"]),
+    ("je", vec!["じぇ"]),
+    ("jo", vec!["じょ", "ぢょ"]),
+    ("jya", vec!["じゃ"]),
+    ("jyu", vec!["じゅ"]),
+    ("jyo", vec!["じょ"]),
+    ("dya", vec!["ぢゃ"]),
+    ("dyu", vec!["ぢゅ"]),
+    ("dyo", vec!["ぢょ"]),
+    ("bya", vec!["びゃ"]),
+    ("byu", vec!["びゅ"]),
+    ("byo", vec!["びょ"]),
+    ("pya", vec!["ぴゃ"]),
+    ("pyu", vec!["ぴゅ"]),
+    ("pyo", vec!["ぴょ"]),
+    ("xa", vec!["ぁ"]),
+    ("xi", vec!["ぃ"]),
+    ("xu", vec!["ぅ"]),
+    ("xe", vec!["ぇ"]),
+    ("xo", vec!["ぉ"]),
+    ("la", vec!["ぁ"]),
+    ("li", vec!["ぃ"]),
+    ("lu", vec!["ぅ"]),
+    ("le", vec!["ぇ"]),
+    ("lo", vec!["ぉ"]),
+    ("xya", vec!["ゃ"]),
+    ("xyu", vec!["ゅ"]),
+    ("xyo", vec!["ょ"]),
+    ("lya", vec!["ゃ"]),
+    ("lyu", vec!["ゅ"]),
+    ("lyo", vec!["ょ"]),
+    ("xtu", vec!["っ"]),
+    ("ltu", vec!["っ"]),
+    ("xtsu", vec!["っ"]),
+    ("ltsu", vec!["っ"]),
+    ("-", vec!["ー"]),
+  ]
+  .into_iter()
+  .collect()
+});
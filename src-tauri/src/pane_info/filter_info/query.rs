@@ -0,0 +1,548 @@
+use std::time::{Duration, SystemTime};
+
+use itertools::Itertools;
+
+use super::{str_match, to_lowercase_per_char, MatchResult};
+use crate::pane_info::FileListItem;
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// ファイル名以外の属性も条件に出来るフィルタ。
+///
+/// 例: `ext:log size>10M modified<7d -dir`
+///
+/// - `word` / `"quoted word"` : ファイル名の部分一致(StrMatch と同じ)
+/// - `name:word` : 同上
+/// - `ext:log` / `ext:log,txt` : 拡張子
+/// - `size>10M` : サイズ(`>`, `>=`, `<`, `<=`, `=`。単位は B/K/M/G/T、1024 倍)
+/// - `modified<7d` : 更新からの経過時間(単位は s/m/h/d/w/y)。`<` なら指定より新しい
+/// - `dir` / `file` : 種別
+/// - 並べると AND。`AND` / `&`、`OR` / `|`、`NOT` / `!` / `-`、括弧が使える
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct QueryParseError {
+  message: String,
+  /// 問題のある箇所(文字単位)
+  position: usize,
+}
+
+/// クエリとして解釈出来るかを確かめる。フィルタバーのエラー表示に使う。
+#[tauri::command]
+pub fn check_filter_query(query: String) -> Result<(), QueryParseError> {
+  parse(&query).map(|_| ())
+}
+
+/// 解釈済みのクエリ。項目毎に解釈し直さない様、フィルタと一緒に持っておく。
+#[derive(Debug, Clone)]
+pub(super) struct ParsedQuery(Expr);
+
+pub(super) fn parse_query(query: &str) -> Result<ParsedQuery, QueryParseError> {
+  parse(query).map(ParsedQuery)
+}
+
+pub(super) fn query_match(
+  query: &ParsedQuery,
+  item: &FileListItem,
+) -> MatchResult {
+  query.0.evaluate(item, SystemTime::now())
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+#[derive(Debug, Clone)]
+enum Expr {
+  And(Box<Expr>, Box<Expr>),
+  Or(Box<Expr>, Box<Expr>),
+  Not(Box<Expr>),
+  Term(Term),
+}
+
+#[derive(Debug, Clone)]
+enum Term {
+  Name(String),
+  Extension(Vec<String>),
+  Size(Comparison, u64),
+  Modified(Comparison, Duration),
+  Directory,
+  File,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Comparison {
+  Less,
+  LessEqual,
+  Greater,
+  GreaterEqual,
+  Equal,
+}
+
+impl Comparison {
+  fn compare<T: PartialOrd>(
+    self,
+    value: T,
+    operand: T,
+  ) -> bool {
+    match self {
+      Comparison::Less => value < operand,
+      Comparison::LessEqual => value <= operand,
+      Comparison::Greater => value > operand,
+      Comparison::GreaterEqual => value >= operand,
+      Comparison::Equal => value == operand,
+    }
+  }
+}
+
+impl Expr {
+  /// 一致したファイル名の文字位置を返す。NOT の中の一致は強調しない。
+  fn evaluate(
+    &self,
+    item: &FileListItem,
+    now: SystemTime,
+  ) -> MatchResult {
+    match self {
+      Expr::And(lhs, rhs) => {
+        let lhs = lhs.evaluate(item, now)?;
+        let rhs = rhs.evaluate(item, now)?;
+        Some(merge_idx_list(lhs, rhs))
+      }
+      Expr::Or(lhs, rhs) => match (lhs.evaluate(item, now), rhs.evaluate(item, now)) {
+        (Some(lhs), Some(rhs)) => Some(merge_idx_list(lhs, rhs)),
+        (lhs, rhs) => lhs.or(rhs),
+      },
+      Expr::Not(expr) => match expr.evaluate(item, now) {
+        Some(_) => None,
+        None => Some(Vec::new()),
+      },
+      Expr::Term(term) => term.evaluate(item, now),
+    }
+  }
+}
+
+fn merge_idx_list(
+  lhs: Vec<usize>,
+  rhs: Vec<usize>,
+) -> Vec<usize> {
+  lhs.into_iter().chain(rhs).sorted().dedup().collect()
+}
+
+impl Term {
+  fn evaluate(
+    &self,
+    item: &FileListItem,
+    now: SystemTime,
+  ) -> MatchResult {
+    let is_match = match self {
+      Term::Name(word) => return name_match(word, &item.file_name),
+      Term::Extension(extensions) => {
+        !item.is_directory
+          && extensions
+            .iter()
+            .any(|extension| extension.eq_ignore_ascii_case(&item.file_extension))
+      }
+      Term::Size(comparison, size) => item.file_size.is_some_and(|file_size| comparison.compare(file_size, *size)),
+      Term::Modified(comparison, age) => item
+        .modified_time
+        .and_then(|modified_time| now.duration_since(modified_time).ok())
+        .is_some_and(|elapsed| comparison.compare(elapsed, *age)),
+      Term::Directory => item.is_directory,
+      Term::File => !item.is_directory,
+    };
+    is_match.then(Vec::new)
+  }
+}
+
+/// 大文字を含まなければ、大文字小文字を区別しない。
+fn name_match(
+  word: &str,
+  file_name: &str,
+) -> MatchResult {
+  if word.chars().any(|c| c.is_uppercase()) {
+    return str_match(word, file_name);
+  }
+  str_match(&word.to_lowercase(), &to_lowercase_per_char(file_name))
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  Word(String),
+  Quoted(String),
+  And,
+  Or,
+  Not,
+  OpenParen,
+  CloseParen,
+}
+
+fn tokenize(query: &str) -> Result<Vec<(Token, usize)>, QueryParseError> {
+  let chars = query.chars().collect_vec();
+  let mut tokens = Vec::new();
+  let mut idx = 0;
+  while idx < chars.len() {
+    let start = idx;
+    let token = match chars[idx] {
+      c if c.is_whitespace() => {
+        idx += 1;
+        continue;
+      }
+      '(' => Token::OpenParen,
+      ')' => Token::CloseParen,
+      '&' => Token::And,
+      '|' => Token::Or,
+      '!' | '-' => Token::Not,
+      '"' => {
+        let Some(length) = chars[idx + 1..].iter().position(|c| *c == '"') else {
+          return Err(QueryParseError::new("Unterminated quotation", start));
+        };
+        idx += length + 1;
+        Token::Quoted(chars[start + 1..idx].iter().collect())
+      }
+      _ => {
+        let length = chars[idx..]
+          .iter()
+          .position(|c| c.is_whitespace() || matches!(c, '(' | ')' | '"'))
+          .unwrap_or(chars.len() - idx);
+        idx += length - 1;
+        let word = chars[start..=idx].iter().collect::<String>();
+        match word.as_str() {
+          "AND" => Token::And,
+          "OR" => Token::Or,
+          "NOT" => Token::Not,
+          _ => Token::Word(word),
+        }
+      }
+    };
+    tokens.push((token, start));
+    idx += 1;
+  }
+  Ok(tokens)
+}
+
+fn parse(query: &str) -> Result<Expr, QueryParseError> {
+  let tokens = tokenize(query)?;
+  let mut parser = Parser {
+    tokens,
+    idx: 0,
+    end_position: query.chars().count(),
+  };
+  let expr = parser.parse_or()?;
+  if let Some((_, position)) = parser.peek() {
+    return Err(QueryParseError::new("Unexpected ')'", position));
+  }
+  Ok(expr)
+}
+
+struct Parser {
+  tokens: Vec<(Token, usize)>,
+  idx: usize,
+  end_position: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<(Token, usize)> {
+    self.tokens.get(self.idx).cloned()
+  }
+
+  fn next(&mut self) -> Option<(Token, usize)> {
+    let token = self.peek();
+    self.idx += 1;
+    token
+  }
+
+  fn parse_or(&mut self) -> Result<Expr, QueryParseError> {
+    let mut expr = self.parse_and()?;
+    while let Some((Token::Or, _)) = self.peek() {
+      self.next();
+      expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+    }
+    Ok(expr)
+  }
+
+  /// 項を並べた場合も AND とみなす。
+  fn parse_and(&mut self) -> Result<Expr, QueryParseError> {
+    let mut expr = self.parse_not()?;
+    loop {
+      match self.peek() {
+        Some((Token::And, _)) => {
+          self.next();
+        }
+        Some((Token::Or | Token::CloseParen, _)) | None => return Ok(expr),
+        Some(_) => {}
+      }
+      expr = Expr::And(Box::new(expr), Box::new(self.parse_not()?));
+    }
+  }
+
+  fn parse_not(&mut self) -> Result<Expr, QueryParseError> {
+    if let Some((Token::Not, _)) = self.peek() {
+      self.next();
+      return Ok(Expr::Not(Box::new(self.parse_not()?)));
+    }
+    self.parse_primary()
+  }
+
+  fn parse_primary(&mut self) -> Result<Expr, QueryParseError> {
+    let Some((token, position)) = self.next() else {
+      return Err(QueryParseError::new("Missing condition", self.end_position));
+    };
+    match token {
+      Token::OpenParen => {
+        let expr = self.parse_or()?;
+        match self.next() {
+          Some((Token::CloseParen, _)) => Ok(expr),
+          _ => Err(QueryParseError::new("Missing ')'", position)),
+        }
+      }
+      Token::Quoted(word) => Ok(Expr::Term(Term::Name(word))),
+      Token::Word(word) => Ok(Expr::Term(parse_term(&word, position)?)),
+      Token::CloseParen => Err(QueryParseError::new("Unexpected ')'", position)),
+      Token::And | Token::Or | Token::Not => Err(QueryParseError::new("Missing condition", position)),
+    }
+  }
+}
+
+fn parse_term(
+  word: &str,
+  position: usize,
+) -> Result<Term, QueryParseError> {
+  if let Some((field, value)) = word.split_once(':') {
+    let value_position = position + field.chars().count() + 1;
+    return match field.to_lowercase().as_str() {
+      "name" if !value.is_empty() => Ok(Term::Name(value.to_owned())),
+      "ext" if !value.is_empty() => Ok(Term::Extension(
+        value
+          .split(',')
+          .map(|extension| extension.trim_start_matches('.').to_owned())
+          .collect(),
+      )),
+      "name" | "ext" => Err(QueryParseError::new("Missing value", value_position)),
+      _ => Err(QueryParseError::new(&format!("Unknown field '{}'", field), position)),
+    };
+  }
+
+  let lower_word = word.to_lowercase();
+  for (field, make_term) in [
+    ("size", parse_size_term as fn(Comparison, &str, usize) -> Result<Term, QueryParseError>),
+    ("modified", parse_modified_term),
+  ] {
+    let Some(rest) = lower_word.strip_prefix(field) else {
+      continue;
+    };
+    let (comparison, operator_length) = match rest {
+      _ if rest.starts_with(">=") => (Comparison::GreaterEqual, 2),
+      _ if rest.starts_with("<=") => (Comparison::LessEqual, 2),
+      _ if rest.starts_with('>') => (Comparison::Greater, 1),
+      _ if rest.starts_with('<') => (Comparison::Less, 1),
+      _ if rest.starts_with('=') => (Comparison::Equal, 1),
+      _ => continue,
+    };
+    let value_position = position + field.len() + operator_length;
+    return make_term(comparison, &rest[operator_length..], value_position);
+  }
+
+  match lower_word.as_str() {
+    "dir" => Ok(Term::Directory),
+    "file" => Ok(Term::File),
+    _ => Ok(Term::Name(word.to_owned())),
+  }
+}
+
+fn parse_size_term(
+  comparison: Comparison,
+  value: &str,
+  position: usize,
+) -> Result<Term, QueryParseError> {
+  let (number, unit) = split_number(value);
+  let multiplier: u64 = match unit {
+    "" | "b" => 1,
+    "k" | "kb" => 1 << 10,
+    "m" | "mb" => 1 << 20,
+    "g" | "gb" => 1 << 30,
+    "t" | "tb" => 1 << 40,
+    _ => return Err(QueryParseError::new(&format!("Unknown size unit '{}'", unit), position)),
+  };
+  let Ok(number) = number.parse::<f64>() else {
+    return Err(QueryParseError::new("Size must be a number like '10M'", position));
+  };
+  Ok(Term::Size(comparison, (number * multiplier as f64) as u64))
+}
+
+fn parse_modified_term(
+  comparison: Comparison,
+  value: &str,
+  position: usize,
+) -> Result<Term, QueryParseError> {
+  const MINUTE: u64 = 60;
+  const HOUR: u64 = 60 * MINUTE;
+  const DAY: u64 = 24 * HOUR;
+  let (number, unit) = split_number(value);
+  let seconds = match unit {
+    "s" => 1,
+    "m" | "min" => MINUTE,
+    "h" => HOUR,
+    "d" => DAY,
+    "w" => 7 * DAY,
+    "y" => 365 * DAY,
+    "" => return Err(QueryParseError::new("Missing time unit (s/m/h/d/w/y)", position)),
+    _ => return Err(QueryParseError::new(&format!("Unknown time unit '{}'", unit), position)),
+  };
+  let Ok(number) = number.parse::<f64>() else {
+    return Err(QueryParseError::new("Age must be a number like '7d'", position));
+  };
+  let Ok(duration) = Duration::try_from_secs_f64(number * seconds as f64) else {
+    return Err(QueryParseError::new("Age is too large", position));
+  };
+  Ok(Term::Modified(comparison, duration))
+}
+
+/// `10M` を `("10", "m")` に分ける。
+fn split_number(value: &str) -> (&str, &str) {
+  let unit_start = value
+    .find(|c: char| !c.is_ascii_digit() && c != '.')
+    .unwrap_or(value.len());
+  value.split_at(unit_start)
+}
+
+impl QueryParseError {
+  fn new(
+    message: &str,
+    position: usize,
+  ) -> Self {
+    Self {
+      message: message.to_owned(),
+      position,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+  fn now() -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+  }
+
+  fn file(
+    file_name: &str,
+    file_size: u64,
+    age: Duration,
+  ) -> FileListItem {
+    FileListItem {
+      is_selected: false,
+      file_name: file_name.to_owned(),
+      file_extension: file_name.rsplit_once('.').map_or("-", |(_, extension)| extension).to_owned(),
+      file_size: Some(file_size),
+      file_count: None,
+      is_directory: false,
+      file_icon: None,
+      date: None,
+      modified_time: Some(now() - age),
+      content_match: None,
+    }
+  }
+
+  fn dir(file_name: &str) -> FileListItem {
+    FileListItem {
+      file_extension: "folder".to_owned(),
+      file_size: None,
+      is_directory: true,
+      ..file(file_name, 0, DAY)
+    }
+  }
+
+  fn is_match(
+    query: &str,
+    item: &FileListItem,
+  ) -> bool {
+    parse(query).unwrap().evaluate(item, now()).is_some()
+  }
+
+  fn error_position(query: &str) -> usize {
+    check_filter_query(query.to_owned()).unwrap_err().position
+  }
+
+  #[test]
+  fn and_binds_tighter_than_or() {
+    let log = file("app.log", 0, DAY);
+    let txt = file("memo.txt", 0, DAY);
+    // `ext:txt OR (ext:log AND dir)`
+    assert!(is_match("ext:txt OR ext:log dir", &txt));
+    assert!(!is_match("ext:txt OR ext:log dir", &log));
+    assert!(is_match("ext:txt | ext:log & file", &log));
+  }
+
+  #[test]
+  fn not_binds_tighter_than_and() {
+    let log = file("app.log", 0, DAY);
+    assert!(is_match("-dir ext:log", &log));
+    assert!(is_match("NOT dir AND ext:log", &log));
+    assert!(!is_match("!ext:log OR dir", &log));
+    assert!(is_match("!!ext:log", &log));
+  }
+
+  #[test]
+  fn parentheses_override_precedence() {
+    let log = file("app.log", 0, DAY);
+    assert!(!is_match("(ext:txt OR ext:log) dir", &log));
+    assert!(is_match("-(ext:txt OR dir)", &log));
+    assert!(!is_match("-(ext:txt OR ext:log)", &log));
+  }
+
+  #[test]
+  fn size_units_are_powers_of_1024() {
+    let item = file("a.bin", 1536, DAY);
+    assert!(is_match("size=1536", &item));
+    assert!(is_match("size=1.5K", &item));
+    assert!(is_match("size>1k", &item));
+    assert!(is_match("size<=1.5kb", &item));
+    assert!(!is_match("size>=1M", &item));
+    assert!(is_match("size<1G", &item));
+    assert!(is_match("size<1T", &item));
+  }
+
+  #[test]
+  fn modified_compares_elapsed_time() {
+    let item = file("a.txt", 0, 3 * DAY);
+    assert!(is_match("modified<1w", &item));
+    assert!(is_match("modified>2d", &item));
+    assert!(!is_match("modified<72h", &item));
+    assert!(is_match("modified<=72h", &item));
+    assert!(is_match("modified>4319min", &item));
+    assert!(!is_match("modified>1y", &item));
+  }
+
+  #[test]
+  fn dir_and_file_terms_match_kind() {
+    let item = file("dir", 0, DAY);
+    let folder = dir("file");
+    assert!(is_match("file", &item));
+    assert!(!is_match("dir", &item));
+    assert!(is_match("dir", &folder));
+    // 引用すると名前の一致になる。
+    assert!(is_match("\"dir\"", &item));
+    // 拡張子はディレクトリに一致しない。
+    assert!(!is_match("ext:folder", &folder));
+  }
+
+  #[test]
+  fn names_highlight_matched_characters() {
+    let item = file("report_2024.log", 0, DAY);
+    let result = parse("rep -dir 2024").unwrap().evaluate(&item, now());
+    assert_eq!(result, Some(vec![0, 1, 2, 7, 8, 9, 10]));
+  }
+
+  #[test]
+  fn errors_point_at_offending_characters() {
+    assert_eq!(error_position("a \"bc"), 2);
+    assert_eq!(error_position("(a OR b"), 0);
+    assert_eq!(error_position("a b)"), 3);
+    assert_eq!(error_position("a AND"), 5);
+    assert_eq!(error_position("a OR OR b"), 5);
+    assert_eq!(error_position("foo:bar"), 0);
+    assert_eq!(error_position("x ext:"), 6);
+    assert_eq!(error_position("size>10X"), 5);
+    assert_eq!(error_position("日本 size>=abc"), 9);
+    assert_eq!(error_position("modified<7"), 9);
+    assert_eq!(error_position("modified<7q"), 9);
+  }
+}
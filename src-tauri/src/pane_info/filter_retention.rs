@@ -0,0 +1,71 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use once_cell::sync::Lazy;
+
+use super::{filter_info::FilterInfo, PaneInfo, PANE_DATA};
+use crate::access_error::AccessError;
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// ディレクトリを移動した時のフィルタの扱い
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum FilterRetention {
+  /// 移動の度にクリアする
+  #[default]
+  Clear,
+  /// 移動先でも同じフィルタを使う
+  Sticky,
+  /// ディレクトリ毎にフィルタを覚えておき、戻った時に復元する
+  PerDirectory,
+}
+
+/// ディレクトリ毎のフィルタ。全てのペインで共有する。
+static DIRECTORY_FILTERS: Lazy<Mutex<HashMap<String, FilterInfo>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[tauri::command]
+pub fn set_filter_retention(
+  pane_idx: usize,
+  retention: FilterRetention,
+) -> Result<(), AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let mut pane_info = pane_handler.data.lock().unwrap();
+  pane_info.filter_retention = retention;
+  Ok(())
+}
+
+#[tauri::command]
+pub fn get_filter_retention(pane_idx: usize) -> Result<FilterRetention, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let pane_info = pane_handler.data.lock().unwrap();
+  Ok(pane_info.filter_retention)
+}
+
+/// ディレクトリ毎に覚える設定なら、今のディレクトリのフィルタを覚える。
+/// ディレクトリを離れる前に呼ぶ。
+pub(super) fn remember_directory_filter(pane_info: &PaneInfo) {
+  if pane_info.filter_retention != FilterRetention::PerDirectory || pane_info.dirctry_path.is_empty() {
+    return;
+  }
+  let mut directory_filters = DIRECTORY_FILTERS.lock().unwrap();
+  if pane_info.filter.is_empty() {
+    directory_filters.remove(&pane_info.dirctry_path);
+  } else {
+    directory_filters.insert(pane_info.dirctry_path.clone(), pane_info.filter.clone());
+  }
+}
+
+/// 移動先で使うフィルタ
+pub(super) fn filter_for_directory(
+  pane_info: &PaneInfo,
+  dirctry_path: &str,
+) -> FilterInfo {
+  match pane_info.filter_retention {
+    FilterRetention::Clear => FilterInfo::new(),
+    FilterRetention::Sticky => pane_info.filter.clone(),
+    FilterRetention::PerDirectory => DIRECTORY_FILTERS
+      .lock()
+      .unwrap()
+      .get(dirctry_path)
+      .cloned()
+      .unwrap_or_else(FilterInfo::new),
+  }
+}
@@ -0,0 +1,351 @@
+use std::{
+  collections::{HashMap, HashSet, VecDeque},
+  env, fs,
+  path::{Path, PathBuf},
+  sync::Mutex,
+};
+
+use once_cell::sync::Lazy;
+
+use super::Color;
+
+const ICON_SIZE: u32 = 16;
+const DEFAULT_THEME: &str = "Adwaita";
+const FALLBACK_THEME: &str = "hicolor";
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// MIME タイプからアイコンテーマを引き、PNG を base64 で返す。
+/// アイコンは MIME タイプ単位で同じなので、結果はキャッシュしておく。
+pub(super) fn get_file_icon(
+  filepath: &Path,
+  _background: &Color,
+) -> Option<String> {
+  let mime_type = mime_type(filepath);
+
+  let mut cache = ICON_CACHE.lock().unwrap();
+  if let Some(icon) = cache.get(&mime_type) {
+    return icon.clone();
+  }
+
+  let icon = icon_name_candidates(&mime_type)
+    .iter()
+    .find_map(|icon_name| ICON_THEME.find_icon(icon_name))
+    .and_then(|icon_path| fs::read(icon_path).ok())
+    .map(|bytes| base64::encode(&bytes));
+  cache.insert(mime_type, icon.clone());
+  icon
+}
+
+static ICON_CACHE: Lazy<Mutex<HashMap<String, Option<String>>>> =
+  Lazy::new(|| Mutex::new(HashMap::new()));
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// `$XDG_DATA_HOME` と `$XDG_DATA_DIRS` を優先度順に並べた物。
+fn xdg_data_dirs() -> Vec<PathBuf> {
+  let data_home = env::var_os("XDG_DATA_HOME")
+    .map(PathBuf::from)
+    .filter(|dir| dir.is_absolute())
+    .or_else(|| dirs::home_dir().map(|home| home.join(".local/share")));
+
+  let data_dirs = env::var("XDG_DATA_DIRS")
+    .ok()
+    .filter(|dirs| !dirs.is_empty())
+    .unwrap_or("/usr/local/share:/usr/share".to_owned());
+
+  data_home
+    .into_iter()
+    .chain(data_dirs.split(':').map(PathBuf::from))
+    .collect()
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// shared-mime-info の `globs2` から作った、ファイル名 → MIME タイプの対応表。
+struct MimeDatabase {
+  by_extension: HashMap<String, String>,
+  by_file_name: HashMap<String, String>,
+  icons: HashMap<String, String>,
+  generic_icons: HashMap<String, String>,
+}
+
+static MIME_DATABASE: Lazy<MimeDatabase> = Lazy::new(MimeDatabase::load);
+
+impl MimeDatabase {
+  fn load() -> Self {
+    let mut result = MimeDatabase {
+      by_extension: HashMap::new(),
+      by_file_name: HashMap::new(),
+      icons: HashMap::new(),
+      generic_icons: HashMap::new(),
+    };
+
+    // 優先度の低いディレクトリから読み、高い物で上書きする。
+    for mime_dir in xdg_data_dirs().iter().rev().map(|dir| dir.join("mime")) {
+      result.load_globs2(&mime_dir.join("globs2"));
+      load_alias_file(&mime_dir.join("icons"), &mut result.icons);
+      load_alias_file(&mime_dir.join("generic-icons"), &mut result.generic_icons);
+    }
+    result
+  }
+
+  /// 書式: `weight:mime/type:glob[:flags]`
+  /// 重みの大きい物を優先する。
+  fn load_globs2(
+    &mut self,
+    path: &Path,
+  ) {
+    let Ok(content) = fs::read_to_string(path) else {
+      return;
+    };
+
+    let mut weights = HashMap::<String, u32>::new();
+    for line in content.lines().filter(|line| !line.starts_with('#')) {
+      let mut fields = line.split(':');
+      let (Some(weight), Some(mime_type), Some(glob)) = (fields.next(), fields.next(), fields.next())
+      else {
+        continue;
+      };
+      let weight = weight.parse::<u32>().unwrap_or(50);
+
+      let (table, key) = if let Some(extension) = glob.strip_prefix("*.") {
+        if extension.contains(['*', '?', '[']) {
+          continue;
+        }
+        (&mut self.by_extension, format!("*.{}", extension.to_lowercase()))
+      } else if !glob.contains(['*', '?', '[']) {
+        (&mut self.by_file_name, glob.to_owned())
+      } else {
+        continue;
+      };
+
+      if weights.get(&key).is_some_and(|&current| current >= weight) {
+        continue;
+      }
+      weights.insert(key.clone(), weight);
+      table.insert(
+        key.trim_start_matches("*.").to_owned(),
+        mime_type.to_owned(),
+      );
+    }
+  }
+
+  fn mime_type(
+    &self,
+    file_name: &str,
+  ) -> Option<&String> {
+    if let Some(mime_type) = self.by_file_name.get(file_name) {
+      return Some(mime_type);
+    }
+
+    // `foo.tar.gz` は `tar.gz` → `gz` の順に探す。
+    let lower_name = file_name.to_lowercase();
+    lower_name
+      .match_indices('.')
+      .find_map(|(idx, _)| self.by_extension.get(&lower_name[idx + 1..]))
+  }
+}
+
+/// 書式: `mime/type:icon-name`
+fn load_alias_file(
+  path: &Path,
+  table: &mut HashMap<String, String>,
+) {
+  let Ok(content) = fs::read_to_string(path) else {
+    return;
+  };
+  for (mime_type, icon_name) in content.lines().filter_map(|line| line.split_once(':')) {
+    table.insert(mime_type.to_owned(), icon_name.to_owned());
+  }
+}
+
+fn mime_type(filepath: &Path) -> String {
+  let Ok(meta_data) = fs::metadata(filepath) else {
+    return "application/octet-stream".to_owned();
+  };
+  if meta_data.is_dir() {
+    return "inode/directory".to_owned();
+  }
+
+  let file_name = filepath
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_default();
+  if let Some(mime_type) = MIME_DATABASE.mime_type(&file_name) {
+    return mime_type.clone();
+  }
+
+  use std::os::unix::fs::PermissionsExt;
+  if meta_data.permissions().mode() & 0o111 != 0 {
+    return "application/x-executable".to_owned();
+  }
+  "application/octet-stream".to_owned()
+}
+
+/// freedesktop の仕様に従い、専用アイコン → 汎用アイコンの順に候補を並べる。
+fn icon_name_candidates(mime_type: &str) -> Vec<String> {
+  if mime_type == "inode/directory" {
+    return vec!["folder".to_owned(), "inode-directory".to_owned()];
+  }
+
+  let media = mime_type.split('/').next().unwrap_or_default();
+  let generic_icon = MIME_DATABASE
+    .generic_icons
+    .get(mime_type)
+    .cloned()
+    .unwrap_or(format!("{}-x-generic", media));
+
+  MIME_DATABASE
+    .icons
+    .get(mime_type)
+    .cloned()
+    .into_iter()
+    .chain([
+      mime_type.replace('/', "-"),
+      generic_icon,
+      "text-x-generic".to_owned(),
+      "unknown".to_owned(),
+    ])
+    .collect()
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// アイコンテーマの `index.theme` から読んだ、サイズ別のディレクトリ一覧。
+struct IconThemeInfo {
+  name: String,
+  /// `ICON_SIZE` に近い順
+  directories: Vec<String>,
+  inherits: Vec<String>,
+}
+
+/// 利用中のテーマと、その継承元を優先度順に並べた物。
+struct IconTheme {
+  base_dirs: Vec<PathBuf>,
+  themes: Vec<IconThemeInfo>,
+}
+
+static ICON_THEME: Lazy<IconTheme> = Lazy::new(IconTheme::load);
+
+impl IconTheme {
+  fn load() -> Self {
+    let base_dirs = dirs::home_dir()
+      .map(|home| home.join(".icons"))
+      .into_iter()
+      .chain(xdg_data_dirs().into_iter().map(|dir| dir.join("icons")))
+      .filter(|dir| dir.is_dir())
+      .collect::<Vec<_>>();
+
+    let mut themes = Vec::new();
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::from([current_theme_name()]);
+    while let Some(theme_name) = queue.pop_front() {
+      if !visited.insert(theme_name.clone()) {
+        continue;
+      }
+      let Some(theme) = load_theme_info(&base_dirs, &theme_name) else {
+        continue;
+      };
+      queue.extend(theme.inherits.iter().cloned());
+      themes.push(theme);
+    }
+
+    // 継承の指定が無くても、最後は hicolor を見る。
+    if !visited.contains(FALLBACK_THEME) {
+      themes.extend(load_theme_info(&base_dirs, FALLBACK_THEME));
+    }
+
+    IconTheme { base_dirs, themes }
+  }
+
+  fn find_icon(
+    &self,
+    icon_name: &str,
+  ) -> Option<PathBuf> {
+    let file_name = format!("{}.png", icon_name);
+    self
+      .themes
+      .iter()
+      .flat_map(|theme| {
+        theme.directories.iter().flat_map(move |directory| {
+          self
+            .base_dirs
+            .iter()
+            .map(move |base_dir| base_dir.join(&theme.name).join(directory))
+        })
+      })
+      .chain(std::iter::once(PathBuf::from("/usr/share/pixmaps")))
+      .map(|dir| dir.join(&file_name))
+      .find(|path| path.is_file())
+  }
+}
+
+/// GTK の設定ファイルからテーマ名を取る。見つからなければ GTK の既定のテーマとする。
+fn current_theme_name() -> String {
+  let Some(config_dir) = dirs::config_dir() else {
+    return DEFAULT_THEME.to_owned();
+  };
+
+  ["gtk-4.0/settings.ini", "gtk-3.0/settings.ini"]
+    .iter()
+    .filter_map(|file| fs::read_to_string(config_dir.join(file)).ok())
+    .find_map(|content| {
+      content.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "gtk-icon-theme-name").then(|| value.trim().trim_matches('"').to_owned())
+      })
+    })
+    .filter(|name| !name.is_empty())
+    .unwrap_or(DEFAULT_THEME.to_owned())
+}
+
+fn load_theme_info(
+  base_dirs: &[PathBuf],
+  theme_name: &str,
+) -> Option<IconThemeInfo> {
+  let content = base_dirs
+    .iter()
+    .find_map(|dir| fs::read_to_string(dir.join(theme_name).join("index.theme")).ok())?;
+
+  let mut section = String::new();
+  let mut directories = Vec::new();
+  let mut inherits = Vec::new();
+  let mut sizes = HashMap::<String, u32>::new();
+  let mut hidpi_directories = HashSet::<String>::new();
+  for line in content.lines().map(|line| line.trim()) {
+    if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+      section = name.to_owned();
+      continue;
+    }
+    let Some((key, value)) = line.split_once('=') else {
+      continue;
+    };
+    match (section.as_str(), key.trim()) {
+      ("Icon Theme", "Directories") => {
+        directories = value.split(',').map(|dir| dir.trim().to_owned()).collect();
+      }
+      ("Icon Theme", "Inherits") => {
+        inherits = value.split(',').map(|name| name.trim().to_owned()).collect();
+      }
+      (_, "Size") => {
+        if let Ok(size) = value.trim().parse::<u32>() {
+          sizes.insert(section.clone(), size);
+        }
+      }
+      (_, "Scale") if value.trim() != "1" => {
+        hidpi_directories.insert(section.clone());
+      }
+      _ => {}
+    }
+  }
+
+  // PNG を返すので、scalable(SVG) のディレクトリは除外する。
+  // 高解像度用(`16x16@2` 等)は実サイズが異なるので除外する。
+  directories.retain(|dir| {
+    sizes.contains_key(dir) && !dir.contains("scalable") && !hidpi_directories.contains(dir)
+  });
+  directories.sort_by_key(|dir| sizes[dir].abs_diff(ICON_SIZE));
+
+  Some(IconThemeInfo {
+    name: theme_name.to_owned(),
+    directories,
+    inherits,
+  })
+}
@@ -1,15 +1,25 @@
 ﻿use std::{
   fs::Metadata,
   fs::{self},
-  os::windows::fs::{FileTypeExt, MetadataExt},
   path::PathBuf,
+  time::SystemTime,
 };
 
 use chrono::{DateTime, Local};
-use winapi::um::winbase::GetLogicalDriveStringsA;
-use winapi::um::winnt::CHAR;
 
-use windows::Win32::Storage::FileSystem::FILE_ATTRIBUTE_SYSTEM;
+use crate::access_error::AccessError;
+
+#[cfg(windows)]
+mod win;
+#[cfg(windows)]
+use win as platform;
+
+#[cfg(unix)]
+mod unix;
+#[cfg(unix)]
+use unix as platform;
+#[cfg(unix)]
+pub(crate) use unix::mount_list;
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 #[derive(Debug)]
@@ -26,7 +36,7 @@ impl FileBaseInfo {
     self
       .meta_data
       .as_ref()
-      .map(|meta_data| meta_data.file_size())
+      .map(|meta_data| meta_data.len())
   }
 
   pub(crate) fn is_directory(&self) -> bool {
@@ -34,23 +44,24 @@ impl FileBaseInfo {
       .meta_data
       .as_ref()
       .map(|meta_data| meta_data.file_type())
-      .map(|file_type| file_type.is_dir() || file_type.is_symlink_dir())
+      .map(|file_type| file_type.is_dir() || platform::is_symlink_dir(&file_type))
       .unwrap_or_default()
   }
 
   pub(crate) fn date(&self) -> Option<String> {
-    let Some(meta_data) = self.meta_data.as_ref() else {
-      return None;
-    };
-    get_date_str(&meta_data)
+    let meta_data = self.meta_data.as_ref()?;
+    get_date_str(meta_data)
+  }
+
+  pub(crate) fn modified_time(&self) -> Option<SystemTime> {
+    self.meta_data.as_ref()?.modified().ok()
   }
 
   pub(crate) fn is_system_file(&self) -> bool {
     let Some(meta_data) = self.meta_data.as_ref() else {
       return false;
     };
-    let attrs = meta_data.file_attributes();
-    (attrs & FILE_ATTRIBUTE_SYSTEM.0) != 0
+    platform::is_system_file(&self.file_name, meta_data)
   }
 
   pub(crate) fn file_extension(&self) -> String {
@@ -71,12 +82,11 @@ impl FileBaseInfo {
 }
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
-pub fn get_file_list(path: &str) -> Option<Vec<FileBaseInfo>> {
+pub fn get_file_list(path: &str) -> Result<Vec<FileBaseInfo>, AccessError> {
   if path.is_empty() {
-    // Windows用 ドライブ一覧の表示
-    // Linux対応するなら、この辺の処理の変更が要るはず。
-    return Some(
-      drive_list()
+    // ドライブ一覧(Linux ではマウントポイント一覧)の表示
+    return Ok(
+      platform::drive_list()
         .into_iter()
         .map(|file_name| FileBaseInfo {
           meta_data: fs::metadata(&file_name).ok(),
@@ -87,41 +97,88 @@ pub fn get_file_list(path: &str) -> Option<Vec<FileBaseInfo>> {
   }
 
   let path = PathBuf::from(path);
-  let result: Vec<FileBaseInfo> = fs::read_dir(path)
-    .ok()?
+  let meta_data = fs::metadata(&path).map_err(|error| AccessError::from_io_error(&path, &error))?;
+  if !meta_data.is_dir() {
+    return Err(AccessError::NotADirectory {
+      path: path.to_string_lossy().to_string(),
+    });
+  }
+
+  let result: Vec<FileBaseInfo> = fs::read_dir(&path)
+    .map_err(|error| AccessError::from_io_error(&path, &error))?
     .filter_map(|entry| entry.ok())
     .map(|entry| FileBaseInfo {
       file_name: entry.file_name().to_string_lossy().to_string(),
-      meta_data: entry.metadata().ok(),
+      meta_data: platform::entry_metadata(&entry),
     })
     .collect();
-  Some(result)
+  Ok(result)
 }
 
-///////////////////////////////////////////////////////////////////////////////////////////////////
-fn drive_list() -> Vec<String> {
-  let (buffer, len) = get_logical_drive_strings();
-
-  let raw_ary: Vec<u8> = buffer
-    .into_iter()
-    .take(len as usize)
-    .map(|val| val as u8)
-    .collect();
-
-  raw_ary
-    .split(|&x| x == 0)
-    .filter(|x| !x.is_empty())
-    .filter_map(|drive| String::from_utf8(drive.to_vec()).ok())
-    .map(|drive| drive.replace(r":\", ":"))
-    .collect()
-}
+/// 配下の全てのファイルを、`path` からの相対パスを名前として返す。ディレクトリ自体は含めない。
+/// `max_depth` が 1 なら直下のファイルのみ。
+/// シンボリックリンクのディレクトリは、循環を避ける為に辿らない。
+/// `is_excluded` に一致したファイルとディレクトリ(配下も含む)は除く。
+/// `is_excluded` には名前と、OS に依らず `/` で区切った相対パスを渡す。
+pub fn get_file_list_recursive(
+  path: &str,
+  max_depth: usize,
+  ignore_system_file: bool,
+  is_excluded: impl Fn(&str, &str) -> bool,
+) -> Result<Vec<FileBaseInfo>, AccessError> {
+  let root = PathBuf::from(path);
+  let meta_data = fs::metadata(&root).map_err(|error| AccessError::from_io_error(&root, &error))?;
+  if !meta_data.is_dir() {
+    return Err(AccessError::NotADirectory {
+      path: root.to_string_lossy().to_string(),
+    });
+  }
 
-fn get_logical_drive_strings() -> ([i8; 255], u32) {
-  unsafe {
-    let mut buffer: [CHAR; 255] = [0; 255];
-    let len = GetLogicalDriveStringsA(255, buffer.as_mut_ptr());
-    return (buffer, len);
+  let mut result = Vec::new();
+  // (ディレクトリの相対パス, 深さ)
+  let mut stack = vec![(String::new(), 1)];
+  while let Some((relative_dir, depth)) = stack.pop() {
+    let dir_path = root.join(&relative_dir);
+    let Ok(read_dir) = fs::read_dir(&dir_path) else {
+      // 配下の読めないディレクトリは飛ばす。
+      continue;
+    };
+    for entry in read_dir.filter_map(|entry| entry.ok()) {
+      let entry_name = entry.file_name().to_string_lossy().to_string();
+      let relative_path = if relative_dir.is_empty() {
+        entry_name.clone()
+      } else {
+        format!("{}{}{}", relative_dir, std::path::MAIN_SEPARATOR, entry_name)
+      };
+      if is_excluded(&entry_name, &relative_path.replace(std::path::MAIN_SEPARATOR, "/")) {
+        continue;
+      }
+      let meta_data = platform::entry_metadata(&entry);
+      if ignore_system_file
+        && meta_data
+          .as_ref()
+          .is_some_and(|meta_data| platform::is_system_file(&entry_name, meta_data))
+      {
+        continue;
+      }
+
+      let is_real_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+      if is_real_dir {
+        if depth < max_depth {
+          stack.push((relative_path, depth + 1));
+        }
+        continue;
+      }
+      let info = FileBaseInfo {
+        file_name: relative_path,
+        meta_data,
+      };
+      if !info.is_directory() {
+        result.push(info);
+      }
+    }
   }
+  Ok(result)
 }
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
@@ -131,3 +188,30 @@ fn get_date_str(file_data: &Metadata) -> Option<String> {
   let local_time: DateTime<Local> = modified_time.into();
   Some(local_time.format("%Y/%m/%d %H:%M:%S").to_string())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::pane_info::filter_info::is_glob_match;
+  use tempdir::TempDir;
+
+  #[test]
+  fn recursive_list_matches_exclude_patterns_against_slash_separated_paths() {
+    let work_dir = TempDir::new("get_file_list_test").unwrap();
+    fs::create_dir(work_dir.path().join("sub")).unwrap();
+    fs::write(work_dir.path().join("sub").join("a.tmp"), "").unwrap();
+    fs::write(work_dir.path().join("sub").join("b.txt"), "").unwrap();
+    fs::write(work_dir.path().join("c.tmp"), "").unwrap();
+
+    let mut names = get_file_list_recursive(&work_dir.path().to_string_lossy(), 2, false, |_, relative_path| {
+      is_glob_match("sub/*.tmp", relative_path)
+    })
+    .unwrap()
+    .into_iter()
+    .map(|info| info.file_name)
+    .collect::<Vec<_>>();
+    names.sort();
+
+    assert_eq!(names, vec!["c.tmp".to_owned(), format!("sub{}b.txt", std::path::MAIN_SEPARATOR)]);
+  }
+}
@@ -0,0 +1,129 @@
+use std::{
+  fs::{self, DirEntry, FileType, Metadata},
+  os::unix::fs::FileTypeExt,
+  path::Path,
+};
+
+use itertools::Itertools;
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// シンボリックリンクはリンク先の情報を返す。
+/// リンク切れの場合は、リンク自体の情報とする。
+pub(super) fn entry_metadata(entry: &DirEntry) -> Option<Metadata> {
+  fs::metadata(entry.path()).or_else(|_| entry.metadata()).ok()
+}
+
+/// `entry_metadata` でリンク先を辿っているので、ここでは判定不要。
+pub(super) fn is_symlink_dir(_file_type: &FileType) -> bool {
+  false
+}
+
+/// ドットファイル(隠しファイル)と、デバイス・FIFO・ソケット等の特殊ファイルを、
+/// Windows のシステムファイル相当として扱う。
+pub(super) fn is_system_file(
+  file_name: &str,
+  meta_data: &Metadata,
+) -> bool {
+  if file_name.starts_with('.') {
+    return true;
+  }
+
+  let file_type = meta_data.file_type();
+  file_type.is_block_device()
+    || file_type.is_char_device()
+    || file_type.is_fifo()
+    || file_type.is_socket()
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+const PSEUDO_FS_TYPES: &[&str] = &[
+  "autofs",
+  "binfmt_misc",
+  "bpf",
+  "cgroup",
+  "cgroup2",
+  "configfs",
+  "debugfs",
+  "devpts",
+  "devtmpfs",
+  "efivarfs",
+  "fusectl",
+  "hugetlbfs",
+  "mqueue",
+  "nsfs",
+  "proc",
+  "pstore",
+  "rpc_pipefs",
+  "securityfs",
+  "selinuxfs",
+  "sysfs",
+  "tracefs",
+];
+
+const SYSTEM_MOUNT_ROOTS: &[&str] = &["/dev", "/proc", "/run", "/sys"];
+
+/// `/proc/self/mountinfo` の、マウントポイントとファイルシステム種別の一覧。読めなければ None。
+pub(crate) fn mount_list() -> Option<Vec<(String, String)>> {
+  let mount_info = fs::read_to_string("/proc/self/mountinfo").ok()?;
+  Some(mount_info.lines().filter_map(parse_mount_info_line).collect())
+}
+
+/// マウントポイントの一覧から、仮想ファイルシステムや、システム用の tmpfs を除外する。
+pub(super) fn drive_list() -> Vec<String> {
+  let Some(mount_list) = mount_list() else {
+    return vec!["/".to_owned()];
+  };
+
+  mount_list
+    .into_iter()
+    .filter(|(_, fs_type)| !PSEUDO_FS_TYPES.contains(&fs_type.as_str()))
+    .filter(|(mount_point, fs_type)| fs_type != "tmpfs" || !is_system_mount(mount_point))
+    .map(|(mount_point, _)| mount_point)
+    .filter(|mount_point| Path::new(mount_point).is_dir())
+    .unique()
+    .sorted()
+    .collect()
+}
+
+fn is_system_mount(mount_point: &str) -> bool {
+  // リムーバブルメディアは /run/media 以下にマウントされる。
+  if mount_point.starts_with("/run/media/") {
+    return false;
+  }
+  SYSTEM_MOUNT_ROOTS
+    .iter()
+    .any(|root| mount_point == *root || mount_point.starts_with(&format!("{}/", root)))
+}
+
+/// 書式: `36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue`
+/// 5番目がマウントポイント、` - ` の次がファイルシステム種別。
+fn parse_mount_info_line(line: &str) -> Option<(String, String)> {
+  let (front, back) = line.split_once(" - ")?;
+  let mount_point = front.split(' ').nth(4)?;
+  let fs_type = back.split(' ').next()?;
+  Some((unescape_octal(mount_point), fs_type.to_owned()))
+}
+
+/// 空白等は `\040` の様に8進数でエスケープされている。
+fn unescape_octal(str: &str) -> String {
+  let bytes = str.as_bytes();
+  let mut result = Vec::with_capacity(bytes.len());
+  let mut idx = 0;
+  while idx < bytes.len() {
+    let escaped = bytes.get(idx + 1..idx + 4).filter(|_| bytes[idx] == b'\\');
+    let decoded = escaped
+      .and_then(|digits| std::str::from_utf8(digits).ok())
+      .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+    match decoded {
+      Some(byte) => {
+        result.push(byte);
+        idx += 4;
+      }
+      None => {
+        result.push(bytes[idx]);
+        idx += 1;
+      }
+    }
+  }
+  String::from_utf8_lossy(&result).to_string()
+}
@@ -0,0 +1,52 @@
+use std::{
+  fs::{DirEntry, FileType, Metadata},
+  os::windows::fs::{FileTypeExt, MetadataExt},
+};
+
+use winapi::um::winbase::GetLogicalDriveStringsA;
+use winapi::um::winnt::CHAR;
+
+use windows::Win32::Storage::FileSystem::FILE_ATTRIBUTE_SYSTEM;
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+pub(super) fn entry_metadata(entry: &DirEntry) -> Option<Metadata> {
+  entry.metadata().ok()
+}
+
+pub(super) fn is_symlink_dir(file_type: &FileType) -> bool {
+  file_type.is_symlink_dir()
+}
+
+pub(super) fn is_system_file(
+  _file_name: &str,
+  meta_data: &Metadata,
+) -> bool {
+  let attrs = meta_data.file_attributes();
+  (attrs & FILE_ATTRIBUTE_SYSTEM.0) != 0
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+pub(super) fn drive_list() -> Vec<String> {
+  let (buffer, len) = get_logical_drive_strings();
+
+  let raw_ary: Vec<u8> = buffer
+    .into_iter()
+    .take(len as usize)
+    .map(|val| val as u8)
+    .collect();
+
+  raw_ary
+    .split(|&x| x == 0)
+    .filter(|x| !x.is_empty())
+    .filter_map(|drive| String::from_utf8(drive.to_vec()).ok())
+    .map(|drive| drive.replace(r":\", ":"))
+    .collect()
+}
+
+fn get_logical_drive_strings() -> ([i8; 255], u32) {
+  unsafe {
+    let mut buffer: [CHAR; 255] = [0; 255];
+    let len = GetLogicalDriveStringsA(255, buffer.as_mut_ptr());
+    return (buffer, len);
+  }
+}
@@ -0,0 +1,179 @@
+use super::{
+  apply_filter, filter_info::FilterInfo, remember_directory_filter, restart_content_search_job,
+  restart_directory_size_job, watch_directory, FileListFullInfo, FileListUiInfo, PaneInfo, PANE_DATA,
+};
+use crate::access_error::AccessError;
+
+/// 戻る/進む それぞれで保持する最大数
+const MAX_HISTORY_NUM: usize = 100;
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// ディレクトリを離れた時点の状態
+#[derive(Debug, Serialize, Clone)]
+pub struct HistoryEntry {
+  dirctry_path: String,
+  focus_file_name: Option<String>,
+  focus_idx: usize,
+  filter: FilterInfo,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct NavigationHistory {
+  /// 新しい物が末尾
+  back: Vec<HistoryEntry>,
+  /// 新しい物が末尾
+  forward: Vec<HistoryEntry>,
+}
+
+impl NavigationHistory {
+  /// 別のディレクトリに移動する前に呼ぶ。進む方の履歴は破棄する。
+  pub(super) fn push(
+    &mut self,
+    pane_info: &PaneInfo,
+  ) {
+    let Some(entry) = HistoryEntry::new(pane_info) else {
+      return;
+    };
+    push_limited(&mut self.back, entry);
+    self.forward.clear();
+  }
+}
+
+fn push_limited(
+  history: &mut Vec<HistoryEntry>,
+  entry: HistoryEntry,
+) {
+  history.push(entry);
+  if history.len() > MAX_HISTORY_NUM {
+    history.remove(0);
+  }
+}
+
+impl HistoryEntry {
+  fn new(pane_info: &PaneInfo) -> Option<Self> {
+    if pane_info.dirctry_path.is_empty() {
+      return None;
+    }
+    let file_list_info = pane_info.file_list_info.as_ref();
+    Some(Self {
+      dirctry_path: pane_info.dirctry_path.clone(),
+      focus_file_name: file_list_info.and_then(|info| info.focus_file_name()),
+      focus_idx: file_list_info.map(|info| info.focus_idx).unwrap_or(0),
+      filter: pane_info.filter.clone(),
+    })
+  }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// 履歴の移動結果。フロントエンドはパスとフィルタを表示に反映する。
+#[derive(Debug, Serialize, Clone)]
+pub struct HistoryNavigationResult {
+  dirctry_path: String,
+  filter: FilterInfo,
+  file_list_info: Option<FileListUiInfo>,
+}
+
+/// 戻る履歴が無ければ None を返す。
+#[tauri::command]
+pub fn go_back(
+  app_handle: tauri::AppHandle,
+  pane_idx: usize,
+) -> Result<Option<HistoryNavigationResult>, AccessError> {
+  navigate(&app_handle, pane_idx, Direction::Back)
+}
+
+/// 進む履歴が無ければ None を返す。
+#[tauri::command]
+pub fn go_forward(
+  app_handle: tauri::AppHandle,
+  pane_idx: usize,
+) -> Result<Option<HistoryNavigationResult>, AccessError> {
+  navigate(&app_handle, pane_idx, Direction::Forward)
+}
+
+#[tauri::command]
+pub fn get_navigation_history(pane_idx: usize) -> Result<NavigationHistory, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let pane_info = pane_handler.data.lock().unwrap();
+  Ok(pane_info.history.clone())
+}
+
+enum Direction {
+  Back,
+  Forward,
+}
+
+fn navigate(
+  app_handle: &tauri::AppHandle,
+  pane_idx: usize,
+  direction: Direction,
+) -> Result<Option<HistoryNavigationResult>, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let mut pane_info = pane_handler.get_info_for_ui_operation();
+
+  let current = HistoryEntry::new(&pane_info);
+  let history = &mut pane_info.history;
+  let (from, to) = match direction {
+    Direction::Back => (&mut history.back, &mut history.forward),
+    Direction::Forward => (&mut history.forward, &mut history.back),
+  };
+  let Some(entry) = from.pop() else {
+    return Ok(None);
+  };
+  if let Some(current) = current {
+    push_limited(to, current);
+  }
+  remember_directory_filter(&pane_info);
+
+  let restored = restore(pane_idx, &mut pane_info, &entry);
+  restart_directory_size_job(app_handle, pane_idx, &pane_info);
+  restart_content_search_job(app_handle, pane_idx, &pane_info);
+  restored?;
+  Ok(Some(HistoryNavigationResult {
+    dirctry_path: entry.dirctry_path,
+    filter: entry.filter,
+    file_list_info: pane_info.to_ui_info(),
+  }))
+}
+
+/// 離れた時点のフィルタとフォーカス位置に戻す。
+/// フォーカスしていた要素が無くなっていれば、同じ位置をフォーカスする。
+/// 開けなくてもパスは変更しておき、開ける様になったら更新で表示する。
+fn restore(
+  pane_idx: usize,
+  pane_info: &mut PaneInfo,
+  entry: &HistoryEntry,
+) -> Result<(), AccessError> {
+  if pane_info.dirctry_path != entry.dirctry_path {
+    watch_directory(pane_idx, &entry.dirctry_path, pane_info.recursive_listing.is_some());
+  }
+
+  pane_info.dirctry_path = entry.dirctry_path.clone();
+  pane_info.filter = FilterInfo::new();
+  let (file_list_info, result) = match FileListFullInfo::new(
+    &entry.dirctry_path,
+    None,
+    &pane_info.sort_spec,
+    pane_info.recursive_listing.as_ref(),
+  ) {
+    Ok(file_list_info) => (Some(file_list_info), Ok(())),
+    Err(access_error) => (None, Err(access_error)),
+  };
+  pane_info.file_list_info = file_list_info;
+  pane_info.viewing_idx_range = 0..0;
+  pane_info.ui_snapshot = None;
+  apply_filter(pane_info, entry.filter.clone());
+
+  let Some(file_list_info) = &mut pane_info.file_list_info else {
+    return result;
+  };
+  let found_idx = entry.focus_file_name.as_ref().and_then(|focus_file_name| {
+    file_list_info
+      .filtered_item_info
+      .iter()
+      .position(|item| &file_list_info.full_item_list[item.org_idx].file_name == focus_file_name)
+  });
+  let last_idx = file_list_info.filtered_item_info.len().saturating_sub(1);
+  file_list_info.focus_idx = found_idx.unwrap_or(entry.focus_idx.min(last_idx));
+  Ok(())
+}
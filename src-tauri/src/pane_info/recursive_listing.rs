@@ -0,0 +1,92 @@
+use super::{
+  apply_filter, content_search::restart_content_search_job, directory_size::restart_directory_size_job,
+  filter_info::is_glob_match, focus_by_name, watch_directory, FileListFullInfo, FileListUiInfo, PANE_DATA,
+};
+use crate::access_error::AccessError;
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// 配下のファイルを再帰的に、相対パスを名前として一覧する表示の設定。
+/// ペイン毎に持ち、ディレクトリを移動しても保つ。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RecursiveListing {
+  /// 辿る最大の深さ。1 なら直下のファイルのみ。
+  max_depth: usize,
+  /// グロブ(`.git`, `target`, `*.tmp` 等)。
+  /// 名前か相対パスに一致したファイルとディレクトリ(配下も含む)を除く。
+  exclude_patterns: Vec<String>,
+}
+
+impl RecursiveListing {
+  pub(super) fn max_depth(&self) -> usize {
+    self.max_depth.max(1)
+  }
+
+  pub(super) fn is_excluded(
+    &self,
+    file_name: &str,
+    relative_path: &str,
+  ) -> bool {
+    self
+      .exclude_patterns
+      .iter()
+      .filter(|pattern| !pattern.is_empty())
+      .any(|pattern| is_glob_match(pattern, file_name) || is_glob_match(pattern, relative_path))
+  }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+/// None で通常の表示に戻す。フィルタとソート指定は保ったまま一覧を取り直す。
+#[tauri::command]
+pub fn set_recursive_listing(
+  app_handle: tauri::AppHandle,
+  pane_idx: usize,
+  recursive_listing: Option<RecursiveListing>,
+) -> Result<Option<FileListUiInfo>, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let mut pane_info = pane_handler.get_info_for_ui_operation();
+
+  if pane_info.recursive_listing == recursive_listing {
+    return Ok(pane_info.to_ui_info());
+  }
+
+  let focus_file_name = pane_info
+    .file_list_info
+    .as_ref()
+    .and_then(|file_list_info| file_list_info.focus_file_name());
+  pane_info.recursive_listing = recursive_listing;
+  let file_list_info = FileListFullInfo::new(
+    &pane_info.dirctry_path,
+    focus_file_name.clone(),
+    &pane_info.sort_spec,
+    pane_info.recursive_listing.as_ref(),
+  );
+  watch_directory(pane_idx, &pane_info.dirctry_path, pane_info.recursive_listing.is_some());
+
+  let (file_list_info, access_error) = match file_list_info {
+    Ok(file_list_info) => (Some(file_list_info), None),
+    Err(access_error) => (None, Some(access_error)),
+  };
+  pane_info.file_list_info = file_list_info;
+  pane_info.ui_snapshot = None;
+  let filter = pane_info.filter.clone();
+  if !filter.is_empty() {
+    apply_filter(&mut pane_info, filter);
+    if let Some(focus_file_name) = focus_file_name {
+      focus_by_name(&mut pane_info, &focus_file_name);
+    }
+  }
+  restart_directory_size_job(&app_handle, pane_idx, &pane_info);
+  restart_content_search_job(&app_handle, pane_idx, &pane_info);
+
+  if let Some(access_error) = access_error {
+    return Err(access_error);
+  }
+  Ok(pane_info.to_ui_info())
+}
+
+#[tauri::command]
+pub fn get_recursive_listing(pane_idx: usize) -> Result<Option<RecursiveListing>, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let pane_info = pane_handler.data.lock().unwrap();
+  Ok(pane_info.recursive_listing.clone())
+}
@@ -0,0 +1,88 @@
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+use super::filter_info::FilterInfo;
+use crate::setting_file::{read_setting_file, write_setting_file};
+
+const SETTING_FILE_NAME: &str = "general/filters.json";
+
+/// 最近使ったフィルタの最大数
+const MAX_RECENT_NUM: usize = 20;
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SavedFilter {
+  name: String,
+  filter: FilterInfo,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SavedFilters {
+  saved: Vec<SavedFilter>,
+  /// 新しい物が先頭
+  recent: Vec<FilterInfo>,
+}
+
+static SAVED_FILTERS: Lazy<Mutex<SavedFilters>> = Lazy::new(|| {
+  let saved_filters = read_setting_file(SETTING_FILE_NAME)
+    .and_then(|content| serde_json::from_str(&content).ok())
+    .unwrap_or_default();
+  Mutex::new(saved_filters)
+});
+
+/// 変更後の内容を返す。
+/// 設定ファイルに書けなくても、起動中は変更後の内容を使う。
+fn update(modify: impl FnOnce(&mut SavedFilters)) -> SavedFilters {
+  let mut saved_filters = SAVED_FILTERS.lock().unwrap();
+  modify(&mut saved_filters);
+  if let Ok(content) = serde_json::to_string_pretty(&*saved_filters) {
+    write_setting_file(SETTING_FILE_NAME, &content);
+  }
+  saved_filters.clone()
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+#[tauri::command]
+pub fn get_saved_filters() -> SavedFilters {
+  SAVED_FILTERS.lock().unwrap().clone()
+}
+
+/// 同じ名前があれば置き換える。
+#[tauri::command]
+pub fn save_filter(
+  name: String,
+  filter: FilterInfo,
+) -> SavedFilters {
+  update(|saved_filters| match saved_filters.saved.iter_mut().find(|saved| saved.name == name) {
+    Some(saved) => saved.filter = filter,
+    None => saved_filters.saved.push(SavedFilter { name, filter }),
+  })
+}
+
+#[tauri::command]
+pub fn delete_saved_filter(name: String) -> SavedFilters {
+  update(|saved_filters| saved_filters.saved.retain(|saved| saved.name != name))
+}
+
+/// フィルタバーで確定した時に呼ぶ。
+#[tauri::command]
+pub fn add_recent_filter(filter: FilterInfo) -> SavedFilters {
+  remember_recent_filter(&filter);
+  get_saved_filters()
+}
+
+/// 最近使ったフィルタの先頭に移す。空のフィルタは覚えない。
+pub(crate) fn remember_recent_filter(filter: &FilterInfo) {
+  if filter.is_empty() {
+    return;
+  }
+  if SAVED_FILTERS.lock().unwrap().recent.first() == Some(filter) {
+    return;
+  }
+  update(|saved_filters| {
+    saved_filters.recent.retain(|recent| recent != filter);
+    saved_filters.recent.insert(0, filter.clone());
+    saved_filters.recent.truncate(MAX_RECENT_NUM);
+  });
+}
@@ -1,5 +1,5 @@
-
 use super::{FileListUiInfo, PANE_DATA};
+use crate::access_error::AccessError;
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 
@@ -7,11 +7,12 @@ use super::{FileListUiInfo, PANE_DATA};
 pub fn add_selecting_idx(
   pane_idx: usize,
   additional_select_idx_list: Vec<usize>,
-) -> Option<FileListUiInfo> {
-  let mut pane_info = PANE_DATA.pane_info_list[pane_idx].get_info_for_ui_operation();
+) -> Result<Option<FileListUiInfo>, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let mut pane_info = pane_handler.get_info_for_ui_operation();
 
   let Some(ref mut file_list_info) = pane_info.file_list_info else {
-    return None;
+    return Ok(None);
   };
 
   for idx in additional_select_idx_list {
@@ -21,18 +22,19 @@ pub fn add_selecting_idx(
     file_list_info.full_item_list[item.org_idx].is_selected = true;
   }
 
-  Some(file_list_info.to_ui_info())
+  Ok(pane_info.to_ui_info())
 }
 
 #[tauri::command]
 pub fn set_selecting_idx(
   pane_idx: usize,
   new_select_idx_list: Vec<usize>,
-) -> Option<FileListUiInfo> {
-  let mut pane_info = PANE_DATA.pane_info_list[pane_idx].get_info_for_ui_operation();
+) -> Result<Option<FileListUiInfo>, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let mut pane_info = pane_handler.get_info_for_ui_operation();
 
   let Some(ref mut file_list_info) = pane_info.file_list_info else {
-    return None;
+    return Ok(None);
   };
 
   for item in file_list_info.filtered_item_info.iter_mut() {
@@ -46,27 +48,50 @@ pub fn set_selecting_idx(
     file_list_info.full_item_list[item.org_idx].is_selected = true;
   }
 
-  Some(file_list_info.to_ui_info())
+  Ok(pane_info.to_ui_info())
 }
 
 #[tauri::command]
 pub fn toggle_selection(
   pane_idx: usize,
   trg_idx: usize,
-) -> Option<FileListUiInfo> {
-  let mut pane_info = PANE_DATA.pane_info_list[pane_idx].get_info_for_ui_operation();
+) -> Result<Option<FileListUiInfo>, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let mut pane_info = pane_handler.get_info_for_ui_operation();
 
   let Some(ref mut file_list_info) = pane_info.file_list_info else {
-    return None;
+    return Ok(None);
+  };
+
+  if let Some(item) = file_list_info.filtered_item_info.get(trg_idx) {
+    let is_selected = &mut file_list_info.full_item_list[item.org_idx].is_selected;
+    *is_selected = !*is_selected;
+  }
+
+  Ok(pane_info.to_ui_info())
+}
+
+/// 選択中の要素名。選択が無ければ、フォーカス位置の要素名。
+/// フロントエンドは表示範囲付近の要素しか持っていないので、こちらで集める。
+#[tauri::command]
+pub fn get_selecting_item_name(pane_idx: usize) -> Result<Vec<String>, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let pane_info = pane_handler.data.lock().unwrap();
+
+  let Some(ref file_list_info) = pane_info.file_list_info else {
+    return Ok(vec![]);
   };
 
-  file_list_info
+  let selecting_item_name = file_list_info
     .filtered_item_info
-    .get_mut(trg_idx)
-    .map(|item| {
-      file_list_info.full_item_list[item.org_idx].is_selected =
-        !file_list_info.full_item_list[item.org_idx].is_selected
-    });
+    .iter()
+    .map(|item| &file_list_info.full_item_list[item.org_idx])
+    .filter(|item| item.is_selected)
+    .map(|item| item.file_name.clone())
+    .collect::<Vec<_>>();
+  if !selecting_item_name.is_empty() {
+    return Ok(selecting_item_name);
+  }
 
-  Some(file_list_info.to_ui_info())
+  Ok(file_list_info.focus_file_name().into_iter().collect())
 }
@@ -1,8 +1,11 @@
-use super::{FileListItem, FileListUiInfo, PANE_DATA};
+use std::cmp::Ordering;
+
+use super::{FileListItem, FileListUiInfo, PaneInfo, PANE_DATA};
+use crate::access_error::AccessError;
 use crate::pane_info::FileListFullInfo;
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub enum SortKey {
   Name,
   FileType,
@@ -10,26 +13,209 @@ pub enum SortKey {
   Date,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+  Ascending,
+  Descending,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SortCondition {
+  key: SortKey,
+  order: SortOrder,
+}
+
+/// ペイン毎のソート指定。一覧の更新後にも適用し直す。
+/// `conditions` は優先度順。
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SortSpec {
+  conditions: Vec<SortCondition>,
+  directories_first: bool,
+  natural_order: bool,
+}
+
+impl SortSpec {
+  /// 何も指定が無い場合は、取得した順(新規の物は末尾)のままにする。
+  pub(super) fn is_active(&self) -> bool {
+    self.directories_first || !self.conditions.is_empty()
+  }
+
+  pub(super) fn sort(
+    &self,
+    item_list: &mut [FileListItem],
+  ) {
+    if !self.is_active() {
+      return;
+    }
+    item_list.sort_by(|a, b| self.compare(a, b));
+  }
+
+  fn compare(
+    &self,
+    a: &FileListItem,
+    b: &FileListItem,
+  ) -> Ordering {
+    let directory_order = if self.directories_first {
+      b.is_directory.cmp(&a.is_directory)
+    } else {
+      Ordering::Equal
+    };
+
+    self
+      .conditions
+      .iter()
+      .fold(directory_order, |ordering, condition| {
+        ordering.then_with(|| condition.compare(a, b, self.natural_order))
+      })
+  }
+
+  pub(super) fn contains_key(
+    &self,
+    key: SortKey,
+  ) -> bool {
+    self.conditions.iter().any(|condition| condition.key == key)
+  }
+
+  /// 見出しのクリック用。
+  /// 先頭のキーと同じなら昇順/降順を切り替え、違うなら先頭のキーにする。
+  /// それまでのキーは、2 番目以降のキーとして残す。
+  fn select_key(
+    &mut self,
+    key: SortKey,
+  ) {
+    let order = match self.conditions.first() {
+      Some(first) if first.key == key && first.order == SortOrder::Ascending => SortOrder::Descending,
+      _ => SortOrder::Ascending,
+    };
+    self.conditions.retain(|condition| condition.key != key);
+    self.conditions.insert(0, SortCondition { key, order });
+  }
+}
+
+impl SortCondition {
+  fn compare(
+    &self,
+    a: &FileListItem,
+    b: &FileListItem,
+    natural_order: bool,
+  ) -> Ordering {
+    let str_cmp = |a: &str, b: &str| {
+      if natural_order {
+        natural_cmp(a, b)
+      } else {
+        a.cmp(b)
+      }
+    };
+
+    let ordering = match self.key {
+      SortKey::Name => str_cmp(&a.file_name, &b.file_name),
+      SortKey::FileType => str_cmp(&a.file_extension, &b.file_extension),
+      SortKey::Size => a.file_size.cmp(&b.file_size),
+      SortKey::Date => a.modified_time.cmp(&b.modified_time),
+    };
+    match self.order {
+      SortOrder::Ascending => ordering,
+      SortOrder::Descending => ordering.reverse(),
+    }
+  }
+}
+
+/// 数字の並びを数値として比較する。("file2" < "file10")
+/// 数字以外の部分は大文字小文字を区別せずに比較し、同じ場合のみ区別する。
+fn natural_cmp(
+  a: &str,
+  b: &str,
+) -> Ordering {
+  let mut a_chars = a.chars().peekable();
+  let mut b_chars = b.chars().peekable();
+  while let (Some(&a_char), Some(&b_char)) = (a_chars.peek(), b_chars.peek()) {
+    let ordering = if a_char.is_ascii_digit() && b_char.is_ascii_digit() {
+      let a_number = take_digits(&mut a_chars);
+      let b_number = take_digits(&mut b_chars);
+      compare_digits(&a_number, &b_number)
+    } else {
+      a_chars.next();
+      b_chars.next();
+      a_char.to_lowercase().cmp(b_char.to_lowercase())
+    };
+    if ordering != Ordering::Equal {
+      return ordering;
+    }
+  }
+
+  a_chars
+    .next()
+    .is_some()
+    .cmp(&b_chars.next().is_some())
+    .then_with(|| a.cmp(b))
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+  let mut result = String::new();
+  while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+    result.push(c);
+  }
+  result
+}
+
+/// 桁数に制限が無い様に、文字列のまま比較する。
+fn compare_digits(
+  a: &str,
+  b: &str,
+) -> Ordering {
+  let a_trimmed = a.trim_start_matches('0');
+  let b_trimmed = b.trim_start_matches('0');
+  a_trimmed
+    .len()
+    .cmp(&b_trimmed.len())
+    .then_with(|| a_trimmed.cmp(b_trimmed))
+    // 値が同じなら、0 埋めの短い方を先にする。
+    .then_with(|| a.len().cmp(&b.len()))
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
 #[tauri::command]
 pub fn sort_file_list(
   pane_idx: usize,
   sort_key: SortKey,
-) -> Option<FileListUiInfo> {
-  let mut pane_info = PANE_DATA.pane_info_list[pane_idx].get_info_for_ui_operation();
+) -> Result<Option<FileListUiInfo>, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let mut pane_info = pane_handler.get_info_for_ui_operation();
 
-  let Some(ref mut file_list_info) = pane_info.file_list_info else {
-    return None;
+  pane_info.sort_spec.select_key(sort_key);
+  apply_sort_spec(&mut pane_info);
+  Ok(pane_info.to_ui_info())
+}
+
+#[tauri::command]
+pub fn set_sort_spec(
+  pane_idx: usize,
+  sort_spec: SortSpec,
+) -> Result<Option<FileListUiInfo>, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let mut pane_info = pane_handler.get_info_for_ui_operation();
+
+  pane_info.sort_spec = sort_spec;
+  apply_sort_spec(&mut pane_info);
+  Ok(pane_info.to_ui_info())
+}
+
+#[tauri::command]
+pub fn get_sort_spec(pane_idx: usize) -> Result<SortSpec, AccessError> {
+  let pane_handler = PANE_DATA.pane_handler(pane_idx)?;
+  let pane_info = pane_handler.data.lock().unwrap();
+  Ok(pane_info.sort_spec.clone())
+}
+
+/// ソート指定に従って並べ直す。フォーカスしている要素は変えない。
+pub(super) fn apply_sort_spec(pane_info: &mut PaneInfo) {
+  let Some(file_list_info) = pane_info.file_list_info.as_mut() else {
+    return;
   };
 
   let focus_file_name = file_list_info.focus_file_name();
 
-  let sorter = match sort_key {
-    SortKey::Name => |a: &FileListItem, b: &FileListItem| a.file_name.cmp(&b.file_name),
-    SortKey::FileType => |a: &FileListItem, b: &FileListItem| a.file_extension.cmp(&b.file_extension),
-    SortKey::Size => |a: &FileListItem, b: &FileListItem| a.file_size.cmp(&b.file_size),
-    SortKey::Date => |a: &FileListItem, b: &FileListItem| a.date.cmp(&b.date),
-  };
-  file_list_info.full_item_list.sort_by(sorter);
+  pane_info.sort_spec.sort(&mut file_list_info.full_item_list);
 
   let mut new_file_list_info = FileListFullInfo::create(
     std::mem::take(&mut file_list_info.full_item_list),
@@ -47,8 +233,134 @@ pub fn sort_file_list(
     .unwrap_or(0);
 
   pane_info.file_list_info = Some(new_file_list_info);
-  pane_info
-    .file_list_info
-    .as_ref()
-    .map(|item| item.to_ui_info())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::pane_info::filter_info::FilterInfo;
+  use crate::pane_info::sort_by_matching_rate;
+
+  fn item(
+    file_name: &str,
+    is_directory: bool,
+    file_size: u64,
+  ) -> FileListItem {
+    FileListItem {
+      is_selected: false,
+      file_name: file_name.to_owned(),
+      file_extension: file_name.rsplit_once('.').map_or("-", |(_, extension)| extension).to_owned(),
+      file_size: Some(file_size),
+      file_count: None,
+      is_directory,
+      file_icon: None,
+      date: None,
+      modified_time: None,
+      content_match: None,
+    }
+  }
+
+  fn sorted_names(
+    sort_spec: &SortSpec,
+    mut item_list: Vec<FileListItem>,
+  ) -> Vec<String> {
+    sort_spec.sort(&mut item_list);
+    item_list.into_iter().map(|item| item.file_name).collect()
+  }
+
+  fn spec(
+    conditions: &[(SortKey, SortOrder)],
+    directories_first: bool,
+    natural_order: bool,
+  ) -> SortSpec {
+    SortSpec {
+      conditions: conditions
+        .iter()
+        .map(|&(key, order)| SortCondition { key, order })
+        .collect(),
+      directories_first,
+      natural_order,
+    }
+  }
+
+  #[test]
+  fn natural_cmp_compares_numbers_by_value() {
+    assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+    assert_eq!(natural_cmp("file10", "file2"), Ordering::Greater);
+    assert_eq!(natural_cmp("v1.9", "v1.10"), Ordering::Less);
+    assert_eq!(
+      natural_cmp("x99999999999999999999999", "x100000000000000000000000"),
+      Ordering::Less
+    );
+  }
+
+  #[test]
+  fn natural_cmp_breaks_ties_by_zero_padding_and_case() {
+    assert_eq!(natural_cmp("a1", "a01"), Ordering::Less);
+    assert_eq!(natural_cmp("File2", "file10"), Ordering::Less);
+    assert_eq!(natural_cmp("B", "b"), Ordering::Less);
+    assert_eq!(natural_cmp("abc", "abcd"), Ordering::Less);
+    assert_eq!(natural_cmp("same", "same"), Ordering::Equal);
+  }
+
+  #[test]
+  fn sort_uses_natural_order_only_when_enabled() {
+    let item_list = || vec![item("file10", false, 0), item("file2", false, 0)];
+    let by_name = [(SortKey::Name, SortOrder::Ascending)];
+    assert_eq!(sorted_names(&spec(&by_name, false, true), item_list()), ["file2", "file10"]);
+    assert_eq!(sorted_names(&spec(&by_name, false, false), item_list()), ["file10", "file2"]);
+  }
+
+  #[test]
+  fn sort_applies_keys_in_priority_order_and_direction() {
+    let item_list = || {
+      vec![
+        item("b.txt", false, 10),
+        item("a.log", false, 30),
+        item("c.txt", false, 20),
+        item("d.log", false, 30),
+      ]
+    };
+    let type_then_size = [(SortKey::FileType, SortOrder::Ascending), (SortKey::Size, SortOrder::Descending)];
+    assert_eq!(
+      sorted_names(&spec(&type_then_size, false, false), item_list()),
+      ["a.log", "d.log", "c.txt", "b.txt"]
+    );
+    let size_then_name = [(SortKey::Size, SortOrder::Descending), (SortKey::Name, SortOrder::Descending)];
+    assert_eq!(
+      sorted_names(&spec(&size_then_name, false, false), item_list()),
+      ["d.log", "a.log", "c.txt", "b.txt"]
+    );
+  }
+
+  #[test]
+  fn sort_puts_directories_first_before_other_keys() {
+    let item_list = vec![item("a.txt", false, 0), item("z", true, 0), item("b", true, 0)];
+    let by_name_descending = [(SortKey::Name, SortOrder::Descending)];
+    assert_eq!(
+      sorted_names(&spec(&by_name_descending, true, false), item_list),
+      ["z", "b", "a.txt"]
+    );
+  }
+
+  #[test]
+  fn select_key_toggles_first_key_and_keeps_others() {
+    let mut sort_spec = spec(&[(SortKey::Name, SortOrder::Ascending)], false, false);
+    sort_spec.select_key(SortKey::Name);
+    assert_eq!(sort_spec, spec(&[(SortKey::Name, SortOrder::Descending)], false, false));
+    sort_spec.select_key(SortKey::Size);
+    assert_eq!(
+      sort_spec,
+      spec(&[(SortKey::Size, SortOrder::Ascending), (SortKey::Name, SortOrder::Descending)], false, false)
+    );
+  }
+
+  #[test]
+  fn matching_rate_order_takes_precedence_over_sort_spec() {
+    let mut item_list = vec![item("b2", false, 0), item("a", false, 0), item("c", false, 0), item("b1", false, 0)];
+    spec(&[(SortKey::Name, SortOrder::Ascending)], false, false).sort(&mut item_list);
+    sort_by_matching_rate(&mut item_list, &FilterInfo::new_str_match("b"));
+    let names = item_list.iter().map(|item| item.file_name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["b1", "b2", "a", "c"]);
+  }
 }
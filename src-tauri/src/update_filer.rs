@@ -26,8 +26,8 @@ See https://github.com/kuone314/NekoFiler/releases
     &version
   );
 
-  let downloaded_exe_path = download_filer(&version, &work_dir_path).ok_or(error_msg)?;
-  kick_replace_shell_command(&work_dir_path, &downloaded_exe_path)?;
+  let downloaded_exe_path = download_filer(version, work_dir_path).ok_or(error_msg)?;
+  kick_replace_shell_command(work_dir_path, &downloaded_exe_path)?;
 
   std::process::exit(0);
 }
@@ -46,7 +46,7 @@ fn download_filer(
   let _ = Command::new("Powershell")
     .args(["-Command", &download_command])
     .stdout(Stdio::piped())
-    .current_dir(&work_dir_path)
+    .current_dir(work_dir_path)
     .output();
 
   let downloaded_exe_path = &work_dir_path.join("neko_filer.exe");
@@ -111,28 +111,30 @@ remove-item "{}" -force
     src_def, dst_def, update_command_body, remove_files_command, start_filer_command
   );
 
-  let mut file = fs::File::create(&replace_script_path)
+  let mut file = fs::File::create(replace_script_path)
     .ok()
     .ok_or("Fail create script file.")?;
   file
-    .write_all(&update_command.as_bytes())
+    .write_all(update_command.as_bytes())
     .ok()
     .ok_or("Fail write script file.")?;
 
   let parent_dir = work_dir_path.parent().unwrap_or(work_dir_path);
 
   let script_path = replace_script_path
-    .strip_prefix(&parent_dir)
+    .strip_prefix(parent_dir)
     .unwrap_or(replace_script_path);
   let kick_command = format!(
     r#"start powershell -ArgumentList "-Command {}""#,
     script_path.to_string_lossy()
   );
 
+  // 置き換えのスクリプトは、このプロセスの終了後も動き続ける必要があるので、待たない。
+  #[allow(clippy::zombie_processes)]
   let _ = Command::new("Powershell")
     .args(["-Command", &kick_command])
     .stdout(Stdio::inherit())
-    .current_dir(&parent_dir)
+    .current_dir(parent_dir)
     .spawn()
     .unwrap();
   Ok(())
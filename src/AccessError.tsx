@@ -0,0 +1,59 @@
+import { LogInfo } from './LogMessagePane';
+import { v4 as uuidv4 } from 'uuid';
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+// Rust 側の AccessError
+export type AccessError =
+  | { kind: 'NotFound', path: string }
+  | { kind: 'PermissionDenied', path: string }
+  | { kind: 'NotADirectory', path: string }
+  | { kind: 'AlreadyExists', path: string }
+  | { kind: 'Os', path: string, os_error_kind: string, os_error_code: number | null, message: string }
+  | { kind: 'PaneNotFound', pane_idx: number };
+
+// Rust 側の JobError
+export type JobError =
+  | { kind: 'JobNotFound', job_id: string };
+
+// Rust 側の JournalError。移す時のエラーは AccessError のまま来る。
+export type JournalError =
+  | { kind: 'NothingToUndo' }
+  | { kind: 'NothingToRedo' }
+  | { kind: 'ChangedSinceOperation', path: string }
+  | AccessError;
+
+// どれも `kind` で判別する。
+type BackendError = AccessError | JobError | JournalError;
+
+export function IsAccessError(error: unknown): error is BackendError {
+  return typeof error === 'object' && error !== null && 'kind' in error;
+}
+
+export function AccessErrorMessage(error: BackendError): string {
+  switch (error.kind) {
+    case 'NotFound': return `Not found: ${error.path}`;
+    case 'PermissionDenied': return `Permission denied: ${error.path}`;
+    case 'NotADirectory': return `Not a directory: ${error.path}`;
+    case 'AlreadyExists': return `Already exists: ${error.path}`;
+    case 'Os': {
+      const code = (error.os_error_code !== null) ? ` [os error ${error.os_error_code}]` : '';
+      return `${error.os_error_kind}${code}: ${error.path}\n${error.message}`;
+    }
+    case 'PaneNotFound': return `Pane not found: ${error.pane_idx}`;
+    case 'JobNotFound': return `Job not found: ${error.job_id}`;
+    case 'ChangedSinceOperation': return `Changed since the operation: ${error.path}`;
+    case 'NothingToUndo': return 'Nothing to undo.';
+    case 'NothingToRedo': return 'Nothing to redo.';
+  }
+}
+
+export function AccessErrorLog(title: string, error: unknown): LogInfo {
+  return {
+    title: title,
+    stdout: '',
+    stderr: IsAccessError(error) ? AccessErrorMessage(error) : String(error),
+    id: uuidv4(),
+    command: '',
+    rc: null,
+  };
+}
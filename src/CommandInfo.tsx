@@ -20,6 +20,8 @@ import { ButtonStyle, TextInputStyle, useTheme } from './ThemeStyle';
 export const BUILDIN_COMMAND_TYPE = {
   accessCurrentItem: 'accessCurrentItem',
   accessParentDir: 'accessParentDir',
+  historyBack: 'historyBack',
+  historyForward: 'historyForward',
   moveUp: 'moveUp',
   moveUpSelect: 'moveUpSelect',
   moveDown: 'moveDown',
@@ -32,6 +34,9 @@ export const BUILDIN_COMMAND_TYPE = {
   clearSelection: 'clearSelection',
   toggleSelection: 'toggleSelection',
   selectCurrentOnly: 'selectCurrentOnly',
+  toggleDirectoriesFirst: 'toggleDirectoriesFirst',
+  toggleNaturalSort: 'toggleNaturalSort',
+  toggleDirectorySize: 'toggleDirectorySize',
   addNewTab: 'addNewTab',
   removeTab: 'removeTab',
   removeOtherTabs: 'removeOtherTabs',
@@ -45,6 +50,27 @@ export const BUILDIN_COMMAND_TYPE = {
   clearFilter: 'clearFilter',
   setFilterStrMatch: 'setFilterStrMatch',
   setFilterRegExp: 'setFilterRegExp',
+  setFilterRegExpCaptures: 'setFilterRegExpCaptures',
+  setFilterGlob: 'setFilterGlob',
+  setFilterMigemo: 'setFilterMigemo',
+  setFilterQuery: 'setFilterQuery',
+  setFilterGrep: 'setFilterGrep',
+  applyRecentFilter: 'applyRecentFilter',
+  saveFilter: 'saveFilter',
+  selectSavedFilter: 'selectSavedFilter',
+  cycleFilterRetention: 'cycleFilterRetention',
+  toggleRecursiveListing: 'toggleRecursiveListing',
+  goToAnywhere: 'goToAnywhere',
+  toggleFileIndexRoot: 'toggleFileIndexRoot',
+  copyToOppositeDir: 'copyToOppositeDir',
+  moveToOppositeDir: 'moveToOppositeDir',
+  deleteSelection: 'deleteSelection',
+  trashSelection: 'trashSelection',
+  renameSelection: 'renameSelection',
+  newFile: 'newFile',
+  newFolder: 'newFolder',
+  undoFileOperation: 'undoFileOperation',
+  redoFileOperation: 'redoFileOperation',
   focusOppositePane: 'focusOppositePane',
   focusCommandBar: 'focusCommandBar',
   setKeyBind: 'setKeyBind',
@@ -122,6 +148,11 @@ export interface CommandExecuterFunc {
     opposite_path: string,
     separator: separator,
   ) => void;
+  inputByDialog: (
+    title: string,
+    reference_str_ary: string[],
+    onOk: (input_str_ary: string[]) => void,
+  ) => void;
 }
 
 type CommandExecuterProps = {
@@ -234,6 +265,20 @@ export const CommandExecuter = forwardRef<CommandExecuterFunc, CommandExecuterPr
     }
   }
 
+  // スクリプトを介さずに、ダイアログの入力だけを使う。1 行が 1 項目。
+  const inputByDialog = (
+    title: string,
+    reference_str_ary: string[],
+    onOk: (input_str_ary: string[]) => void,
+  ) => {
+    setTitle(title);
+    const str = reference_str_ary.join('\n');
+    setDlgString(str);
+    setRefString(str);
+    dlg.current?.showModal();
+    dlgOnOk.current = (dialog_input_string: string) => onOk(dialog_input_string.split(/\n/));
+  }
+
   const countTextRows = (str: string) => {
     return str.split('\n').length;
   }
@@ -349,6 +394,7 @@ export const CommandExecuter = forwardRef<CommandExecuterFunc, CommandExecuterPr
 
   const functions = {
     execShellCommand: execShellCommand,
+    inputByDialog: inputByDialog,
   };
 
   return element;
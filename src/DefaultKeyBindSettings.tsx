@@ -1,9 +1,12 @@
+import { invoke } from '@tauri-apps/api/core';
 import { alfabetList } from './Utility';
 import { KeyBindSetting, writeKeyBindSetting } from './KeyBindInfo';
 
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
-export function GenerateDefaultKeyBindSeting(): KeyBindSetting[] {
+export async function GenerateDefaultKeyBindSeting(): Promise<KeyBindSetting[]> {
+  // ゴミ箱に対応していない環境(Windows)では、完全に削除する。
+  const deleteCommand = await invoke<boolean>('is_trash_supported') ? 'trashSelection' : 'deleteSelection';
   const defined: KeyBindSetting[] = [
     {
       display_name: 'Copy to clopboard',
@@ -54,8 +57,8 @@ export function GenerateDefaultKeyBindSeting(): KeyBindSetting[] {
       display_name: 'Copy to opposite dirctory',
       key: 'ctrl+c',
       action: {
-        type: 'power_shell',
-        command_name: 'Copy to opposite dirctory',
+        type: 'build_in',
+        command_name: 'copyToOppositeDir',
       },
       valid_on_addressbar: false,
     },
@@ -63,8 +66,8 @@ export function GenerateDefaultKeyBindSeting(): KeyBindSetting[] {
       display_name: 'Move to opposite dirctory',
       key: 'ctrl+x',
       action: {
-        type: 'power_shell',
-        command_name: 'Move to opposite dirctory',
+        type: 'build_in',
+        command_name: 'moveToOppositeDir',
       },
       valid_on_addressbar: false,
     },
@@ -72,8 +75,8 @@ export function GenerateDefaultKeyBindSeting(): KeyBindSetting[] {
       display_name: 'Delete file',
       key: 'ctrl+d',
       action: {
-        type: 'power_shell',
-        command_name: 'Delete file',
+        type: 'build_in',
+        command_name: deleteCommand,
       },
       valid_on_addressbar: false,
     },
@@ -90,8 +93,35 @@ export function GenerateDefaultKeyBindSeting(): KeyBindSetting[] {
       display_name: 'Delete file',
       key: 'delete',
       action: {
-        type: 'power_shell',
-        command_name: 'Delete file',
+        type: 'build_in',
+        command_name: deleteCommand,
+      },
+      valid_on_addressbar: false,
+    },
+    {
+      display_name: 'Delete permanently',
+      key: 'shift+delete',
+      action: {
+        type: 'build_in',
+        command_name: 'deleteSelection',
+      },
+      valid_on_addressbar: false,
+    },
+    {
+      display_name: 'Undo file operation',
+      key: 'ctrl+z',
+      action: {
+        type: 'build_in',
+        command_name: 'undoFileOperation',
+      },
+      valid_on_addressbar: false,
+    },
+    {
+      display_name: 'Redo file operation',
+      key: 'ctrl+y',
+      action: {
+        type: 'build_in',
+        command_name: 'redoFileOperation',
       },
       valid_on_addressbar: false,
     },
@@ -117,8 +147,8 @@ export function GenerateDefaultKeyBindSeting(): KeyBindSetting[] {
       display_name: 'New File',
       key: 'ctrl+n',
       action: {
-        type: 'power_shell',
-        command_name: 'New File',
+        type: 'build_in',
+        command_name: 'newFile',
       },
       valid_on_addressbar: true,
     },
@@ -126,8 +156,8 @@ export function GenerateDefaultKeyBindSeting(): KeyBindSetting[] {
       display_name: 'New Folder',
       key: 'ctrl+n',
       action: {
-        type: 'power_shell',
-        command_name: 'New Folder',
+        type: 'build_in',
+        command_name: 'newFolder',
       },
       valid_on_addressbar: true,
     },
@@ -135,8 +165,8 @@ export function GenerateDefaultKeyBindSeting(): KeyBindSetting[] {
       display_name: 'Rename',
       key: 'ctrl+n',
       action: {
-        type: 'power_shell',
-        command_name: 'Rename',
+        type: 'build_in',
+        command_name: 'renameSelection',
       },
       valid_on_addressbar: true,
     },
@@ -347,6 +377,24 @@ export function GenerateDefaultKeyBindSeting(): KeyBindSetting[] {
       },
       valid_on_addressbar: true,
     },
+    {
+      display_name: 'historyBack',
+      key: 'alt+ArrowLeft',
+      action: {
+        type: 'build_in',
+        command_name: 'historyBack',
+      },
+      valid_on_addressbar: false,
+    },
+    {
+      display_name: 'historyForward',
+      key: 'alt+ArrowRight',
+      action: {
+        type: 'build_in',
+        command_name: 'historyForward',
+      },
+      valid_on_addressbar: false,
+    },
     {
       display_name: 'focusAddoressBar',
       key: 'ctrl+l',
@@ -402,6 +450,114 @@ export function GenerateDefaultKeyBindSeting(): KeyBindSetting[] {
       },
       valid_on_addressbar: true,
     },
+    {
+      display_name: 'Set Filter RegExp Groups',
+      key: 'ctrl+f',
+      action: {
+        type: 'build_in',
+        command_name: 'setFilterRegExpCaptures',
+      },
+      valid_on_addressbar: true,
+    },
+    {
+      display_name: 'Set Filter Glob',
+      key: 'ctrl+f',
+      action: {
+        type: 'build_in',
+        command_name: 'setFilterGlob',
+      },
+      valid_on_addressbar: true,
+    },
+    {
+      display_name: 'Set Filter Migemo',
+      key: 'ctrl+f',
+      action: {
+        type: 'build_in',
+        command_name: 'setFilterMigemo',
+      },
+      valid_on_addressbar: true,
+    },
+    {
+      display_name: 'Set Filter Query',
+      key: 'ctrl+f',
+      action: {
+        type: 'build_in',
+        command_name: 'setFilterQuery',
+      },
+      valid_on_addressbar: true,
+    },
+    {
+      display_name: 'Set Filter Grep',
+      key: 'ctrl+f',
+      action: {
+        type: 'build_in',
+        command_name: 'setFilterGrep',
+      },
+      valid_on_addressbar: true,
+    },
+    {
+      display_name: 'Apply Recent Filter',
+      key: 'ctrl+shift+f',
+      action: {
+        type: 'build_in',
+        command_name: 'applyRecentFilter',
+      },
+      valid_on_addressbar: true,
+    },
+    {
+      display_name: 'Save Filter',
+      key: 'ctrl+alt+f',
+      action: {
+        type: 'build_in',
+        command_name: 'saveFilter',
+      },
+      valid_on_addressbar: true,
+    },
+    {
+      display_name: 'Select Saved Filter',
+      key: 'alt+f',
+      action: {
+        type: 'build_in',
+        command_name: 'selectSavedFilter',
+      },
+      valid_on_addressbar: true,
+    },
+    {
+      display_name: 'Cycle Filter Retention',
+      key: 'ctrl+alt+s',
+      action: {
+        type: 'build_in',
+        command_name: 'cycleFilterRetention',
+      },
+      valid_on_addressbar: true,
+    },
+    {
+      display_name: 'Toggle Recursive Listing',
+      key: 'ctrl+alt+r',
+      action: {
+        type: 'build_in',
+        command_name: 'toggleRecursiveListing',
+      },
+      valid_on_addressbar: true,
+    },
+    {
+      display_name: 'Go to Anywhere',
+      key: 'ctrl+p',
+      action: {
+        type: 'build_in',
+        command_name: 'goToAnywhere',
+      },
+      valid_on_addressbar: true,
+    },
+    {
+      display_name: 'Add/Remove Current Directory to File Index',
+      key: 'ctrl+alt+p',
+      action: {
+        type: 'build_in',
+        command_name: 'toggleFileIndexRoot',
+      },
+      valid_on_addressbar: true,
+    },
     {
       display_name: 'focusOppositePane',
       key: 'tab',
@@ -0,0 +1,126 @@
+import { useEffect, useRef, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import { UnlistenFn, listen } from '@tauri-apps/api/event';
+import React from 'react';
+
+/** @jsxImportSource @emotion/react */
+import { css } from '@emotion/react'
+import { ButtonStyle, useTheme } from './ThemeStyle';
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+type ConflictResolution = 'Overwrite' | 'Skip' | 'Rename';
+
+type FileConflictInfo = {
+  conflict_id: string,
+  job_id: string,
+  src_path: string,
+  dst_path: string,
+  src_is_dir: boolean,
+  dst_is_dir: boolean,
+  src_size: number,
+  dst_size: number,
+  src_modified: string | null,
+  dst_modified: string | null,
+};
+
+// ファイル操作のジョブが、書き込み先の衝突をどうするか聞いてきた時のダイアログ。
+// 複数のジョブが同時に聞いてきたら、来た順に 1 つずつ出す。
+export function FileConflictDialog() {
+  const dlg: React.MutableRefObject<HTMLDialogElement | null> = useRef(null);
+  const theme = useTheme();
+  const buttonstyle = ButtonStyle(theme.baseColor);
+
+  const [conflictAry, setConflictAry] = useState<FileConflictInfo[]>([]);
+  const [applyToAll, setApplyToAll] = useState(false);
+
+  useEffect(() => {
+    let unlistenAry: UnlistenFn[] = [];
+    (async () => {
+      unlistenAry.push(await listen<FileConflictInfo>('file_conflict', event => {
+        setConflictAry(ary => [...ary, event.payload]);
+      }));
+      // 回答済みの物と、回答前にジョブが中断された物を取り除く。
+      unlistenAry.push(await listen<string>('file_conflict_closed', event => {
+        setConflictAry(ary => ary.filter(conflict => conflict.conflict_id !== event.payload));
+      }));
+    })();
+    return () => unlistenAry.forEach(unlisten => unlisten());
+  }, []);
+
+  const conflict = conflictAry.length !== 0 ? conflictAry[0] : undefined;
+  useEffect(() => {
+    if (!conflict) {
+      dlg.current?.close();
+      return;
+    }
+    setApplyToAll(false);
+    if (!dlg.current?.open) { dlg.current?.showModal(); }
+  }, [conflict?.conflict_id]);
+
+  const answer = (resolution: ConflictResolution) => {
+    if (!conflict) { return; }
+    invoke<void>('answer_file_conflict', {
+      conflictId: conflict.conflict_id,
+      resolution: resolution,
+      applyToAll: applyToAll,
+    });
+    setConflictAry(ary => ary.filter(item => item.conflict_id !== conflict.conflict_id));
+  }
+
+  const cancelJob = () => {
+    if (!conflict) { return; }
+    invoke<void>('cancel_file_job', { jobId: conflict.job_id }).catch(() => { });
+  }
+
+  const describe = (isDir: boolean, size: number, modified: string | null) => {
+    const kind = isDir ? 'directory' : size + ' bytes';
+    return kind + (modified ? ', ' + modified : '');
+  }
+
+  return <dialog
+    ref={dlg}
+    css={css({
+      background: theme.baseColor.backgroundColor,
+      color: theme.baseColor.stringDefaultColor,
+    })}
+    // Esc で閉じると回答待ちのままになるので、閉じさせない。
+    onCancel={e => e.preventDefault()}
+  >
+    {
+      conflict
+        ? <div
+          css={css({
+            display: 'grid',
+            gridTemplateRows: 'auto auto auto auto auto',
+          })}
+        >
+          <div>Already exists:</div>
+          <div>{conflict.dst_path} ({describe(conflict.dst_is_dir, conflict.dst_size, conflict.dst_modified)})</div>
+          <div>Source:</div>
+          <div>{conflict.src_path} ({describe(conflict.src_is_dir, conflict.src_size, conflict.src_modified)})</div>
+          <label>
+            <input
+              type='checkbox'
+              checked={applyToAll}
+              onChange={e => setApplyToAll(e.target.checked)}
+            />
+            Apply to all conflicts in this job
+          </label>
+          <div
+            css={css({
+              marginLeft: 'auto',
+              marginRight: 'auto',
+            })}
+          >
+            <button css={buttonstyle} onClick={() => answer('Overwrite')}>
+              {conflict.src_is_dir && conflict.dst_is_dir ? 'Merge' : 'Overwrite'}
+            </button>
+            <button css={buttonstyle} onClick={() => answer('Skip')}>Skip</button>
+            <button css={buttonstyle} onClick={() => answer('Rename')}>Rename</button>
+            <button css={buttonstyle} onClick={cancelJob}>Cancel job</button>
+          </div>
+        </div>
+        : <></>
+    }
+  </dialog>
+}
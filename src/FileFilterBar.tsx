@@ -1,10 +1,11 @@
-import { forwardRef, useEffect, useImperativeHandle, useState } from 'react';
+import { forwardRef, useEffect, useImperativeHandle, useRef, useState } from 'react';
 import React from 'react';
 
 /** @jsxImportSource @emotion/react */
 import { css } from '@emotion/react'
-import Select from 'react-select'
+import Select, { SelectInstance } from 'react-select'
 import { ComboBoxStyle, TextInputStyle, useTheme } from './ThemeStyle';
+import { invoke } from '@tauri-apps/api/core';
 
 
 
@@ -12,6 +13,11 @@ import { ComboBoxStyle, TextInputStyle, useTheme } from './ThemeStyle';
 export const FileFilterType = {
   str_match: "StrMatch",
   reg_expr: "RegExpr",
+  reg_expr_captures: "RegExprCaptures",
+  glob: "Glob",
+  migemo: "Migemo",
+  query: "Query",
+  grep: "Grep",
 } as const;
 export type FileFilterType = typeof FileFilterType[keyof typeof FileFilterType];
 
@@ -22,9 +28,54 @@ const comboLabel = (type: FileFilterType) => {
   switch (type) {
     case 'StrMatch': return 'StrMatch'
     case 'RegExpr': return 'RegExpr'
+    case 'RegExprCaptures': return 'RegExpr(groups)'
+    case 'Glob': return 'Glob'
+    case 'Migemo': return 'Migemo'
+    case 'Query': return 'Query'
+    case 'Grep': return 'Grep'
   }
 }
 
+type QueryParseError = {
+  message: string,
+  position: number,
+};
+
+const checkFilterQuery = async (query: string): Promise<string> => {
+  try {
+    await invoke<void>('check_filter_query', { query });
+    return '';
+  } catch (error) {
+    const parseError = error as QueryParseError;
+    return `${parseError.message} (at ${parseError.position + 1})`;
+  }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+type FilterInfo = {
+  filter_type: FileFilterType,
+  matcher_str: string,
+};
+
+type SavedFilters = {
+  saved: { name: string, filter: FilterInfo }[],
+  recent: FilterInfo[],
+};
+
+type SavedFilterComboItem = {
+  value: FilterInfo,
+  label: string,
+};
+
+const toSavedFilterComboItems = (savedFilters: SavedFilters) => {
+  const saved = savedFilters.saved.map(saved => ({ value: saved.filter, label: saved.name }));
+  const recent = savedFilters.recent.map(filter => ({ value: filter, label: `${comboLabel(filter.filter_type)}: ${filter.matcher_str}` }));
+  return [
+    { label: 'Saved', options: saved },
+    { label: 'Recent', options: recent },
+  ];
+}
+
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 export interface FileFilterBarFunc {
   addFilterString: (str: string) => void,
@@ -32,10 +83,30 @@ export interface FileFilterBarFunc {
   clearFilter: () => void,
   focus: () => void,
   setType: (filterType: FileFilterType) => void,
+  setFilter: (filterType: FileFilterType, matcherString: string) => void,
+  applyRecentFilter: () => void,
+  saveFilter: () => void,
+  selectSavedFilter: () => void,
   isFocus: () => boolean,
 };
 
+export const FilterRetention = {
+  clear: "Clear",
+  sticky: "Sticky",
+  per_directory: "PerDirectory",
+} as const;
+export type FilterRetention = typeof FilterRetention[keyof typeof FilterRetention];
+
+const retentionLabel = (retention: FilterRetention) => {
+  switch (retention) {
+    case 'Clear': return ''
+    case 'Sticky': return '(sticky)'
+    case 'PerDirectory': return '(per dir)'
+  }
+}
+
 type FileFilterBarProps = {
+  retention: FilterRetention,
   onFilterChanged: (filterType: FileFilterType, matcherString: String) => void,
   onEndEdit: () => void,
 };
@@ -48,6 +119,30 @@ export const FileFilterBar = forwardRef<FileFilterBarFunc, FileFilterBarProps>((
     props.onFilterChanged(filterType, matcherString);
   }, [matcherString, filterType]);
 
+  const [queryError, setQueryError] = useState('');
+  useEffect(() => {
+    if (filterType !== 'Query') {
+      setQueryError('');
+      return;
+    }
+    checkFilterQuery(matcherString).then(setQueryError);
+  }, [matcherString, filterType]);
+
+  const [savedFilters, setSavedFilters] = useState<SavedFilters>({ saved: [], recent: [] });
+  useEffect(() => {
+    invoke<SavedFilters>('get_saved_filters').then(setSavedFilters);
+  }, []);
+  const savedFilterSelectRef = useRef<SelectInstance<SavedFilterComboItem>>(null);
+
+  const currentFilter = (): FilterInfo => ({ filter_type: filterType, matcher_str: matcherString });
+  const rememberFilter = async () => {
+    setSavedFilters(await invoke<SavedFilters>('add_recent_filter', { filter: currentFilter() }));
+  }
+  const applyFilter = (filter: FilterInfo) => {
+    setFilterType(filter.filter_type);
+    setMatcherString(filter.matcher_str);
+  }
+
   const [isFocus, setIsFocus] = useState(false);
 
   const theme = useTheme();
@@ -56,6 +151,7 @@ export const FileFilterBar = forwardRef<FileFilterBarFunc, FileFilterBarProps>((
 
   const onKeyDown = async (event: React.KeyboardEvent<HTMLInputElement>) => {
     if (event.key === 'Enter' || event.key === 'Escape') {
+      if (event.key === 'Enter') { rememberFilter(); }
       props.onEndEdit();
       return;
     }
@@ -67,6 +163,23 @@ export const FileFilterBar = forwardRef<FileFilterBarFunc, FileFilterBarProps>((
     clearFilter: () => setMatcherString(``),
     focus: () => inputBoxRef.current?.focus(),
     setType: (filterType: FileFilterType) => setFilterType(filterType),
+    setFilter: (filterType: FileFilterType, matcherString: string) => {
+      setFilterType(filterType);
+      setMatcherString(matcherString);
+    },
+    applyRecentFilter: async () => {
+      const latest = await invoke<SavedFilters>('get_saved_filters');
+      setSavedFilters(latest);
+      const found = latest.recent.find(filter =>
+        filter.filter_type !== filterType || filter.matcher_str !== matcherString);
+      if (found) { applyFilter(found); }
+    },
+    saveFilter: async () => {
+      if (matcherString.length === 0) { return; }
+      const name = `${comboLabel(filterType)}: ${matcherString}`;
+      setSavedFilters(await invoke<SavedFilters>('save_filter', { name, filter: currentFilter() }));
+    },
+    selectSavedFilter: () => savedFilterSelectRef.current?.focus(),
     isFocus: () => isFocus,
   }
 
@@ -74,11 +187,11 @@ export const FileFilterBar = forwardRef<FileFilterBarFunc, FileFilterBarProps>((
   return <div
     css={css({
       display: 'grid',
-      gridTemplateColumns: 'auto auto auto',
+      gridTemplateColumns: 'auto auto auto auto auto',
       textAlign: 'right',
     })}
   >
-    <div>Filter:</div>
+    <div>Filter{retentionLabel(props.retention)}:</div>
     <Select
       styles={comboBoxStyle}
       css={css({
@@ -102,5 +215,30 @@ export const FileFilterBar = forwardRef<FileFilterBarFunc, FileFilterBarProps>((
       onBlur={_ => setIsFocus(false)}
       ref={inputBoxRef}
     />
+    <Select
+      styles={comboBoxStyle}
+      css={css({
+        width: '150pt',
+      })}
+      ref={savedFilterSelectRef}
+      placeholder='Saved / Recent'
+      options={toSavedFilterComboItems(savedFilters)}
+      value={null}
+      openMenuOnFocus={true}
+      onMenuOpen={() => invoke<SavedFilters>('get_saved_filters').then(setSavedFilters)}
+      onChange={(val) => {
+        if (val === null) { return; }
+        applyFilter(val.value);
+        props.onEndEdit();
+      }}
+    />
+    <div
+      css={css({
+        color: 'red',
+        textAlign: 'left',
+      })}
+    >
+      {queryError}
+    </div>
   </div>
 });
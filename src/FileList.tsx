@@ -6,7 +6,6 @@ import React from 'react';
 import { css } from '@emotion/react'
 
 import { FileListRowColorSettings, RowColorSetting, readFileListRowColorSetting } from './FileNameColorSetting';
-import { IsValidIndex } from './Utility';
 import { MatchImpl } from './Matcher';
 import { ColorCodeString } from './ColorCodeString';
 import { useTheme } from './ThemeStyle';
@@ -23,6 +22,13 @@ const SortKey = {
 } as const;
 type SortKey = typeof SortKey[keyof typeof SortKey];
 
+// conditions は優先度順。
+type SortSpec = {
+  conditions: { key: SortKey, order: 'Ascending' | 'Descending' }[],
+  directories_first: boolean,
+  natural_order: boolean,
+}
+
 const defaultAdjustMargin = 2;
 
 const outerBorderWidth = '3pt solid ';
@@ -48,8 +54,16 @@ export type FileListItem = {
   file_extension: string,
   is_directory: boolean,
   file_icon: string | null,
-  file_size: number | null,
+  file_size: number | null, // ディレクトリの場合は、配下の合計(計算済みの場合のみ)
+  file_count: number | null,
   date: string | null,
+  content_match: ContentMatch | null, // 内容検索の結果(検索済みの場合のみ)
+}
+
+export type ContentMatch = {
+  match_count: number,
+  first_line_number: number | null,
+  first_line: string | null,
 }
 
 export type FileListFilteredItem = {
@@ -57,12 +71,67 @@ export type FileListFilteredItem = {
   matched_idx_list: number[],
 }
 
+// filtered_item_list は、item_range_stt から始まる表示範囲付近の要素のみ。
 export type FileListUiInfo = {
+  version: number,
   full_item_num: number,
+  filtered_item_num: number,
+  selected_item_num: number,
+  item_range_stt: number,
   filtered_item_list: FileListFilteredItem[],
   focus_idx: number,
 }
 
+// base_version の一覧に適用すると、version の一覧になる差分。要素はファイル名で識別する。
+export type FileListDelta = {
+  pane_idx: number,
+  base_version: number,
+  version: number,
+  item_range_stt: number,
+  full_item_num: number,
+  filtered_item_num: number,
+  selected_item_num: number,
+  focus_idx: number,
+  removed: string[],
+  added: { idx: number, item: FileListFilteredItem }[],
+  changed: FileListFilteredItem[],
+  selected: string[],
+  deselected: string[],
+}
+
+export function ApplyFileListDelta(info: FileListUiInfo, delta: FileListDelta): FileListUiInfo {
+  const removed = new Set(delta.removed);
+  const changed = new Map(delta.changed.map(item => [item.file_list_item.file_name, item]));
+  const selected = new Set(delta.selected);
+  const deselected = new Set(delta.deselected);
+
+  const setSelection = (item: FileListFilteredItem, isSelected: boolean) => {
+    return { ...item, file_list_item: { ...item.file_list_item, is_selected: isSelected } };
+  }
+
+  const filteredItemList = info.filtered_item_list
+    .filter(item => !removed.has(item.file_list_item.file_name))
+    .map(item => {
+      const fileName = item.file_list_item.file_name;
+      const changedItem = changed.get(fileName);
+      if (changedItem) { return changedItem; }
+      if (selected.has(fileName)) { return setSelection(item, true); }
+      if (deselected.has(fileName)) { return setSelection(item, false); }
+      return item;
+    });
+  delta.added.forEach(added => filteredItemList.splice(added.idx, 0, added.item));
+
+  return {
+    version: delta.version,
+    full_item_num: delta.full_item_num,
+    filtered_item_num: delta.filtered_item_num,
+    selected_item_num: delta.selected_item_num,
+    item_range_stt: delta.item_range_stt,
+    filtered_item_list: filteredItemList,
+    focus_idx: delta.focus_idx,
+  };
+}
+
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 export interface IFileListItemFilter {
   IsMatch(entry: FileListItem): boolean;
@@ -71,7 +140,7 @@ export interface IFileListItemFilter {
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 export interface FileListFunc {
-  selectingItemName: () => string[],
+  selectingItemName: () => Promise<string[]>,
   accessCurrentItem: () => void,
   moveUp: () => void,
   moveUpSelect: () => void,
@@ -85,6 +154,9 @@ export interface FileListFunc {
   clearSelection: () => void,
   toggleSelection: () => void,
   selectCurrentOnly: () => void,
+  toggleDirectoriesFirst: () => void,
+  toggleNaturalSort: () => void,
+  toggleDirectorySize: () => void,
 };
 
 type FileListProps = {
@@ -121,13 +193,17 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
   const [visibleRange, setVisibleRange] = useState<{ start: number; end: number }>({ start: 0, end: 0 });
 
   useEffect(() => {
-    invoke(
-      'set_viewing_idx_range',
-      {
-        paneIdx: props.panel_idx,
-        rangeStt: visibleRange.start,
-        rangeEnd: visibleRange.end,
-      })
+    (async () => {
+      // 受け取り済みの範囲から外れた場合のみ、新しい範囲の一覧が返ってくる。
+      const paneInfo = await invoke<FileListUiInfo | null>(
+        'set_viewing_idx_range',
+        {
+          paneIdx: props.panel_idx,
+          rangeStt: visibleRange.start,
+          rangeEnd: visibleRange.end,
+        });
+      if (paneInfo) { props.updateFileListInfo(paneInfo); }
+    })()
   }, [visibleRange]);
 
   useEffect(() => {
@@ -144,8 +220,13 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
 
   const theme = useTheme();
 
-  const filteredEntries = props.fileListInfo.filtered_item_list;
+  const filteredItemNum = props.fileListInfo.filtered_item_num;
   const currentIndex = props.fileListInfo.focus_idx;
+  const isValidItemIndex = (idx: number) => 0 <= idx && idx < filteredItemNum;
+  // 受け取っていない範囲の要素は undefined
+  const filteredEntry = (idx: number): FileListFilteredItem | undefined => {
+    return props.fileListInfo.filtered_item_list[idx - props.fileListInfo.item_range_stt];
+  }
 
   const addSelectingIndexRange = async (rangeTerm1: number, rangeTerm2: number) => {
     const paneInfo = await invoke<FileListUiInfo>("add_selecting_idx", {
@@ -156,7 +237,7 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
   }
 
   const setCurrentIndex = async (newIndex: number, adjustMargin: number) => {
-    if (!IsValidIndex(filteredEntries, newIndex)) { return; }
+    if (!isValidItemIndex(newIndex)) { return; }
     if (newIndex === currentIndex) { return; }
 
     setAdjustMargin(adjustMargin);
@@ -169,7 +250,7 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
   }
 
   const setupCurrentIndex = (newIndex: number, select: boolean) => {
-    if (!IsValidIndex(filteredEntries, newIndex)) { return; }
+    if (!isValidItemIndex(newIndex)) { return; }
     if (select) {
       addSelectingIndexRange(currentIndex, newIndex);
     }
@@ -211,7 +292,7 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
       setSelectingIndexArray(SequenceAry(start.startIndex, newIdx));
     }
 
-    if (!IsValidIndex(filteredEntries, newIdx)) { return; }
+    if (!isValidItemIndex(newIdx)) { return; }
     const isDrag = (start.startIndex !== newIdx);
     setCurrentIndex(newIdx, isDrag ? 1 : 0);
   }
@@ -220,7 +301,7 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
   }
 
   const onMouseDoubleClick = (row_idx: number, event: React.MouseEvent<Element>) => {
-    if (IsValidIndex(filteredEntries, row_idx)) {
+    if (isValidItemIndex(row_idx)) {
       accessCurrentItem()
     } else {
       props.accessParentDir();
@@ -229,8 +310,8 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
   }
 
   const accessCurrentItem = () => {
-    if (!IsValidIndex(filteredEntries, currentIndex)) { return; }
-    const entry = filteredEntries[currentIndex].file_list_item;
+    const entry = filteredEntry(currentIndex)?.file_list_item;
+    if (!entry) { return; }
     if (entry.is_directory) {
       props.accessDirectry(entry.file_name);
     } else {
@@ -238,16 +319,11 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
     }
   }
 
-  const selectingItemName = () => {
-    if (filteredEntries.length === 0) { return [''] }
-
-    const result = filteredEntries
-      .filter(item => item.file_list_item.is_selected)
-      .map(item => item.file_list_item.file_name);
-
-    return (result.length === 0)
-      ? [filteredEntries[currentIndex].file_list_item.file_name]
-      : result;
+  const selectingItemName = async () => {
+    const result = await invoke<string[]>("get_selecting_item_name", {
+      paneIdx: props.panel_idx,
+    });
+    return (result.length === 0) ? [''] : result;
   }
 
   const moveUp = () => { setupCurrentIndex(currentIndex - 1, false) }
@@ -256,14 +332,14 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
   const moveDownSelect = () => { setupCurrentIndex(currentIndex + 1, true) }
   const moveTop = () => { setupCurrentIndex(0, false) }
   const moveTopSelect = () => { setupCurrentIndex(0, true) }
-  const moveBottom = () => { setupCurrentIndex(filteredEntries.length - 1, false) }
-  const moveBottomSelect = () => { setupCurrentIndex(filteredEntries.length - 1, true) }
+  const moveBottom = () => { setupCurrentIndex(filteredItemNum - 1, false) }
+  const moveBottomSelect = () => { setupCurrentIndex(filteredItemNum - 1, true) }
   const selectAll = () => {
-    const isSelectAll = filteredEntries.every(item => item.file_list_item.is_selected);
+    const isSelectAll = props.fileListInfo.selected_item_num === filteredItemNum;
     if (isSelectAll) {
       clearSelection();
     } else {
-      addSelectingIndexRange(0, filteredEntries.length - 1)
+      addSelectingIndexRange(0, filteredItemNum - 1)
     }
   }
   const clearSelection = async () => {
@@ -284,6 +360,43 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
     setSelectingIndexArray([currentIndex]);
   }
 
+  const [sortSpec, setSortSpec] = useState<SortSpec | null>(null);
+  useEffect(() => {
+    (async () => {
+      setSortSpec(await invoke<SortSpec>('get_sort_spec', { paneIdx: props.panel_idx }));
+    })()
+  }, [props.panel_idx]);
+
+  const sortBy = async (sortKey: SortKey) => {
+    const paneInfo = await invoke<FileListUiInfo>('sort_file_list', {
+      paneIdx: props.panel_idx,
+      sortKey: sortKey,
+    });
+    props.updateFileListInfo(paneInfo);
+    setSortSpec(await invoke<SortSpec>('get_sort_spec', { paneIdx: props.panel_idx }));
+  }
+  const updateSortSpec = async (newSortSpec: SortSpec) => {
+    const paneInfo = await invoke<FileListUiInfo>('set_sort_spec', {
+      paneIdx: props.panel_idx,
+      sortSpec: newSortSpec,
+    });
+    props.updateFileListInfo(paneInfo);
+    setSortSpec(newSortSpec);
+  }
+  const toggleDirectoriesFirst = () => {
+    if (!sortSpec) { return; }
+    updateSortSpec({ ...sortSpec, directories_first: !sortSpec.directories_first });
+  }
+  const toggleNaturalSort = () => {
+    if (!sortSpec) { return; }
+    updateSortSpec({ ...sortSpec, natural_order: !sortSpec.natural_order });
+  }
+
+  const toggleDirectorySize = async () => {
+    const enabled = await invoke<boolean>('is_directory_size_enabled', { paneIdx: props.panel_idx });
+    invoke('set_directory_size_enabled', { paneIdx: props.panel_idx, enabled: !enabled });
+  }
+
   async function setSelectingIndexArray(newIdxList: Array<number>) {
     const paneInfo = await invoke<FileListUiInfo>("set_selecting_idx", {
       paneIdx: props.panel_idx,
@@ -333,7 +446,8 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
       });
     }
 
-    const entry = filteredEntries[row_idx];
+    const entry = filteredEntry(row_idx);
+    if (!entry) { return toTableColor(colorSetting.defaultColor); }
     const isSelectionColor = props.isActive && entry.file_list_item.is_selected;
     if (isSelectionColor) {
       return toTableColor(colorSetting.selectionColor);
@@ -352,7 +466,7 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
   }
 
   const filteredItemNumInfo = () => {
-    const filteredNum = (props.fileListInfo.full_item_num - filteredEntries.length);
+    const filteredNum = (props.fileListInfo.full_item_num - filteredItemNum);
     if (filteredNum === 0) { return '' }
     return filteredNum + ' file(s) filterd.'
   }
@@ -360,7 +474,7 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
   function FileNameWithEmphasis(item: FileListFilteredItem): React.ReactNode {
     const file_name = item.file_list_item.file_name;
     const emphasisIdxAry = item.matched_idx_list;
-    const charFlagPairs = file_name.split('').map((str, idx) => {
+    const charFlagPairs = Array.from(file_name).map((str, idx) => {
       const flag = emphasisIdxAry.includes(idx);
       return { str, flag };
     });
@@ -379,10 +493,14 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
       return colorSetting.defaultColor.activeHightlight;
     })();
 
+    const contentMatch = item.file_list_item.content_match;
     return <>
       {charFlagPairs.map((pair, idx) => pair.flag
         ? <b style={{ color: enphansisColor }} key={idx}>{pair.str}</b>
         : <span key={idx}>{pair.str}</span>)}
+      {contentMatch && contentMatch.match_count > 0
+        ? <span style={{ opacity: 0.6 }}>{` [${contentMatch.match_count}] ${contentMatch.first_line_number}: ${contentMatch.first_line}`}</span>
+        : null}
     </>;
   }
 
@@ -402,13 +520,21 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
     clearSelection: clearSelection,
     toggleSelection: toggleSelection,
     selectCurrentOnly,
+    toggleDirectoriesFirst,
+    toggleNaturalSort,
+    toggleDirectorySize,
   }
 
   const colWidthsTotal = colWidths.reduce((acc, cur) => acc + cur, 0);
 
   const Row = ({ index, style }: { index: number; style: React.CSSProperties }) => {
-    if (IsValidIndex(filteredEntries, index)) {
-      const entry = filteredEntries[index].file_list_item;
+    if (isValidItemIndex(index)) {
+      const filteredItem = filteredEntry(index);
+      if (!filteredItem) {
+        // 一覧の受信待ち
+        return <div key={"Row" + index} style={{ ...style }} css={table_color(index)} />;
+      }
+      const entry = filteredItem.file_list_item;
       return (
         <div
           key={"Row" + index}
@@ -448,10 +574,10 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
                 >
                   {(() => {
                     switch (columnIndex) {
-                      case 0: return <img src={`data:image/bmp;base64,${entry.file_icon ?? ""}`} />;
-                      case 1: return < >{FileNameWithEmphasis(filteredEntries[index])}</>;
+                      case 0: return <img src={`data:image/${IconImageType(entry.file_icon)};base64,${entry.file_icon ?? ""}`} />;
+                      case 1: return < >{FileNameWithEmphasis(filteredItem)}</>;
                       case 2: return < >{entry.file_extension}</>;
-                      case 3: return < >{entry.file_size ?? "-"}</>;
+                      case 3: return < >{FileSizeStr(entry)}</>;
                       case 4: return < >{entry.date}</>;
                     }
                   })()}
@@ -475,9 +601,9 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
     } else {
       return <div
         style={{ ...style }}
-        onMouseDown={(event) => { onMouseDown(filteredEntries.length, event) }}
+        onMouseDown={(event) => { onMouseDown(filteredItemNum, event) }}
         onMouseUp={onMouseUp}
-        onMouseMove={(event) => { onMouseMove(filteredEntries.length, event) }}
+        onMouseMove={(event) => { onMouseMove(filteredItemNum, event) }}
       >
         {filteredItemNumInfo()}
       </div>
@@ -496,8 +622,8 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
       <FileListHeader
         backgroundColor={theme.baseColor.backgroundColor}
         foregroundColor={theme.baseColor.stringDefaultColor}
-        panel_idx={props.panel_idx}
-        updateFileListInfo={props.updateFileListInfo}
+        sortSpec={sortSpec}
+        onSortKeyClicked={sortBy}
         colWidths={colWidths}
         setColWidths={setColWidths}
       />
@@ -506,7 +632,7 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
           height={props.height - headerHeight}
           width={Math.max(colWidthsTotal, props.width)}
           itemSize={rowHeight}
-          itemCount={filteredEntries.length + 1}
+          itemCount={filteredItemNum + 1}
           ref={listRef}
           onItemsRendered={
             (props) => setVisibleRange({ start: props.visibleStartIndex, end: props.visibleStopIndex })}
@@ -518,6 +644,19 @@ export const FileList = forwardRef<FileListFunc, FileListProps>((props, ref) =>
   )
 });
 
+///////////////////////////////////////////////////////////////////////////////////////////////////
+// Windows では BMP、Linux では PNG のアイコンが来る。
+function IconImageType(fileIcon: string | null): string {
+  return fileIcon?.startsWith('iVBORw0KGgo') ? 'png' : 'bmp';
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+function FileSizeStr(entry: FileListItem): string {
+  if (entry.file_size === null) { return "-"; }
+  if (entry.file_count === null) { return entry.file_size.toString(); }
+  return `${entry.file_size} (${entry.file_count} files)`;
+}
+
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 function CalcScrollIndex(
   visibleRange: { start: number; end: number; },
@@ -540,8 +679,8 @@ function FileListHeader(
   props: {
     backgroundColor: string,
     foregroundColor: string,
-    panel_idx: number,
-    updateFileListInfo: (info: FileListUiInfo) => void,
+    sortSpec: SortSpec | null,
+    onSortKeyClicked: (sortKey: SortKey) => void,
     colWidths: number[],
     setColWidths: React.Dispatch<React.SetStateAction<number[]>>
   }
@@ -569,6 +708,13 @@ function FileListHeader(
     document.removeEventListener("mouseup", handleMouseUp);
   };
 
+  // 先頭のソートキーのみ、向きを表示する。
+  const sortMark = (sortKey: SortKey | null) => {
+    const first = props.sortSpec?.conditions[0];
+    if (!first || first.key !== sortKey) { return ''; }
+    return (first.order === 'Ascending') ? ' ▲' : ' ▼';
+  }
+
   const separator = (idx: number) => <div
     onMouseDown={(e) => handleMouseDown(idx, e)}
     style={{
@@ -596,13 +742,9 @@ function FileListHeader(
     {columns.map((col, idx) => (
       <div
         key={idx}
-        onClick={async () => {
+        onClick={() => {
           if (!col.sortKey) return;
-          const paneInfo = await invoke<FileListUiInfo>('sort_file_list', {
-            paneIdx: props.panel_idx,
-            sortKey: col.sortKey,
-          });
-          props.updateFileListInfo(paneInfo);
+          props.onSortKeyClicked(col.sortKey);
         }}
         style={{
           flexShrink: 0,
@@ -615,6 +757,7 @@ function FileListHeader(
         }}
       >
         {col.title}
+        {sortMark(col.sortKey)}
         {separator(idx)}
       </div>
     ))}
@@ -0,0 +1,91 @@
+import { forwardRef, useImperativeHandle, useRef } from 'react';
+
+/** @jsxImportSource @emotion/react */
+import { css } from '@emotion/react'
+import { SelectInstance } from 'react-select'
+import AsyncSelect from 'react-select/async'
+import { ComboBoxStyle, useTheme } from './ThemeStyle';
+import { invoke } from '@tauri-apps/api/core';
+
+
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+type GoToCandidate = {
+  path: string,
+  dirctry_path: string,
+  file_name: string,
+  matched_idx_list: number[],
+};
+
+type GoToComboItem = {
+  value: GoToCandidate,
+  label: string,
+};
+
+const loadCandidates = async (query: string): Promise<GoToComboItem[]> => {
+  const candidates = await invoke<GoToCandidate[]>('go_to_anywhere', { query, maxNum: null })
+    .catch(_ => []);
+  return candidates.map(candidate => ({ value: candidate, label: candidate.path }));
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+export interface GoToAnywhereBarFunc {
+  focus: () => void,
+};
+
+type GoToAnywhereBarProps = {
+  onSelected: (dirctryPath: string, fileName: string) => void,
+  onEndEdit: () => void,
+};
+
+// 索引済みの全てのファイルから、パスの一部で探して移動する。
+export const GoToAnywhereBar = forwardRef<GoToAnywhereBarFunc, GoToAnywhereBarProps>((props, ref) => {
+  useImperativeHandle(ref, () => functions);
+  const selectRef = useRef<SelectInstance<GoToComboItem>>(null);
+
+  const theme = useTheme();
+  const comboBoxStyle = ComboBoxStyle(theme.baseColor);
+
+  const functions = {
+    focus: () => selectRef.current?.focus(),
+  }
+
+  const formatOptionLabel = (item: GoToComboItem) => {
+    const emphasisIdxAry = item.value.matched_idx_list;
+    return <div>
+      {Array.from(item.value.path).map((c, idx) => {
+        const isEmphasis = emphasisIdxAry.includes(idx);
+        return <span key={idx} css={css({ fontWeight: isEmphasis ? 'bold' : 'normal' })}>{c}</span>
+      })}
+    </div>
+  }
+
+  return <div
+    css={css({
+      display: 'grid',
+      gridTemplateColumns: 'auto 1fr',
+      textAlign: 'right',
+    })}
+  >
+    <div>Go to:</div>
+    <AsyncSelect
+      styles={comboBoxStyle}
+      ref={selectRef}
+      placeholder='Path in file index'
+      loadOptions={loadCandidates}
+      formatOptionLabel={formatOptionLabel}
+      value={null}
+      autoFocus={true}
+      openMenuOnFocus={true}
+      onBlur={props.onEndEdit}
+      onKeyDown={(event) => {
+        if (event.key === 'Escape') { props.onEndEdit(); }
+      }}
+      onChange={(val) => {
+        if (val === null) { return; }
+        props.onSelected(val.value.dirctry_path, val.value.file_name);
+        props.onEndEdit();
+      }}
+    />
+  </div>
+});
@@ -113,6 +113,6 @@ export async function writeKeyBindSetting(setting: KeyBindSetting[]) {
 
 export async function readKeyBindSetting(): Promise<KeyBindSetting[]> {
   const read = await readSettings(new SettingInfo);
-  return read ?? GenerateDefaultKeyBindSeting();
+  return read ?? await GenerateDefaultKeyBindSeting();
 }
 
@@ -8,7 +8,8 @@ import { UnlistenFn, listen } from "@tauri-apps/api/event";
 import { Box } from "@mui/material";
 
 import { IoIosArrowDropright, IoIosArrowDropdown } from "react-icons/io";
-import { TextInputStyle, useTheme } from "./ThemeStyle";
+import { ButtonStyle, TextInputStyle, useTheme } from "./ThemeStyle";
+import { invoke } from "@tauri-apps/api/core";
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
 export interface LogMessagePeinFunc {
@@ -29,15 +30,25 @@ export interface LogInfo {
   stderr: string,
 }
 
+// ファイル操作のジョブ。ログの id とジョブの id は同じ。
+type FileJobInfo = {
+  id: string,
+  title: string,
+  description: string,
+  state: 'Queued' | 'Running' | 'Paused',
+}
+
 function LogPane(
   props: {
     logPaneInfo: LogPaneInfo,
+    job: FileJobInfo | undefined,
     onClick: () => void,
     onCommandClick: () => void,
   }
 ) {
   const theme = useTheme();
   const textInputStyle = TextInputStyle(theme.baseColor);
+  const buttonStyle = ButtonStyle(theme.baseColor);
 
   const logInfo = props.logPaneInfo.logInfo;
 
@@ -52,6 +63,24 @@ function LogPane(
       : <IoIosArrowDropright />
   }
 
+  function JobButtons(job: FileJobInfo) {
+    const isPaused = job.state === 'Paused';
+    return <div onClick={e => e.stopPropagation()}>
+      <button
+        css={buttonStyle}
+        onClick={() => invoke(isPaused ? 'resume_file_job' : 'pause_file_job', { jobId: job.id })}
+      >
+        {isPaused ? 'Resume' : 'Pause'}
+      </button>
+      <button
+        css={buttonStyle}
+        onClick={() => invoke('cancel_file_job', { jobId: job.id })}
+      >
+        Cancel
+      </button>
+    </div>
+  }
+
   function Deteal() {
     return <>
       <div
@@ -126,6 +155,7 @@ function LogPane(
         <Icon
           isOpen={props.logPaneInfo.isOpen}
         />
+        {props.job ? JobButtons(props.job) : <></>}
       </div>
       {
         props.logPaneInfo.isOpen
@@ -172,6 +202,20 @@ export const LogMessagePein = forwardRef<LogMessagePeinFunc, LogMessagePeinProps
     }
   }, [])
 
+  const [jobAry, setJobAry] = useState<FileJobInfo[]>([]);
+  useEffect(() => {
+    let unlisten: UnlistenFn | null;
+    (async () => {
+      setJobAry(await invoke<FileJobInfo[]>('list_file_jobs'));
+      unlisten = await listen('file_jobs_updated', event => {
+        setJobAry(event.payload as FileJobInfo[]);
+      });
+    })()
+    return () => {
+      if (unlisten) { unlisten(); }
+    }
+  }, [])
+
   const logPaneRef = React.createRef<HTMLDivElement>();
   useEffect(() => {
     if (requireScrollToBottom) { scrollToBottom(); }
@@ -211,6 +255,7 @@ export const LogMessagePein = forwardRef<LogMessagePeinFunc, LogMessagePeinProps
           logAry.map((logInfo, idx) => <div key={idx} >{
             <LogPane
               logPaneInfo={logInfo}
+              job={jobAry.find(job => job.id === logInfo.logInfo.id)}
               onClick={() => toggleLogPaneOpen(idx)}
               onCommandClick={() => toggleLogPaneCommandOpen(idx)} />
           }</div>)
@@ -14,6 +14,8 @@ import { TabColorSettings } from './TabColorSetting';
 import { ReadLastOpenedTabs, TabInfo, TabsInfo, WriteLastOpenedTabs } from './TabsInfo';
 import { BookMarkPane } from './BookMarkPane';
 import { Updater, UpdaterFunc } from './Updater';
+import { FileConflictDialog } from './FileConflictDialog';
+import { TrashDialog, TrashDialogFunc } from './TrashDialog';
 import { invoke } from '@tauri-apps/api/core';
 
 import { ErrorBoundary, FallbackProps } from 'react-error-boundary';
@@ -60,12 +62,12 @@ export function MainModeView(
 
   const [currentPaneIndex, setCurrentPaneIndex] = useState(0);
   const [openSettings, setOpenSettings] = useState(false);
-  const [itemNums, setItemNums] = useState<number[]>([0, 0]);
-  const [selectItemNums, setSelectItemNums] = useState<number[]>([0, 0]);
+  const [itemNums, setItemNums] = useState<number[]>([]);
+  const [selectItemNums, setSelectItemNums] = useState<number[]>([]);
 
   const [statasBarStr, setStatasBarStr] = useState("");
   useEffect(() => {
-    setStatasBarStr(`Item:${itemNums[currentPaneIndex]}  Select:${selectItemNums[currentPaneIndex]}`);
+    setStatasBarStr(`Item:${itemNums[currentPaneIndex] ?? 0}  Select:${selectItemNums[currentPaneIndex] ?? 0}`);
   }, [itemNums, selectItemNums, currentPaneIndex]);
   const [separator, setSeparator] = useState<separator>('\\');
 
@@ -106,10 +108,12 @@ export function MainModeView(
     WriteLastOpenedTabs(newTabsPathAry);
   }
 
+  // ペインが 3 つ以上の場合は、次のペインを反対側のペインとして扱う。
+  const oppositePaneIndex = (paneIndex: number) => (paneIndex + 1) % tabsPathAry.length;
+
   const getOppositePath = () => {
     if (tabsPathAry.length === 0) { return ''; }
-    const oppositeIndex = (currentPaneIndex + 1) % 2;
-    return GetActive(tabsPathAry[oppositeIndex]).path;
+    return GetActive(tabsPathAry[oppositePaneIndex(currentPaneIndex)]).path;
   }
 
   const setItemNum = (value: number, idx: number) => {
@@ -127,7 +131,7 @@ export function MainModeView(
     });
   }
 
-  const grid = [React.createRef<HTMLDivElement>(), React.createRef<HTMLDivElement>()];
+  const grid = tabsPathAry.map(_ => React.createRef<HTMLDivElement>());
 
 
   const logMessagePeinFunc = useRef<LogMessagePeinFunc>(null);
@@ -137,10 +141,11 @@ export function MainModeView(
   };
 
   const updaterFunc = useRef<UpdaterFunc>(null);
+  const trashDialogFunc = useRef<TrashDialogFunc>(null);
 
   const commandBarHeight = 60; // とりあえず固定で。
   const borderThickness = 2;
-  const paneHeight = ((props.height - commandBarHeight) / 2) - (borderThickness * 2);
+  const paneHeight = ((props.height - commandBarHeight) / Math.max(tabsPathAry.length, 1)) - (borderThickness * 2);
 
   async function OpenSettingDir(): Promise<void> {
     const settingDir = await invoke<string>("setting_dir", {}).catch(_ => null);
@@ -158,8 +163,7 @@ export function MainModeView(
   }
 
   function duplicateTabToOppositePane(dirPath: string) {
-    const oppositeIndex = (currentPaneIndex + 1) % 2;
-    addTab(oppositeIndex, dirPath);
+    addTab(oppositePaneIndex(currentPaneIndex), dirPath);
   }
 
   const commandBarFunc = useRef<CommandBarFuncs>(null);
@@ -193,6 +197,11 @@ export function MainModeView(
         addLogMessage={addLogMessage}
         ref={updaterFunc}
       />
+      <FileConflictDialog />
+      <TrashDialog
+        addLogMessage={addLogMessage}
+        ref={trashDialogFunc}
+      />
       <div
         css={css({
           display: 'grid',
@@ -213,7 +222,7 @@ export function MainModeView(
         <div
           css={css({
             display: 'grid',
-            gridTemplateRows: 'auto auto auto', // Pane Pane commandBar
+            gridTemplateRows: 'auto '.repeat(tabsPathAry.length) + 'auto', // Pane... commandBar
           })}
         >
           {
@@ -234,7 +243,6 @@ export function MainModeView(
                   <ErrorBoundary FallbackComponent={ErrorFallback}>
                     <PaneTabs
                       isActive={isActive}
-                      panel_idx={idx}
                       height={paneHeight}
                       pathAry={pathAry}
                       tabColorSetting={props.tabColorSetting}
@@ -248,7 +256,7 @@ export function MainModeView(
                       setTabName={props.setTabName}
                       separator={separator}
                       gridRef={grid[idx]}
-                      focusOppositePane={() => { grid[(idx + 1) % 2].current?.focus(); }}
+                      focusOppositePane={() => { grid[oppositePaneIndex(idx)].current?.focus(); }}
                       focusCommandBar={() => commandBarFunc.current?.focus()}
                       setKeyBind={props.setKeyBind}
                       duplicateTabToOppositePane={duplicateTabToOppositePane}
@@ -260,14 +268,14 @@ export function MainModeView(
           <CommandBar
             path={getPath}
             addLogMessage={addLogMessage}
-            focusToFileList={() => grid[currentPaneIndex].current?.focus()}
+            focusToFileList={() => grid[currentPaneIndex]?.current?.focus()}
             ref={commandBarFunc}
           />
         </div>
         <div
           css={css({
             display: 'grid',
-            gridTemplateRows: 'auto auto auto 1fr auto', // Separator CheckBox Settings logPane statusBar
+            gridTemplateRows: 'auto auto auto auto 1fr auto', // Separator Trash CheckBox Settings logPane statusBar
             height: props.height - 20,
           })}
         >
@@ -276,6 +284,11 @@ export function MainModeView(
             onClick={() => { setSeparator(separator === '/' ? '\\' : '/') }}>
             separator:{separator}
           </button>
+          <button
+            css={settingButtonStyle}
+            onClick={() => { trashDialogFunc.current?.open() }}>
+            trash
+          </button>
 
           <label>
             <input
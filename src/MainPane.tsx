@@ -6,7 +6,7 @@ import React from 'react';
 
 import { separator } from './FilePathSeparator';
 import { AddressBar, AddressBarFunc, } from './AddressBar';
-import { FileList, FileListFunc, FileListUiInfo, } from './FileList';
+import { ApplyFileListDelta, FileList, FileListDelta, FileListFunc, FileListUiInfo, } from './FileList';
 
 import { BUILDIN_COMMAND_TYPE, CommandExecuter, CommandExecuterFunc } from './CommandInfo';
 import { KeyBindSetting, COMMAND_TYPE, readKeyBindSetting, match } from './KeyBindInfo';
@@ -23,11 +23,14 @@ import { executeShellCommand } from './RustFuncs';
 import { TabFuncs } from './PaneTabs';
 import { ContextMenuInfo, readContextMenuSetting } from './ContextMenu';
 import { LogInfo } from './LogMessagePane';
-import { FileFilterBar, FileFilterBarFunc, FileFilterType } from './FileFilterBar';
+import { FileFilterBar, FileFilterBarFunc, FileFilterType, FilterRetention } from './FileFilterBar';
+import { GoToAnywhereBar } from './GoToAnywhereBar';
+import { AccessErrorLog } from './AccessError';
 import { MenuitemStyle, ReadonlyTextInputStyle, useTheme } from './ThemeStyle';
 import { UnlistenFn, listen } from '@tauri-apps/api/event';
 import { PiLinkLight } from 'react-icons/pi';
 import AutoSizer from 'react-virtualized-auto-sizer';
+import { v4 as uuidv4 } from 'uuid'
 
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////
@@ -40,7 +43,6 @@ export type UpdateFileListUiInfo = {
 export const MainPanel = (
   props: {
     isActive: boolean,
-    panel_idx: number,
     dirPath: string,
     pined: boolean,
     onPathChanged: (newPath: string) => void
@@ -57,12 +59,44 @@ export const MainPanel = (
     duplicateTabToOppositePane: () => void,
   }
 ) => {
+  // バックエンド側のペインの id。表示している間だけ確保する。
+  const [paneIdx, setPaneIdx] = useState<number | null>(null);
   useEffect(() => {
-    filterBarFunc.current?.clearFilter();
-    AccessDirectory(props.dirPath, null);
-  }, [props.dirPath]);
+    let createdPaneIdx: number | null = null;
+    let unmounted = false;
+    (async () => {
+      const newPaneIdx = await invoke<number>("create_pane");
+      if (unmounted) {
+        invoke("close_pane", { paneIdx: newPaneIdx });
+        return;
+      }
+      createdPaneIdx = newPaneIdx;
+      setPaneIdx(newPaneIdx);
+    })()
+    return () => {
+      unmounted = true;
+      if (createdPaneIdx !== null) { invoke("close_pane", { paneIdx: createdPaneIdx }); }
+    }
+  }, []);
 
-  const [fileListInfo, setFileListInfo] = useState<FileListUiInfo | null>(null);
+  // 履歴の移動で表示済みのパス。フィルタのクリアと再取得をしない為に使う。
+  const navigatedDirPath = useRef<string | null>(null);
+  useEffect(() => {
+    if (paneIdx === null) { return; }
+    if (navigatedDirPath.current === props.dirPath) {
+      navigatedDirPath.current = null;
+      return;
+    }
+    AccessDirectory(props.dirPath, null);
+  }, [props.dirPath, paneIdx]);
+
+  const [fileListInfo, setFileListInfoState] = useState<FileListUiInfo | null>(null);
+  // 差分の適用はイベントのコールバック内で行うので、最新の値を参照できる様にしておく。
+  const fileListInfoRef = useRef<FileListUiInfo | null>(null);
+  const setFileListInfo = (info: FileListUiInfo | null) => {
+    fileListInfoRef.current = info;
+    setFileListInfoState(info);
+  }
 
   const [linkDestination, setLinkDestination] = useState<string | null>(null);
   useEffect(
@@ -77,10 +111,7 @@ export const MainPanel = (
 
   useEffect(() => {
     props.onItemNumChanged(fileListInfo?.full_item_num ?? 0);
-    const selectedItemNum = fileListInfo?.filtered_item_list
-      .filter(item => item.file_list_item.is_selected)
-      .length ?? 0;
-    props.onSelectItemNumChanged(selectedItemNum);
+    props.onSelectItemNumChanged(fileListInfo?.selected_item_num ?? 0);
   }, [fileListInfo]);
 
   const filterBarFunc = useRef<FileFilterBarFunc>(null);
@@ -89,7 +120,7 @@ export const MainPanel = (
     const newFileListInfo = await invoke<FileListUiInfo | null>(
       "set_filter",
       {
-        paneIdx: props.panel_idx,
+        paneIdx: paneIdx,
         filter: {
           filter_type: filterType,
           matcher_str: matcherString,
@@ -99,17 +130,39 @@ export const MainPanel = (
   }
 
   useEffect(() => {
+    if (paneIdx === null) { return; }
     let unlisten: UnlistenFn | null;
+    let unlistenPatch: UnlistenFn | null;
     (async () => {
       unlisten = await listen('update_path_list', event => {
         const payload = (event.payload as UpdateFileListUiInfo);
-        if (payload.pane_idx !== props.panel_idx) { return; }
+        if (payload.pane_idx !== paneIdx) { return; }
 
         setFileListInfo(payload.data);
       });
+      unlistenPatch = await listen('patch_path_list', async event => {
+        const payload = (event.payload as FileListDelta);
+        if (payload.pane_idx !== paneIdx) { return; }
+
+        const current = fileListInfoRef.current;
+        if (current && payload.version <= current.version) { return; }
+        if (current && payload.base_version === current.version) {
+          setFileListInfo(ApplyFileListDelta(current, payload));
+          return;
+        }
+
+        // 差分の基準がずれているので、一覧全体を取り直す。
+        const fullInfo = await invoke<FileListUiInfo | null>(
+          "get_file_list_ui_info",
+          { paneIdx: paneIdx });
+        setFileListInfo(fullInfo);
+      });
     })()
-    return () => { if (unlisten) { unlisten(); } }
-  }, [])
+    return () => {
+      if (unlisten) { unlisten(); }
+      if (unlistenPatch) { unlistenPatch(); }
+    }
+  }, [paneIdx])
 
   const [keyBindInfo, setKeyBindInfo] = useState<KeyBindSetting[]>([]);
   useEffect(() => {
@@ -160,16 +213,18 @@ export const MainPanel = (
 
   const onAddressInputed = async (path: string) => {
     const adjusted = await invoke<AdjustedAddressbarStr>("adjust_addressbar_str", { str: path })
-      .catch(_ => { return null; });
-    AccessDirectory(
-      adjusted?.dir ?? path,
-      adjusted?.file_name ?? "");
-    if (adjusted) {
-      myGrid.current?.focus();
-    }
+      .catch(error => {
+        props.addLogMessage(AccessErrorLog("Access failed.", error));
+        return null;
+      });
+    if (!adjusted) { return; }
+
+    AccessDirectory(adjusted.dir, adjusted.file_name);
+    myGrid.current?.focus();
   }
 
   const AccessDirectory = async (trgDir: string, trgFile: string | null) => {
+    if (paneIdx === null) { return; }
     // normalize だと、ドライブ直下が `C:\\` となるので、一旦末端の区切りを削除してから、区切りを付加する。
     const newDir = (trgDir === "")
       ? ""
@@ -181,12 +236,147 @@ export const MainPanel = (
     }
 
     props.onPathChanged(newDir);
-    const paneInfo = await invoke<FileListUiInfo>("set_dirctry_path", {
-      paneIdx: props.panel_idx,
+    const paneInfo = await invoke<FileListUiInfo | null>("set_dirctry_path", {
+      paneIdx: paneIdx,
       path: newDir,
       initialFocus: trgFile,
+    }).catch(error => {
+      props.addLogMessage(AccessErrorLog("Access failed.", error));
+      return null;
     });
     setFileListInfo(paneInfo);
+    syncFilterBar();
+  }
+
+  // 移動先のフィルタはバックエンドが決める。(クリア、そのまま、ディレクトリ毎に復元)
+  const syncFilterBar = async () => {
+    const filter = await invoke<{ filter_type: FileFilterType, matcher_str: string }>("get_filter", { paneIdx: paneIdx })
+      .catch(_ => null);
+    if (!filter) { return; }
+    filterBarFunc.current?.setFilter(filter.filter_type, filter.matcher_str);
+  }
+
+  const [filterRetention, setFilterRetention] = useState<FilterRetention>('Clear');
+  useEffect(() => {
+    if (paneIdx === null) { return; }
+    invoke<FilterRetention>("get_filter_retention", { paneIdx: paneIdx }).then(setFilterRetention);
+  }, [paneIdx]);
+  const cycleFilterRetention = async () => {
+    const order: FilterRetention[] = ['Clear', 'Sticky', 'PerDirectory'];
+    const next = order[(order.indexOf(filterRetention) + 1) % order.length];
+    await invoke<void>("set_filter_retention", { paneIdx: paneIdx, retention: next });
+    setFilterRetention(next);
+  }
+
+  // 配下の全てのファイルを相対パスで表示する。フィルタとソートはそのまま。
+  type RecursiveListing = { max_depth: number, exclude_patterns: string[] };
+  const defaultRecursiveListing: RecursiveListing = {
+    max_depth: 16,
+    exclude_patterns: ['.git', 'node_modules', 'target'],
+  };
+  const [recursiveListing, setRecursiveListing] = useState<RecursiveListing | null>(null);
+  useEffect(() => {
+    if (paneIdx === null) { return; }
+    invoke<RecursiveListing | null>("get_recursive_listing", { paneIdx: paneIdx }).then(setRecursiveListing);
+  }, [paneIdx]);
+  const toggleRecursiveListing = async () => {
+    const next = recursiveListing ? null : defaultRecursiveListing;
+    const paneInfo = await invoke<FileListUiInfo | null>("set_recursive_listing", {
+      paneIdx: paneIdx,
+      recursiveListing: next,
+    }).catch(error => {
+      props.addLogMessage(AccessErrorLog("Access failed.", error));
+      return null;
+    });
+    setFileListInfo(paneInfo);
+    setRecursiveListing(next);
+  }
+
+  // 進捗と項目毎のエラーは、バックエンドからログペインに送られる。
+  // 何も選択していない時の [''] は、ディレクトリ自体を指してしまうので除く。
+  const selectingFileItemNames = async () => {
+    const itemNames = await FileListFunctions.current?.selectingItemName() ?? [];
+    return itemNames.filter(itemName => itemName !== '');
+  }
+  const transferSelection = async (command: 'copy_items' | 'move_items') => {
+    const itemNames = await selectingFileItemNames();
+    if (itemNames.length === 0) { return; }
+    invoke<string>(command, {
+      srcDir: props.dirPath,
+      itemNames: itemNames,
+      dstDir: props.getOppositePath(),
+      conflictPolicy: 'Ask',
+    });
+  }
+  const removeSelection = async (command: 'delete_items' | 'trash_items') => {
+    const itemNames = await selectingFileItemNames();
+    if (itemNames.length === 0) { return; }
+    if (command === 'trash_items' && !await invoke<boolean>('is_trash_supported')) {
+      props.addLogMessage({
+        title: 'Trash is not supported on this platform.',
+        stdout: '',
+        stderr: "Use 'Delete permanently' (deleteSelection) to delete the items.",
+        id: uuidv4(),
+        command: '',
+        rc: null,
+      });
+      return;
+    }
+    invoke<string>(command, { srcDir: props.dirPath, itemNames: itemNames });
+  }
+  const renameSelection = async () => {
+    const srcNames = await selectingFileItemNames();
+    if (srcNames.length === 0) { return; }
+    commandExecuterFunc.current?.inputByDialog('Rename', srcNames, (dstNames) => {
+      invoke<void>("rename_items", { dir: props.dirPath, srcNames: srcNames, dstNames: dstNames })
+        .catch(error => props.addLogMessage(AccessErrorLog('Rename failed.', error)));
+    });
+  }
+  const createItems = (isDir: boolean) => {
+    commandExecuterFunc.current?.inputByDialog(isDir ? 'New Folder' : 'New File', [], (names) => {
+      invoke<void>("create_items", { dir: props.dirPath, names: names, isDir: isDir })
+        .catch(error => props.addLogMessage(AccessErrorLog('Create failed.', error)));
+    });
+  }
+  // 記録したファイル操作を取り消す(やり直す)。記録後に変更されていたら、理由をログに出す。
+  const applyFileJournal = (command: 'undo_file_operation' | 'redo_file_operation') => {
+    const title = (command === 'undo_file_operation') ? 'Undo' : 'Redo';
+    invoke<{ title: string, description: string }>(command, {})
+      .then(applied => props.addLogMessage({
+        title: `${title}: ${applied.title}`,
+        stdout: applied.description,
+        stderr: '',
+        id: uuidv4(),
+        command: '',
+        rc: 0,
+      }))
+      .catch(error => props.addLogMessage(AccessErrorLog(`${title} refused.`, error)));
+  }
+
+  // 索引済みのファイルへ、ディレクトリを知らなくても移動する。
+  const [isGoToAnywhereOpen, setGoToAnywhereOpen] = useState(false);
+  const goToAnywhere = () => {
+    setFocusToListOnContextMenuClosed(false);
+    setGoToAnywhereOpen(true);
+  }
+  type FileIndexSetting = { roots: string[], exclude_patterns: string[] };
+  const toggleFileIndexRoot = async () => {
+    if (props.dirPath === "") { return; }
+    const root = RemoveTrailingSeparators(props.dirPath);
+    const setting = await invoke<FileIndexSetting>("get_file_index_setting");
+    const isIndexed = setting.roots.includes(root);
+    const roots = isIndexed
+      ? setting.roots.filter(indexed => indexed !== root)
+      : [...setting.roots, root];
+    await invoke<void>("set_file_index_setting", { setting: { ...setting, roots: roots } });
+    props.addLogMessage({
+      title: isIndexed ? "Removed from file index." : "Added to file index.",
+      stdout: root,
+      stderr: '',
+      id: uuidv4(),
+      command: '',
+      rc: null,
+    });
   }
 
   const focusAddoressBar = () => {
@@ -220,6 +410,8 @@ export const MainPanel = (
     switch (commandName) {
       case BUILDIN_COMMAND_TYPE.accessCurrentItem: FileListFunctions.current?.accessCurrentItem(); return;
       case BUILDIN_COMMAND_TYPE.accessParentDir: accessParentDir(); return;
+      case BUILDIN_COMMAND_TYPE.historyBack: navigateHistory('go_back'); return;
+      case BUILDIN_COMMAND_TYPE.historyForward: navigateHistory('go_forward'); return;
       case BUILDIN_COMMAND_TYPE.moveUp: FileListFunctions.current?.moveUp(); return;
       case BUILDIN_COMMAND_TYPE.moveUpSelect: FileListFunctions.current?.moveUpSelect(); return;
       case BUILDIN_COMMAND_TYPE.moveDown: FileListFunctions.current?.moveDown(); return;
@@ -232,6 +424,9 @@ export const MainPanel = (
       case BUILDIN_COMMAND_TYPE.clearSelection: FileListFunctions.current?.clearSelection(); return;
       case BUILDIN_COMMAND_TYPE.toggleSelection: FileListFunctions.current?.toggleSelection(); return;
       case BUILDIN_COMMAND_TYPE.selectCurrentOnly: FileListFunctions.current?.selectCurrentOnly(); return;
+      case BUILDIN_COMMAND_TYPE.toggleDirectoriesFirst: FileListFunctions.current?.toggleDirectoriesFirst(); return;
+      case BUILDIN_COMMAND_TYPE.toggleNaturalSort: FileListFunctions.current?.toggleNaturalSort(); return;
+      case BUILDIN_COMMAND_TYPE.toggleDirectorySize: FileListFunctions.current?.toggleDirectorySize(); return;
       case BUILDIN_COMMAND_TYPE.addNewTab: addNewTab(); return;
       case BUILDIN_COMMAND_TYPE.removeTab: removeTab(); return;
       case BUILDIN_COMMAND_TYPE.removeOtherTabs: removeOtherTabs(); return;
@@ -245,6 +440,27 @@ export const MainPanel = (
       case BUILDIN_COMMAND_TYPE.clearFilter: filterBarFunc.current?.clearFilter(); return;
       case BUILDIN_COMMAND_TYPE.setFilterStrMatch: filterBarFunc.current?.setType(`StrMatch`); return;
       case BUILDIN_COMMAND_TYPE.setFilterRegExp: filterBarFunc.current?.setType(`RegExpr`); return;
+      case BUILDIN_COMMAND_TYPE.setFilterRegExpCaptures: filterBarFunc.current?.setType(`RegExprCaptures`); return;
+      case BUILDIN_COMMAND_TYPE.setFilterGlob: filterBarFunc.current?.setType(`Glob`); return;
+      case BUILDIN_COMMAND_TYPE.setFilterMigemo: filterBarFunc.current?.setType(`Migemo`); return;
+      case BUILDIN_COMMAND_TYPE.setFilterQuery: filterBarFunc.current?.setType(`Query`); return;
+      case BUILDIN_COMMAND_TYPE.setFilterGrep: filterBarFunc.current?.setType(`Grep`); return;
+      case BUILDIN_COMMAND_TYPE.applyRecentFilter: filterBarFunc.current?.applyRecentFilter(); return;
+      case BUILDIN_COMMAND_TYPE.saveFilter: filterBarFunc.current?.saveFilter(); return;
+      case BUILDIN_COMMAND_TYPE.selectSavedFilter: filterBarFunc.current?.selectSavedFilter(); return;
+      case BUILDIN_COMMAND_TYPE.cycleFilterRetention: cycleFilterRetention(); return;
+      case BUILDIN_COMMAND_TYPE.toggleRecursiveListing: toggleRecursiveListing(); return;
+      case BUILDIN_COMMAND_TYPE.goToAnywhere: goToAnywhere(); return;
+      case BUILDIN_COMMAND_TYPE.toggleFileIndexRoot: toggleFileIndexRoot(); return;
+      case BUILDIN_COMMAND_TYPE.copyToOppositeDir: transferSelection('copy_items'); return;
+      case BUILDIN_COMMAND_TYPE.moveToOppositeDir: transferSelection('move_items'); return;
+      case BUILDIN_COMMAND_TYPE.deleteSelection: removeSelection('delete_items'); return;
+      case BUILDIN_COMMAND_TYPE.trashSelection: removeSelection('trash_items'); return;
+      case BUILDIN_COMMAND_TYPE.renameSelection: renameSelection(); return;
+      case BUILDIN_COMMAND_TYPE.newFile: createItems(false); return;
+      case BUILDIN_COMMAND_TYPE.newFolder: createItems(true); return;
+      case BUILDIN_COMMAND_TYPE.undoFileOperation: applyFileJournal('undo_file_operation'); return;
+      case BUILDIN_COMMAND_TYPE.redoFileOperation: applyFileJournal('redo_file_operation'); return;
       case BUILDIN_COMMAND_TYPE.focusOppositePane: props.focusOppositePane(); return;
       case BUILDIN_COMMAND_TYPE.focusCommandBar: focusCommandBar(); return;
       case BUILDIN_COMMAND_TYPE.setKeyBind: props.setKeyBind(srcKey); return;
@@ -252,7 +468,7 @@ export const MainPanel = (
     }
   }
 
-  const execCommand = (
+  const execCommand = async (
     command: KeyBindSetting,
     srcKey: React.KeyboardEvent<HTMLDivElement> | null
   ) => {
@@ -266,7 +482,7 @@ export const MainPanel = (
       commandExecuterFunc.current?.execShellCommand(
         command.action.command_name,
         props.dirPath,
-        FileListFunctions.current?.selectingItemName() ?? [],
+        await FileListFunctions.current?.selectingItemName() ?? [],
         props.getOppositePath(),
         props.separator);
       return
@@ -317,6 +533,49 @@ export const MainPanel = (
     file_name: string,
   };
 
+  type HistoryNavigationResult = {
+    dirctry_path: string,
+    filter: { filter_type: FileFilterType, matcher_str: string },
+    file_list_info: FileListUiInfo | null,
+  };
+
+  type NavigationHistory = {
+    back: { dirctry_path: string }[],
+    forward: { dirctry_path: string }[],
+  };
+
+  const navigateHistory = async (command: 'go_back' | 'go_forward') => {
+    if (paneIdx === null) { return; }
+    // 開けなくても移動はするので、移動先を先に調べておく。
+    const history = await invoke<NavigationHistory>('get_navigation_history', { paneIdx: paneIdx });
+    const entries = (command === 'go_back') ? history.back : history.forward;
+    const target = entries[entries.length - 1];
+    if (!target) { return; }
+
+    const result = await invoke<HistoryNavigationResult | null>(command, { paneIdx: paneIdx })
+      .catch(error => {
+        props.addLogMessage(AccessErrorLog("Access failed.", error));
+        return undefined;
+      });
+    if (result === null) { return; }
+    if (result === undefined) {
+      if (target.dirctry_path !== props.dirPath) {
+        navigatedDirPath.current = target.dirctry_path;
+      }
+      props.onPathChanged(target.dirctry_path);
+      setFileListInfo(null);
+      syncFilterBar();
+      return;
+    }
+
+    if (result.dirctry_path !== props.dirPath) {
+      navigatedDirPath.current = result.dirctry_path;
+    }
+    props.onPathChanged(result.dirctry_path);
+    filterBarFunc.current?.setFilter(result.filter.filter_type, result.filter.matcher_str);
+    setFileListInfo(result.file_list_info);
+  }
+
   const accessParentDir = async () => {
     const dirName = await basename(props.dirPath).catch(_ => { return null; });
     if (dirName === null) {
@@ -373,10 +632,10 @@ export const MainPanel = (
         contextMenuInfoAry.map((command, idx) => {
           return <MenuItem
             css={menuItemStyle}
-            onClick={_ => commandExecuterFunc.current?.execShellCommand(
+            onClick={async _ => commandExecuterFunc.current?.execShellCommand(
               command.command_name,
               props.dirPath,
-              FileListFunctions.current?.selectingItemName() ?? [],
+              await FileListFunctions.current?.selectingItemName() ?? [],
               props.getOppositePath(),
               props.separator
             )}
@@ -405,6 +664,18 @@ export const MainPanel = (
     </div>
   }
 
+  function RecursiveListingInfo() {
+    const exclude = recursiveListing?.exclude_patterns.join(' ') ?? '';
+    return <div>
+      <input
+        style={readonlyTextInputStyle}
+        type="text"
+        value={`Recursive (depth ${recursiveListing?.max_depth}, exclude: ${exclude})`}
+        readOnly
+      />
+    </div>
+  }
+
   return (
     <>
       <div
@@ -427,7 +698,18 @@ export const MainPanel = (
         />
         <div>
           {linkDestination ? LinkDestination() : null}
+          {recursiveListing ? RecursiveListingInfo() : null}
+          {isGoToAnywhereOpen
+            ? <GoToAnywhereBar
+              onSelected={(dirctryPath, fileName) => AccessDirectory(dirctryPath, fileName)}
+              onEndEdit={() => {
+                setGoToAnywhereOpen(false);
+                myGrid.current?.focus();
+              }}
+            />
+            : null}
           <FileFilterBar
+            retention={filterRetention}
             onFilterChanged={setFilter}
             onEndEdit={() => myGrid.current?.focus()}
             ref={filterBarFunc}
@@ -445,12 +727,12 @@ export const MainPanel = (
             e.preventDefault();
           }}
         >
-          {fileListInfo ?
+          {(fileListInfo && paneIdx !== null) ?
             <AutoSizer>
               {({ height, width }) => {
                 return <FileList
                   isActive={props.isActive}
-                  panel_idx={props.panel_idx}
+                  panel_idx={paneIdx}
                   fileListInfo={fileListInfo}
                   updateFileListInfo={setFileListInfo}
                   accessParentDir={accessParentDir}
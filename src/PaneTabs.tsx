@@ -33,7 +33,6 @@ export interface TabFuncs {
 export const PaneTabs = (
   props: {
     isActive: boolean,
-    panel_idx: number,
     height: number,
     pathAry: TabsInfo,
     tabColorSetting?: TabColorSettings
@@ -242,7 +241,6 @@ export const PaneTabs = (
         </div >
         <MainPanel
           isActive={props.isActive}
-          panel_idx={props.panel_idx}
           dirPath={tabAry[activeTabIdx].path}
           pined={tabAry[activeTabIdx].pined}
           onPathChanged={onPathChanged}
@@ -35,7 +35,7 @@ export async function ReadLastOpenedTabs(): Promise<TabsInfo[]> {
     const initTabs = await invoke<String>("read_setting_file", { fileName: last_opend_setting_file_name });
 
     let result = JSON5.parse(initTabs.toString()) as { version: number, data: TabsInfo[], };
-    if (result.data.length !== 2) {
+    if (result.data.length === 0) {
       return [{ ...defaultTabInfo() }, { ...defaultTabInfo() }];
     }
 
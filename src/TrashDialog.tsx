@@ -0,0 +1,139 @@
+import { forwardRef, useImperativeHandle, useRef, useState } from 'react';
+import { invoke } from '@tauri-apps/api/core';
+import React from 'react';
+
+/** @jsxImportSource @emotion/react */
+import { css } from '@emotion/react'
+import { LogInfo } from './LogMessagePane';
+import { AccessErrorLog } from './AccessError';
+import { ButtonStyle, useTheme } from './ThemeStyle';
+
+///////////////////////////////////////////////////////////////////////////////////////////////////
+type TrashItemInfo = {
+  id: string,
+  name: string,
+  original_path: string,
+  deletion_date: string,
+  is_dir: boolean,
+};
+
+export interface TrashDialogFunc {
+  open: () => void;
+}
+
+type TrashDialogProps = {
+  addLogMessage: (message: LogInfo) => void,
+};
+
+// ゴミ箱の中身を一覧し、選んだ物を元の場所に戻す。
+export const TrashDialog = forwardRef<TrashDialogFunc, TrashDialogProps>((props, ref) => {
+  useImperativeHandle(ref, () => functions);
+
+  const dlg: React.MutableRefObject<HTMLDialogElement | null> = useRef(null);
+  const theme = useTheme();
+  const buttonstyle = ButtonStyle(theme.baseColor);
+
+  const [itemAry, setItemAry] = useState<TrashItemInfo[]>([]);
+  const [selectingIdAry, setSelectingIdAry] = useState<string[]>([]);
+
+  const reload = async () => {
+    const items = await invoke<TrashItemInfo[]>('list_trash_items', {}).catch(() => []);
+    setItemAry(items);
+    setSelectingIdAry(ary => ary.filter(id => items.some(item => item.id === id)));
+  }
+
+  const toggleSelection = (id: string) => {
+    setSelectingIdAry(ary => ary.includes(id) ? ary.filter(item => item !== id) : [...ary, id]);
+  }
+
+  const restore = async () => {
+    await invoke<void>('restore_trash_items', { ids: selectingIdAry })
+      .catch(error => props.addLogMessage(AccessErrorLog('Restore failed.', error)));
+    setSelectingIdAry([]);
+    reload();
+  }
+
+  const emptyTrash = async () => {
+    await invoke<void>('empty_trash', {})
+      .catch(error => props.addLogMessage(AccessErrorLog('Empty trash failed.', error)));
+    reload();
+  }
+
+  const functions = {
+    open: () => {
+      setSelectingIdAry([]);
+      reload();
+      dlg.current?.showModal();
+    },
+  };
+
+  return <dialog
+    ref={dlg}
+    css={css({
+      background: theme.baseColor.backgroundColor,
+      color: theme.baseColor.stringDefaultColor,
+      minWidth: '60%',
+    })}
+  >
+    <div
+      css={css({
+        display: 'grid',
+        gridTemplateRows: '1fr auto',
+      })}
+    >
+      <table
+        css={css({
+          borderCollapse: 'collapse',
+          maxHeight: '60vh',
+          overflow: 'auto',
+          display: 'block',
+        })}
+      >
+        <tbody>
+          {itemAry.map(item => {
+            const isSelecting = selectingIdAry.includes(item.id);
+            return <tr
+              key={item.id}
+              css={css({
+                background: isSelecting ? theme.baseColor.stringDefaultColor : theme.baseColor.backgroundColor,
+                color: isSelecting ? theme.baseColor.backgroundColor : theme.baseColor.stringDefaultColor,
+              })}
+              onClick={() => toggleSelection(item.id)}
+            >
+              <td>{item.name + (item.is_dir ? '/' : '')}</td>
+              <td>{item.original_path}</td>
+              <td>{item.deletion_date}</td>
+            </tr>
+          })}
+        </tbody>
+      </table>
+      <div
+        css={css({
+          marginLeft: 'auto',
+          marginRight: 'auto',
+        })}
+      >
+        <button
+          css={buttonstyle}
+          disabled={selectingIdAry.length === 0}
+          onClick={restore}
+        >
+          Restore
+        </button>
+        <button
+          css={buttonstyle}
+          disabled={itemAry.length === 0}
+          onClick={emptyTrash}
+        >
+          Empty trash
+        </button>
+        <button
+          css={buttonstyle}
+          onClick={() => { dlg.current?.close() }}
+        >
+          Close
+        </button>
+      </div>
+    </div>
+  </dialog>
+});